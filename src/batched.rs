@@ -1,22 +1,39 @@
-use js_sys::Float32Array;
-use std::cell::RefCell;
+use js_sys::{Float32Array, JSON, Reflect, Uint32Array};
+use serde::Serialize;
+use std::cell::{Cell, RefCell, RefMut};
 use std::rc::Rc;
-use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsValue;
-use web_sys::{WebGl2RenderingContext as Gl, WebGlProgram, WebGlUniformLocation};
+use wasm_bindgen::prelude::*;
+use web_sys::{
+    OffscreenCanvas, WebGl2RenderingContext as Gl, WebGlProgram, WebGlTexture, WebGlUniformLocation,
+};
 
 use crate::batcher::{
-    Mesh, COLOR_COMPONENTS, MATRIX_FLOATS, MESH_VERTEX_STRIDE, POSITION_COMPONENTS,
+    BILLBOARD_FLOATS, COLOR_COMPONENTS, DEFAULT_INSTANCE_PARAMS, InstanceFormat, MATRIX_FLOATS,
+    MAX_EXTRA_COLUMNS, MAX_PICK_HANDLE, MESH_VERTEX_STRIDE, Mesh, PARAM_COLOR, PARAM_DEPTH_BIAS,
+    PARAM_FLAT, PARAM_PICK_ID, POSITION_COMPONENTS, SPRITE_FLOATS, billboard_matrix,
+    decode_pick_id, instance_origin, instance_sphere, merge_meshes, sprite_matrix,
+    vertex_attributes,
+};
+use crate::camera::{
+    UpAxis, fit_distance, frustum_planes, multiply, orbit_view_matrix, perspective_depth_range,
+    perspective_matrix, screen_bounds, sphere_in_frustum, transformed_bounds,
 };
-use crate::context::{shared_context, SharedContext};
-use crate::gpu::{GlBuffer, VertexArray};
+use crate::color;
+use crate::context::{SharedContext, Viewport, shared_context, shared_context_offscreen};
+use crate::gpu::{GlBuffer, Primitive, RenderTarget, VertexArray, query_size_range};
+use crate::handles::{InstanceHandle, MeshHandle};
 use crate::instances::InstanceStore;
-use crate::mesh_instances::MeshInstances;
+use crate::mesh_instances::{DEFAULT_GROWTH_FACTOR, GROWTH_FACTOR_RANGE, MeshInstances};
+use crate::mesh_library::MeshLibrary;
+use crate::scene::{SCENE_VERSION, SceneDocument, SceneMesh};
 use crate::shader::{
-    compile_shader, fragment_shader_source, link_program, vertex_shader_source,
+    compile_shader, fragment_shader_source, link_program, shader_precision, vertex_shader_source,
+    with_precision,
 };
+use crate::snapshot::{InstanceSnapshot, RendererSnapshot};
 use crate::utils::{
-    array_to_vec, clamp_unit, copy_into_matrix, error, identity_matrix, matrix_from_array,
+    MatrixLayout, array_to_vec, clamp_unit, error, identity_matrix, log, matrix_from_array,
 };
 
 #[wasm_bindgen]
@@ -32,8 +49,185 @@ impl BatchedRenderer {
         BatchedRenderer::with_shared_context(context)
     }
 
+    pub fn from_offscreen(canvas: OffscreenCanvas) -> Result<BatchedRenderer, JsValue> {
+        let context = shared_context_offscreen(canvas)?;
+        BatchedRenderer::with_shared_context(context)
+    }
+
     pub fn register_mesh(&self, vertices: &Float32Array) -> Result<u32, JsValue> {
-        self.inner.borrow_mut().register_mesh(vertices)
+        self.inner_mut()
+            .register_mesh(vertices, InstanceFormat::Matrix, MeshOptions::default())
+    }
+
+    // options: { depthTest?: boolean (default true), extraColumns?: 0..=4,
+    //            dynamic?: boolean (default false, see update_mesh_vertices),
+    //            interpolated?: boolean (default false, see set_instance_transform_pair),
+    //            primitive?: Primitive (default Triangles) }
+    pub fn register_mesh_with_options(
+        &self,
+        vertices: &Float32Array,
+        options: &JsValue,
+    ) -> Result<u32, JsValue> {
+        let options = mesh_options(options)?;
+        self.inner_mut()
+            .register_mesh(vertices, InstanceFormat::Matrix, options)
+    }
+
+    pub fn register_mesh_billboard(&self, vertices: &Float32Array) -> Result<u32, JsValue> {
+        self.inner_mut()
+            .register_mesh(vertices, InstanceFormat::Billboard, MeshOptions::default())
+    }
+
+    pub fn register_mesh_sprite(&self, vertices: &Float32Array) -> Result<u32, JsValue> {
+        self.inner_mut()
+            .register_mesh(vertices, InstanceFormat::Sprite, MeshOptions::default())
+    }
+
+    pub fn register_from_library(&self, library: &MeshLibrary, id: u32) -> Result<u32, JsValue> {
+        let mesh = library.get(id)?;
+        self.inner_mut()
+            .upload_mesh(mesh, InstanceFormat::Matrix, MeshOptions::default())
+    }
+
+    pub fn reset_meshes(&self) {
+        self.inner_mut().reset_meshes();
+    }
+
+    pub fn remove_mesh(&self, mesh_handle: u32) -> Result<(), JsValue> {
+        self.inner_mut()
+            .remove_mesh(MeshHandle::from_js(mesh_handle)?)
+    }
+
+    pub fn set_retain_mesh_data(&self, enabled: bool) {
+        self.inner_mut().retain_mesh_data = enabled;
+    }
+
+    pub fn mesh_vertices(&self, mesh_handle: u32) -> Result<Float32Array, JsValue> {
+        let inner = self.inner.borrow();
+        let mesh = inner.retained_mesh(MeshHandle::from_js(mesh_handle)?)?;
+        Ok(Float32Array::from(mesh.raw()))
+    }
+
+    // Bakes retained meshes through one transform each (16 floats per handle,
+    // in the renderer's matrix layout) into a single new matrix mesh.
+    pub fn merge_meshes(
+        &self,
+        handles: &Uint32Array,
+        transforms: &Float32Array,
+    ) -> Result<u32, JsValue> {
+        let handles = handles.to_vec();
+        if transforms.length() as usize != handles.len() * MATRIX_FLOATS {
+            return Err(error(
+                "merge_meshes needs 16 transform floats per mesh handle",
+            ));
+        }
+        let mut inner = self.inner_mut();
+        let merged = {
+            let mut parts = Vec::with_capacity(handles.len());
+            for (index, &handle) in handles.iter().enumerate() {
+                let start = (index * MATRIX_FLOATS) as u32;
+                let matrix = transforms.subarray(start, start + MATRIX_FLOATS as u32);
+                let transform = matrix_from_array(&matrix, inner.matrix_layout)?;
+                parts.push((
+                    inner.retained_mesh(MeshHandle::from_js(handle)?)?,
+                    transform,
+                ));
+            }
+            merge_meshes(&parts).map_err(error)?
+        };
+        inner.upload_mesh(&merged, InstanceFormat::Matrix, MeshOptions::default())
+    }
+
+    // Rewrites a dynamic mesh's vertices in place; the vertex count is fixed
+    // at registration.
+    pub fn update_mesh_vertices(
+        &self,
+        mesh_handle: u32,
+        vertices: &Float32Array,
+    ) -> Result<(), JsValue> {
+        self.inner_mut()
+            .update_mesh_vertices(MeshHandle::from_js(mesh_handle)?, vertices)
+    }
+
+    // Debugging aid: the next render pass that draws this mesh uses
+    // `primitive` instead of triangles, then the mesh reverts.
+    pub fn draw_mesh_as(&self, mesh_handle: u32, primitive: Primitive) -> Result<(), JsValue> {
+        self.inner_mut()
+            .draw_mesh_as(MeshHandle::from_js(mesh_handle)?, primitive)
+    }
+
+    pub fn set_mesh_layer(&self, mesh_handle: u32, layer: u32) -> Result<(), JsValue> {
+        self.inner_mut()
+            .set_mesh_layer(MeshHandle::from_js(mesh_handle)?, layer)
+    }
+
+    pub fn set_mesh_opaque(&self, mesh_handle: u32, opaque: bool) -> Result<(), JsValue> {
+        self.inner_mut()
+            .set_mesh_opaque(MeshHandle::from_js(mesh_handle)?, opaque)
+    }
+
+    // How much a mesh's instance buffer grows when it fills up; clamped to
+    // [1.1, 2.0]. Lower values trade more reallocations for less slack.
+    pub fn set_instance_growth_factor(&self, factor: f32) -> Result<(), JsValue> {
+        if !factor.is_finite() {
+            return Err(error("instance growth factor must be finite"));
+        }
+        let factor = factor.clamp(GROWTH_FACTOR_RANGE[0], GROWTH_FACTOR_RANGE[1]);
+        let mut inner = self.inner_mut();
+        inner.instance_growth_factor = factor;
+        for instances in inner.mesh_instances.iter_mut().flatten() {
+            instances.set_growth_factor(factor);
+        }
+        Ok(())
+    }
+
+    pub fn set_auto_draw_sort(&self, enabled: bool) {
+        self.inner_mut().auto_draw_sort = enabled;
+    }
+
+    // Two-phase transparency: opaque meshes first with blending off, then
+    // the rest back to front with depth writes off.
+    pub fn set_sorted_transparency(&self, enabled: bool) {
+        self.inner_mut().sorted_transparency = enabled;
+    }
+
+    // Resolves non-opaque meshes through multisample coverage instead of
+    // blending, so they need no back-to-front order. Only useful with an MSAA
+    // context and cutout-style alpha.
+    pub fn set_alpha_to_coverage(&self, enabled: bool) -> Result<(), JsValue> {
+        let mut inner = self.inner_mut();
+        if enabled && get_i32_parameter(&inner.gl, Gl::SAMPLES)? == 0 {
+            log("alpha to coverage has no effect without a multisampled context");
+        }
+        inner.alpha_to_coverage = enabled;
+        Ok(())
+    }
+
+    // Back to the defaults `pipeline_state` reports, applied to GL right away:
+    // full viewport, no alpha to coverage, no pending draw_mesh_as overrides.
+    pub fn reset_pipeline_state(&self) {
+        self.inner_mut().reset_pipeline_state();
+    }
+
+    // Counts the registered primitive, so line and point meshes report 0.
+    pub fn mesh_triangle_count(&self, mesh_handle: u32) -> Result<u32, JsValue> {
+        self.inner
+            .borrow()
+            .mesh_triangle_count(MeshHandle::from_js(mesh_handle)?)
+    }
+
+    pub fn mesh_instance_count(&self, mesh_handle: u32) -> Result<u32, JsValue> {
+        let inner = self.inner.borrow();
+        Ok(inner
+            .instances_for(MeshHandle::from_js(mesh_handle)?)?
+            .len() as u32)
+    }
+
+    pub fn mesh_instance_capacity(&self, mesh_handle: u32) -> Result<u32, JsValue> {
+        let inner = self.inner.borrow();
+        Ok(inner
+            .instances_for(MeshHandle::from_js(mesh_handle)?)?
+            .capacity() as u32)
     }
 
     pub fn create_instance(
@@ -41,7 +235,8 @@ impl BatchedRenderer {
         mesh_handle: u32,
         transform: &Float32Array,
     ) -> Result<u32, JsValue> {
-        self.inner.borrow_mut().create_instance(mesh_handle, transform)
+        self.inner_mut()
+            .create_instance(MeshHandle::from_js(mesh_handle)?, transform)
     }
 
     pub fn set_instance_transform(
@@ -49,13 +244,130 @@ impl BatchedRenderer {
         instance_handle: u32,
         transform: &Float32Array,
     ) -> Result<(), JsValue> {
-        self.inner
-            .borrow_mut()
-            .set_instance_transform(instance_handle, transform)
+        self.inner_mut()
+            .set_instance_transform(InstanceHandle::from_js(instance_handle)?, transform)
+    }
+
+    // For fixed-timestep simulations on interpolated meshes: the vertex
+    // shader blends from `previous` to `next` by the interpolation alpha.
+    pub fn set_instance_transform_pair(
+        &self,
+        instance_handle: u32,
+        previous: &Float32Array,
+        next: &Float32Array,
+    ) -> Result<(), JsValue> {
+        self.inner_mut().set_instance_transform_pair(
+            InstanceHandle::from_js(instance_handle)?,
+            previous,
+            next,
+        )
+    }
+
+    pub fn set_interpolation_alpha(&self, alpha: f32) -> Result<(), JsValue> {
+        self.inner_mut().set_interpolation_alpha(alpha)
+    }
+
+    pub fn create_billboard(
+        &self,
+        mesh_handle: u32,
+        x: f32,
+        y: f32,
+        z: f32,
+        scale: f32,
+    ) -> Result<u32, JsValue> {
+        self.inner_mut()
+            .create_billboard(MeshHandle::from_js(mesh_handle)?, [x, y, z, scale])
+    }
+
+    pub fn set_billboard(
+        &self,
+        instance_handle: u32,
+        x: f32,
+        y: f32,
+        z: f32,
+        scale: f32,
+    ) -> Result<(), JsValue> {
+        self.inner_mut()
+            .set_billboard(InstanceHandle::from_js(instance_handle)?, [x, y, z, scale])
+    }
+
+    pub fn create_sprite(
+        &self,
+        mesh_handle: u32,
+        x: f32,
+        y: f32,
+        rotation: f32,
+        scale: f32,
+    ) -> Result<u32, JsValue> {
+        self.inner_mut()
+            .create_sprite(MeshHandle::from_js(mesh_handle)?, [x, y, rotation, scale])
+    }
+
+    pub fn set_sprite(
+        &self,
+        instance_handle: u32,
+        x: f32,
+        y: f32,
+        rotation: f32,
+        scale: f32,
+    ) -> Result<(), JsValue> {
+        self.inner_mut().set_sprite(
+            InstanceHandle::from_js(instance_handle)?,
+            [x, y, rotation, scale],
+        )
+    }
+
+    pub fn set_instance_visible(&self, instance_handle: u32, visible: bool) -> Result<(), JsValue> {
+        self.inner_mut()
+            .set_instance_visible(InstanceHandle::from_js(instance_handle)?, visible)
+    }
+
+    pub fn set_instance_extra(
+        &self,
+        instance_handle: u32,
+        values: &Float32Array,
+    ) -> Result<(), JsValue> {
+        let values = array_to_vec(values);
+        self.inner_mut()
+            .set_instance_extra(InstanceHandle::from_js(instance_handle)?, &values)
+    }
+
+    pub fn set_instance_depth_bias(&self, instance_handle: u32, bias: f32) -> Result<(), JsValue> {
+        self.inner_mut()
+            .set_instance_depth_bias(InstanceHandle::from_js(instance_handle)?, bias)
+    }
+
+    // Multiplies the mesh's vertex colors, or replaces them for flat instances.
+    pub fn set_instance_color(
+        &self,
+        instance_handle: u32,
+        r: f32,
+        g: f32,
+        b: f32,
+        a: f32,
+    ) -> Result<(), JsValue> {
+        self.inner_mut()
+            .set_instance_color(InstanceHandle::from_js(instance_handle)?, [r, g, b, a])
+    }
+
+    pub fn set_instance_flat(&self, instance_handle: u32, flat: bool) -> Result<(), JsValue> {
+        let flag = if flat { 1.0 } else { 0.0 };
+        self.inner_mut().set_instance_param(
+            InstanceHandle::from_js(instance_handle)?,
+            PARAM_FLAT,
+            &[flag],
+        )
     }
 
     pub fn remove_instance(&self, instance_handle: u32) -> Result<(), JsValue> {
-        self.inner.borrow_mut().remove_instance(instance_handle)
+        self.inner_mut()
+            .remove_instance(InstanceHandle::from_js(instance_handle)?)
+    }
+
+    pub fn instance_mesh(&self, instance_handle: u32) -> Result<u32, JsValue> {
+        self.inner
+            .borrow()
+            .instance_mesh(InstanceHandle::from_js(instance_handle)?)
     }
 
     pub fn queue_instance(
@@ -63,27 +375,94 @@ impl BatchedRenderer {
         mesh_handle: u32,
         transform: &Float32Array,
     ) -> Result<(), JsValue> {
-        self.inner
-            .borrow_mut()
-            .queue_instance(mesh_handle, transform)
+        self.inner_mut()
+            .queue_instance(MeshHandle::from_js(mesh_handle)?, transform)
+    }
+
+    // Hard ceiling on live instances across all meshes; `undefined` lifts
+    // it. Over budget, creating throws and queueing drops the instance.
+    pub fn set_max_total_instances(&self, max: Option<u32>) {
+        self.inner_mut().max_total_instances = max;
+    }
+
+    pub fn instances_dropped_last_frame(&self) -> u32 {
+        self.inner.borrow().frame_stats.transient_dropped
+    }
+
+    pub fn queue_sticky_instance(
+        &self,
+        mesh_handle: u32,
+        transform: &Float32Array,
+    ) -> Result<(), JsValue> {
+        self.inner_mut()
+            .queue_sticky_instance(MeshHandle::from_js(mesh_handle)?, transform)
+    }
+
+    pub fn clear_sticky(&self) {
+        self.inner_mut().clear_sticky();
     }
 
     pub fn flush(&self) -> Result<(), JsValue> {
         self.inner.borrow_mut().render_pass()
     }
 
+    // Draws only the meshes tagged with `layer`, e.g. between other passes
+    // that share this renderer's instances.
+    pub fn render_layer(&self, layer: u32) -> Result<(), JsValue> {
+        self.inner.borrow_mut().render_layer(layer)
+    }
+
+    pub fn begin_frame(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.clear(r, g, b, a);
+    }
+
+    pub fn end_frame(&self) -> Result<(), JsValue> {
+        let mut inner = self.inner.borrow_mut();
+        inner.render_pass()?;
+        inner.gl.flush();
+        Ok(())
+    }
+
+    pub fn set_billboard_facing(&self, enabled: bool) {
+        self.inner_mut().set_billboard_facing(enabled);
+    }
+
+    pub fn set_matrix_layout(&self, layout: MatrixLayout) {
+        self.inner_mut().matrix_layout = layout;
+    }
+
     pub fn set_view_matrix(&self, matrix: &Float32Array) -> Result<(), JsValue> {
-        self.inner.borrow_mut().set_view_matrix(matrix)
+        self.inner_mut().set_view_matrix(matrix)
     }
 
     pub fn set_projection_matrix(&self, matrix: &Float32Array) -> Result<(), JsValue> {
-        self.inner.borrow_mut().set_projection_matrix(matrix)
+        self.inner_mut().set_projection_matrix(matrix)
+    }
+
+    // Re-uploads the stored view/projection right now, e.g. before a pick or
+    // readback that does not go through a render pass.
+    pub fn commit_camera(&self) -> Result<(), JsValue> {
+        self.inner.borrow().commit_camera()
     }
 
     pub fn clear(&self, r: f32, g: f32, b: f32, a: f32) {
         let color = [clamp_unit(r), clamp_unit(g), clamp_unit(b), clamp_unit(a)];
-        let context = self.context_handle();
-        context.clear(color, Some(1.0));
+        self.inner.borrow().clear(color);
+    }
+
+    pub fn clear_hex(&self, hex: &str) -> Result<(), JsValue> {
+        let color = color::parse_hex(hex).map_err(error)?;
+        self.inner.borrow().clear(color);
+        Ok(())
+    }
+
+    // Depth written by `clear`/`clear_hex`; 0.0 for reversed-Z projections.
+    pub fn set_clear_depth(&self, depth: f32) -> Result<(), JsValue> {
+        if !depth.is_finite() {
+            return Err(error("clear depth must be finite"));
+        }
+        self.inner_mut().clear_depth = depth.clamp(0.0, 1.0);
+        Ok(())
     }
 
     pub fn resize(&self, width: u32, height: u32) {
@@ -91,6 +470,44 @@ impl BatchedRenderer {
         context.resize(width, height);
     }
 
+    pub fn resize_with_perspective(
+        &self,
+        width: u32,
+        height: u32,
+        fov_y_radians: f32,
+        near: f32,
+        far: f32,
+    ) -> Result<(), JsValue> {
+        let context = self.context_handle();
+        context.resize(width, height);
+        let projection =
+            perspective_matrix(fov_y_radians, context.aspect_ratio(), near, far).map_err(error)?;
+        self.inner_mut().set_projection(projection);
+        Ok(())
+    }
+
+    pub fn set_viewport(&self, x: u32, y: u32, width: u32, height: u32) -> Result<(), JsValue> {
+        let viewport = Viewport::new(x, y, width, height).map_err(error)?;
+        self.inner_mut().viewport = Some(viewport);
+        Ok(())
+    }
+
+    pub fn clear_viewport(&self) {
+        self.inner_mut().viewport = None;
+    }
+
+    pub fn set_max_dimension(&self, max: u32) -> Result<(), JsValue> {
+        self.context_handle().set_max_dimension(max)
+    }
+
+    pub fn is_usable(&self) -> bool {
+        self.context_handle().is_usable()
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.context_handle().aspect_ratio()
+    }
+
     pub fn max_instances(&self) -> u32 {
         self.inner.borrow().max_instances()
     }
@@ -99,12 +516,149 @@ impl BatchedRenderer {
         self.inner.borrow().instance_count()
     }
 
+    pub fn visible_instance_count(&self) -> u32 {
+        self.inner.borrow().visible_instance_count()
+    }
+
+    pub fn world_bounds(&self) -> Result<Float32Array, JsValue> {
+        let (min, max) = self
+            .inner
+            .borrow()
+            .world_bounds()
+            .ok_or_else(|| error("no instances to bound"))?;
+        Ok(Float32Array::from(&[min, max].concat()[..]))
+    }
+
+    pub fn frame_all(
+        &self,
+        fov_y_radians: f32,
+        near: f32,
+        far: f32,
+        up: Option<UpAxis>,
+    ) -> Result<Float32Array, JsValue> {
+        let up = up.unwrap_or(UpAxis::YUp);
+        let (distance, target) = self.inner_mut().frame_all(fov_y_radians, near, far, up)?;
+        Ok(Float32Array::from(
+            &[distance, target[0], target[1], target[2]][..],
+        ))
+    }
+
+    pub fn instance_screen_bounds(&self, instance_handle: u32) -> Result<Float32Array, JsValue> {
+        let rect = self
+            .inner
+            .borrow()
+            .instance_screen_bounds(InstanceHandle::from_js(instance_handle)?)?;
+        Ok(Float32Array::from(&rect[..]))
+    }
+
+    // Everything a stats overlay needs in one boundary crossing:
+    // { meshes, instances, queued, drawCallsLastFrame, trianglesLastFrame }.
+    pub fn scene_summary(&self) -> Result<JsValue, JsValue> {
+        let summary = self.inner.borrow().scene_summary();
+        serde_wasm_bindgen::to_value(&summary).map_err(JsValue::from)
+    }
+
+    // { maxVertexAttribs, used }: the device's vertex attribute limit and
+    // the most any registered mesh binds. Registration refuses layouts over it.
+    pub fn attribute_budget(&self) -> Result<JsValue, JsValue> {
+        let budget = self.inner.borrow().attribute_budget();
+        serde_wasm_bindgen::to_value(&budget).map_err(JsValue::from)
+    }
+
+    // The GL state a render pass leaves behind, so third-party passes can
+    // save and restore around it:
+    // { depthTest, depthWrite, cullMode, blendMode, blendEquation }.
+    pub fn pipeline_state(&self) -> Result<JsValue, JsValue> {
+        let state = self.inner.borrow().pipeline_state();
+        serde_wasm_bindgen::to_value(&state).map_err(JsValue::from)
+    }
+
     pub fn queued_instances(&self) -> u32 {
         self.inner.borrow().queued_instances()
     }
 
+    pub fn last_transient_drawn(&self) -> u32 {
+        self.inner.borrow().last_transient_drawn()
+    }
+
+    pub fn sticky_instances(&self) -> u32 {
+        self.inner.borrow().sticky_instances.len() as u32
+    }
+
     pub fn defragment_instances(&self) {
-        self.inner.borrow_mut().defragment_instances();
+        self.inner_mut().defragment_instances();
+    }
+
+    // Portable JSON of the whole scene: meshes (retained with
+    // set_retain_mesh_data), persistent instances, camera and clear color.
+    pub fn to_json(&self) -> Result<String, JsValue> {
+        let document = self.inner.borrow().scene_document()?;
+        let value = serde_wasm_bindgen::to_value(&document).map_err(JsValue::from)?;
+        JSON::stringify(&value)?
+            .as_string()
+            .ok_or_else(|| error("failed to stringify the scene"))
+    }
+
+    // Rebuilds a `to_json` dump on a new renderer, keeping every handle.
+    pub fn from_json(canvas_id: &str, json: &str) -> Result<BatchedRenderer, JsValue> {
+        let value = JSON::parse(json).map_err(|_| error("scene JSON is malformed"))?;
+        let document: SceneDocument = serde_wasm_bindgen::from_value(value)
+            .map_err(|err| error(&format!("invalid scene document: {err}")))?;
+        document.validate().map_err(error)?;
+        let renderer = BatchedRenderer::new(canvas_id)?;
+        renderer.inner_mut().load_scene(document)?;
+        Ok(renderer)
+    }
+
+    pub fn snapshot(&self) -> Result<JsValue, JsValue> {
+        let snapshot = self.inner.borrow().snapshot();
+        serde_wasm_bindgen::to_value(&snapshot).map_err(JsValue::from)
+    }
+
+    pub fn restore(&self, snapshot: JsValue) -> Result<(), JsValue> {
+        let snapshot: RendererSnapshot = serde_wasm_bindgen::from_value(snapshot)
+            .map_err(|err| error(&format!("invalid renderer snapshot: {err}")))?;
+        self.inner_mut().restore(snapshot)
+    }
+
+    pub fn gl_error(&self) -> u32 {
+        self.inner.borrow().gl.get_error()
+    }
+
+    pub fn enable_depth_prepass(&self, enabled: bool) {
+        self.inner_mut().depth_prepass = enabled;
+    }
+
+    // Skips instances whose bounding sphere lies outside the view frustum.
+    // Interpolated meshes are never culled.
+    pub fn set_culling_enabled(&self, enabled: bool) {
+        self.inner_mut().set_culling_enabled(enabled);
+    }
+
+    pub fn culled_instance_count(&self) -> u32 {
+        self.inner.borrow().frame_stats.culled
+    }
+
+    pub fn point_size_limits(&self) -> Float32Array {
+        Float32Array::from(self.inner.borrow().point_size_limits.as_slice())
+    }
+
+    pub fn set_point_size(&self, size: f32) -> Result<(), JsValue> {
+        self.inner_mut().set_point_size(size)
+    }
+
+    pub fn debug_depth_texture(&self) -> Result<WebGlTexture, JsValue> {
+        self.inner.borrow_mut().debug_depth_texture()
+    }
+
+    // Handle of the instance drawn at drawing-buffer pixel (x, y), top-left
+    // origin; `undefined` over the background.
+    pub fn pick(&self, x: u32, y: u32) -> Result<Option<u32>, JsValue> {
+        self.inner.borrow_mut().pick(x, y)
+    }
+
+    pub fn check_gl_errors(&self, enabled: bool) {
+        self.inner.borrow_mut().check_gl_errors = enabled;
     }
 }
 
@@ -120,9 +674,16 @@ impl BatchedRenderer {
         self.inner.clone()
     }
 
-    fn context_handle(&self) -> SharedContext {
+    pub(crate) fn context_handle(&self) -> SharedContext {
         self.inner.borrow().context.clone()
     }
+
+    // Borrow for JS calls that change what the next frame shows.
+    fn inner_mut(&self) -> RefMut<'_, BatchedRendererInner> {
+        let mut inner = self.inner.borrow_mut();
+        inner.dirty = true;
+        inner
+    }
 }
 
 pub(crate) struct BatchedRendererInner {
@@ -132,15 +693,55 @@ pub(crate) struct BatchedRendererInner {
     position_location: u32,
     color_location: u32,
     instance_locations: [u32; 4],
+    extra_locations: [Option<u32>; MAX_EXTRA_COLUMNS],
+    params_location: u32,
+    instance_color_location: u32,
+    previous_locations: [u32; MATRIX_FLOATS / 4],
     view_location: WebGlUniformLocation,
     projection_location: WebGlUniformLocation,
-    meshes: Vec<GpuMesh>,
-    mesh_instances: Vec<MeshInstances>,
+    instance_format_location: WebGlUniformLocation,
+    billboard_facing_location: WebGlUniformLocation,
+    point_size_location: WebGlUniformLocation,
+    interpolate_location: WebGlUniformLocation,
+    interpolation_alpha_location: WebGlUniformLocation,
+    depth_debug_location: WebGlUniformLocation,
+    depth_params_location: WebGlUniformLocation,
+    pick_location: WebGlUniformLocation,
+    // Removed meshes leave None behind so the other mesh handles stay valid.
+    meshes: Vec<Option<GpuMesh>>,
+    mesh_instances: Vec<Option<MeshInstances>>,
     instance_store: InstanceStore,
     transient_instances: Vec<u32>,
+    sticky_instances: Vec<u32>,
     view_matrix: [f32; MATRIX_FLOATS],
     projection_matrix: [f32; MATRIX_FLOATS],
     max_instances_per_draw: usize,
+    max_vertex_attribs: usize,
+    check_gl_errors: bool,
+    matrix_layout: MatrixLayout,
+    billboard_facing: bool,
+    depth_prepass: bool,
+    culling: bool,
+    point_size: f32,
+    point_size_limits: [f32; 2],
+    auto_draw_sort: bool,
+    sorted_transparency: bool,
+    alpha_to_coverage: bool, // replaces blending for non-opaque meshes
+    clear_depth: f32,
+    clear_color: Cell<[f32; 4]>, // last color passed to clear, recorded for to_json
+    draw_order: Vec<usize>,
+    retain_mesh_data: bool,
+    depth_debug_target: Option<RenderTarget>,
+    pick_target: Option<RenderTarget>,
+    viewport: Option<Viewport>,
+    instance_growth_factor: f32,
+    frame_stats: FrameStats, // counted during the most recent render pass or composer frame
+    composing: bool,         // inside a composer frame, so each pass adds to frame_stats
+    draw_layer: Option<u32>, // set while render_layer draws a single layer
+    dirty: bool,             // changed since the last render pass
+    max_total_instances: Option<u32>,
+    interpolation_alpha: f32,
+    transients_dropped: u32, // since the last render pass
 }
 
 impl BatchedRendererInner {
@@ -153,9 +754,19 @@ impl BatchedRendererInner {
 
         let uniform_vectors = get_i32_parameter(&gl, Gl::MAX_VERTEX_UNIFORM_VECTORS)?;
         let max_instances_per_draw = compute_instance_budget(uniform_vectors)?;
+        let max_vertex_attribs = get_i32_parameter(&gl, Gl::MAX_VERTEX_ATTRIBS)?.max(0) as usize;
 
-        let vert_shader = compile_shader(&gl, Gl::VERTEX_SHADER, vertex_shader_source())?;
-        let frag_shader = compile_shader(&gl, Gl::FRAGMENT_SHADER, fragment_shader_source())?;
+        let precision = shader_precision();
+        let vert_shader = compile_shader(
+            &gl,
+            Gl::VERTEX_SHADER,
+            &with_precision(vertex_shader_source(), precision),
+        )?;
+        let frag_shader = compile_shader(
+            &gl,
+            Gl::FRAGMENT_SHADER,
+            &with_precision(fragment_shader_source(), precision),
+        )?;
         let program = link_program(&gl, &vert_shader, &frag_shader)?;
 
         let position_location = gl
@@ -180,6 +791,18 @@ impl BatchedRendererInner {
                 .try_into()
                 .map_err(|_| error("a_instance_col3 attribute missing"))?,
         ];
+        let extra_locations = std::array::from_fn(|index| {
+            let name = format!("a_instance_extra{index}");
+            u32::try_from(gl.get_attrib_location(&program, &name)).ok()
+        });
+        let params_location = gl
+            .get_attrib_location(&program, "a_instance_params")
+            .try_into()
+            .map_err(|_| error("a_instance_params attribute missing"))?;
+        let instance_color_location = gl
+            .get_attrib_location(&program, "a_instance_color")
+            .try_into()
+            .map_err(|_| error("a_instance_color attribute missing"))?;
 
         let view_location = gl
             .get_uniform_location(&program, "u_view")
@@ -187,6 +810,38 @@ impl BatchedRendererInner {
         let projection_location = gl
             .get_uniform_location(&program, "u_projection")
             .ok_or_else(|| error("u_projection uniform missing"))?;
+        let instance_format_location = gl
+            .get_uniform_location(&program, "u_instance_format")
+            .ok_or_else(|| error("u_instance_format uniform missing"))?;
+        let billboard_facing_location = gl
+            .get_uniform_location(&program, "u_billboard_facing")
+            .ok_or_else(|| error("u_billboard_facing uniform missing"))?;
+        let point_size_location = gl
+            .get_uniform_location(&program, "u_point_size")
+            .ok_or_else(|| error("u_point_size uniform missing"))?;
+        let point_size_limits = query_size_range(&gl, Gl::ALIASED_POINT_SIZE_RANGE);
+        let interpolate_location = gl
+            .get_uniform_location(&program, "u_interpolate")
+            .ok_or_else(|| error("u_interpolate uniform missing"))?;
+        let interpolation_alpha_location = gl
+            .get_uniform_location(&program, "u_interpolation_alpha")
+            .ok_or_else(|| error("u_interpolation_alpha uniform missing"))?;
+        let mut previous_locations = [0; MATRIX_FLOATS / 4];
+        for (index, location) in previous_locations.iter_mut().enumerate() {
+            *location = gl
+                .get_attrib_location(&program, &format!("a_instance_prev{index}"))
+                .try_into()
+                .map_err(|_| error("a_instance_prev attribute missing"))?;
+        }
+        let depth_debug_location = gl
+            .get_uniform_location(&program, "u_depth_debug")
+            .ok_or_else(|| error("u_depth_debug uniform missing"))?;
+        let depth_params_location = gl
+            .get_uniform_location(&program, "u_depth_params")
+            .ok_or_else(|| error("u_depth_params uniform missing"))?;
+        let pick_location = gl
+            .get_uniform_location(&program, "u_pick")
+            .ok_or_else(|| error("u_pick uniform missing"))?;
 
         let renderer = BatchedRendererInner {
             context,
@@ -195,153 +850,1039 @@ impl BatchedRendererInner {
             position_location,
             color_location,
             instance_locations,
+            extra_locations,
+            params_location,
+            instance_color_location,
+            previous_locations,
             view_location,
             projection_location,
+            instance_format_location,
+            billboard_facing_location,
+            point_size_location,
+            interpolate_location,
+            interpolation_alpha_location,
+            depth_debug_location,
+            depth_params_location,
+            pick_location,
             meshes: Vec::new(),
             mesh_instances: Vec::new(),
             instance_store: InstanceStore::new(),
             transient_instances: Vec::new(),
+            sticky_instances: Vec::new(),
             view_matrix: identity_matrix(),
             projection_matrix: identity_matrix(),
             max_instances_per_draw,
+            max_vertex_attribs,
+            check_gl_errors: false,
+            matrix_layout: MatrixLayout::ColumnMajor,
+            billboard_facing: false,
+            depth_prepass: false,
+            culling: false,
+            point_size: 1.0,
+            point_size_limits,
+            auto_draw_sort: false,
+            sorted_transparency: false,
+            alpha_to_coverage: false,
+            clear_depth: 1.0,
+            clear_color: Cell::new([0.0; 4]),
+            draw_order: Vec::new(),
+            retain_mesh_data: false,
+            depth_debug_target: None,
+            pick_target: None,
+            viewport: None,
+            instance_growth_factor: DEFAULT_GROWTH_FACTOR,
+            frame_stats: FrameStats::default(),
+            composing: false,
+            draw_layer: None,
+            dirty: true,
+            max_total_instances: None,
+            interpolation_alpha: 1.0,
+            transients_dropped: 0,
         };
 
         renderer.gl.use_program(Some(&renderer.program));
-        renderer.upload_view_matrix();
-        renderer.upload_projection_matrix();
+        renderer.bind_globals();
 
         Ok(renderer)
     }
 
+    pub(crate) fn viewport(&self) -> Option<Viewport> {
+        self.viewport
+    }
+
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub(crate) fn render_layer(&mut self, layer: u32) -> Result<(), JsValue> {
+        self.draw_layer = Some(layer);
+        let result = self.render_pass();
+        self.draw_layer = None;
+        result
+    }
+
+    // A composer frame may draw this renderer in several layer passes; the
+    // counters then cover all of them instead of only the last.
+    pub(crate) fn begin_composed_frame(&mut self) {
+        if !self.composing {
+            self.reset_frame_stats();
+            self.composing = true;
+        }
+    }
+
+    pub(crate) fn end_composed_frame(&mut self) {
+        self.composing = false;
+    }
+
+    fn reset_frame_stats(&mut self) {
+        self.frame_stats = FrameStats {
+            transient_dropped: std::mem::take(&mut self.transients_dropped),
+            ..FrameStats::default()
+        };
+    }
+
     pub(crate) fn render_pass(&mut self) -> Result<(), JsValue> {
+        if !self.composing {
+            self.reset_frame_stats();
+        }
+        if !self.context.check_usable() {
+            // the frame is still consumed so queued instances don't pile up
+            self.remove_transient_instances();
+            return Ok(());
+        }
+        // queued instances are gone after this pass, so the next frame differs
+        self.dirty = !self.transient_instances.is_empty();
         if self.instance_store.is_empty() {
             self.transient_instances.clear();
             return Ok(());
         }
 
+        // re-applied every pass since other passes reset it to the full canvas
+        if let Some(viewport) = self.viewport {
+            self.context.apply_viewport(viewport);
+        }
+        let result = self.draw_scene();
+        if self.viewport.is_some() {
+            self.context.reset_viewport();
+        }
+        result
+    }
+
+    fn draw_scene(&mut self) -> Result<(), JsValue> {
         self.prepare_pipeline();
+        self.report_gl_error("prepare_pipeline");
+        self.frame_stats.culled += self.cull_instances();
 
-        for mesh_index in 0..self.mesh_instances.len() {
-            self.draw_mesh_instances(mesh_index)?;
+        if self.sorted_transparency {
+            self.draw_two_phase()?;
+        } else if self.depth_prepass {
+            // Fill depth first, then shade only the fragments that won.
+            self.gl.color_mask(false, false, false, false);
+            let prepass = self.draw_all_meshes();
+            // restored before propagating so a failed prepass can't mask later passes
+            self.gl.color_mask(true, true, true, true);
+            prepass?;
+            self.gl.depth_func(Gl::EQUAL);
+            self.gl.depth_mask(false);
+            let result = self.draw_all_meshes();
+            self.gl.depth_mask(true);
+            self.gl.depth_func(Gl::LEQUAL);
+            result?;
+        } else {
+            self.draw_all_meshes()?;
         }
 
+        self.frame_stats.transient_drawn += self.drawn_transient_count();
         self.remove_transient_instances();
+        // both prepass draws share the override, so it is cleared only now
+        let layer = self.draw_layer;
+        for mesh in self.meshes.iter_mut().flatten() {
+            if layer.is_none_or(|layer| mesh.layer == layer) {
+                mesh.primitive_override = None;
+            }
+        }
         Ok(())
     }
 
-    fn prepare_pipeline(&self) {
-        self.gl.use_program(Some(&self.program));
-        self.gl.enable(Gl::DEPTH_TEST);
-        self.gl.depth_func(Gl::LEQUAL);
-        self.gl.enable(Gl::CULL_FACE);
-        self.gl.enable(Gl::BLEND);
-        self.gl
-            .blend_func(Gl::SRC_ALPHA, Gl::ONE_MINUS_SRC_ALPHA);
-        self.bind_globals();
+    // Queued instances that took part in this pass: hidden, culled, and
+    // filtered-out layer ones are skipped.
+    fn drawn_transient_count(&self) -> u32 {
+        let layer = self.draw_layer;
+        self.transient_instances
+            .iter()
+            .filter_map(|&handle| self.instance_store.get(handle))
+            .filter(|record| {
+                let mesh = self.meshes[record.mesh_index].as_ref();
+                let instances = self.mesh_instances[record.mesh_index].as_ref();
+                mesh.is_some_and(|mesh| layer.is_none_or(|layer| mesh.layer == layer))
+                    && instances.is_some_and(|instances| instances.is_drawn(record.slot_index))
+            })
+            .count() as u32
     }
 
-    pub(crate) fn register_mesh(&mut self, vertices: &Float32Array) -> Result<u32, JsValue> {
-        let data = array_to_vec(vertices);
-        let mesh = Mesh::new(data).map_err(error)?;
-        let vertex_count = (mesh.raw().len() / MESH_VERTEX_STRIDE) as i32;
-        if vertex_count <= 0 {
-            return Err(error("mesh requires at least one triangle"));
-        }
-
-        let vao = VertexArray::new(&self.gl)?;
-        let vertex_buffer = GlBuffer::new(&self.gl)?;
-        let mesh_instances = MeshInstances::new(&self.gl, INITIAL_INSTANCE_HINT)?;
+    pub(crate) fn debug_depth_texture(&mut self) -> Result<WebGlTexture, JsValue> {
+        let size = self.context.size();
+        let target = match self.depth_debug_target.take() {
+            Some(target) if target.size() == size => target,
+            _ => RenderTarget::new(&self.gl, size.0, size.1)?,
+        };
+
+        target.bind();
+        self.gl.clear_color(0.0, 0.0, 0.0, 1.0);
+        self.gl.clear_depth(1.0);
+        self.gl.clear(Gl::COLOR_BUFFER_BIT | Gl::DEPTH_BUFFER_BIT);
+        self.prepare_pipeline();
+        let (near, far, perspective) = match perspective_depth_range(&self.projection_matrix) {
+            Some((near, far)) => (near, far, 1.0),
+            None => (0.0, 1.0, 0.0),
+        };
+        self.gl
+            .uniform3f(Some(&self.depth_params_location), near, far, perspective);
+        self.gl.uniform1i(Some(&self.depth_debug_location), 1);
+        self.cull_instances();
+        let result = self.draw_all_meshes();
+        self.gl.uniform1i(Some(&self.depth_debug_location), 0);
+        self.gl.bind_vertex_array(None);
+        self.gl.bind_framebuffer(Gl::FRAMEBUFFER, None);
+        self.context.reset_viewport();
+        result?;
+
+        let texture = target.texture().clone();
+        self.depth_debug_target = Some(target);
+        Ok(texture)
+    }
+
+    // Draws every instance's handle into an offscreen target and reads back
+    // the one pixel; the pass leaves the frame counters alone.
+    pub(crate) fn pick(&mut self, x: u32, y: u32) -> Result<Option<u32>, JsValue> {
+        let (width, height) = self.context.size();
+        if x >= width || y >= height || !self.context.check_usable() {
+            return Ok(None);
+        }
+        let target = match self.pick_target.take() {
+            Some(target) if target.size() == (width, height) => target,
+            _ => RenderTarget::new(&self.gl, width, height)?,
+        };
+
+        target.bind();
+        self.gl.clear_color(0.0, 0.0, 0.0, 0.0);
+        self.gl.clear_depth(1.0);
+        self.gl.clear(Gl::COLOR_BUFFER_BIT | Gl::DEPTH_BUFFER_BIT);
+        if let Some(viewport) = self.viewport {
+            self.context.apply_viewport(viewport);
+        }
+        self.prepare_pipeline();
+        let stats = self.frame_stats;
+        self.gl.uniform1i(Some(&self.pick_location), 1);
+        self.cull_instances();
+        let result = self.draw_all_meshes();
+        self.gl.uniform1i(Some(&self.pick_location), 0);
+        self.frame_stats = stats;
+        let mut pixel = [0u8; 4];
+        let read = result.and_then(|_| {
+            // GL rows start at the bottom
+            self.gl.read_pixels_with_opt_u8_array(
+                x as i32,
+                (height - 1 - y) as i32,
+                1,
+                1,
+                Gl::RGBA,
+                Gl::UNSIGNED_BYTE,
+                Some(&mut pixel),
+            )
+        });
+        self.gl.bind_framebuffer(Gl::FRAMEBUFFER, None);
+        self.context.reset_viewport();
+        self.pick_target = Some(target);
+        read?;
+
+        // queued instances have no handle on the JS side
+        Ok(decode_pick_id(pixel).filter(|handle| {
+            !self.transient_instances.contains(handle) && !self.sticky_instances.contains(handle)
+        }))
+    }
+
+    fn set_culling_enabled(&mut self, enabled: bool) {
+        self.culling = enabled;
+        if !enabled {
+            for instances in self.mesh_instances.iter_mut().flatten() {
+                instances.clear_culling();
+            }
+        }
+    }
+
+    // Marks the instances of the meshes this pass draws that fall outside
+    // the frustum, so the next flush packs them out; returns how many.
+    fn cull_instances(&mut self) -> u32 {
+        if !self.culling {
+            return 0;
+        }
+        let planes = frustum_planes(&multiply(&self.projection_matrix, &self.view_matrix));
+        let layer = self.draw_layer;
+        let facing = self.billboard_facing;
+        let mut culled = 0;
+        for (mesh, instances) in self.meshes.iter().zip(&mut self.mesh_instances) {
+            let (Some(mesh), Some(instances)) = (mesh, instances) else {
+                continue;
+            };
+            // an interpolated instance is drawn between two transforms
+            if mesh.interpolated || layer.is_some_and(|layer| mesh.layer != layer) {
+                continue;
+            }
+            culled += instances.cull(|values| {
+                let (center, radius) = instance_sphere(mesh.format, values, mesh.sphere, facing);
+                sphere_in_frustum(&planes, center, radius)
+            });
+        }
+        culled as u32
+    }
+
+    fn draw_all_meshes(&mut self) -> Result<(), JsValue> {
+        let mut order = std::mem::take(&mut self.draw_order);
+        order.clear();
+        let layer = self.draw_layer;
+        order.extend(self.meshes.iter().enumerate().filter_map(|(index, mesh)| {
+            let mesh = mesh.as_ref()?;
+            layer
+                .is_none_or(|layer| mesh.layer == layer)
+                .then_some(index)
+        }));
+        let opaque: Vec<bool> = self
+            .meshes
+            .iter()
+            .map(|mesh| mesh.as_ref().is_some_and(|mesh| mesh.opaque))
+            .collect();
+        if self.auto_draw_sort {
+            // stable, so registration order is kept within each group
+            order.sort_by_key(|&index| !opaque[index]);
+        }
+
+        // translucent meshes toggle either blending or coverage, never both
+        let translucency = if self.alpha_to_coverage {
+            self.gl.disable(Gl::BLEND);
+            Gl::SAMPLE_ALPHA_TO_COVERAGE
+        } else {
+            Gl::BLEND
+        };
+        let mut translucent = !self.alpha_to_coverage; // prepare_pipeline leaves blending on
+        let mut result = Ok(());
+        for &mesh_index in &order {
+            let opaque = opaque[mesh_index];
+            if opaque == translucent {
+                if opaque {
+                    self.gl.disable(translucency);
+                } else {
+                    self.gl.enable(translucency);
+                }
+                translucent = !opaque;
+            }
+            result = self.draw_mesh_instances(mesh_index);
+            if result.is_err() {
+                break;
+            }
+        }
+        self.gl.disable(Gl::SAMPLE_ALPHA_TO_COVERAGE);
+        self.gl.enable(Gl::BLEND);
+        // left bound, the last mesh's VAO would absorb the next pass's attribute setup
+        self.gl.bind_vertex_array(None);
+        self.draw_order = order;
+        result
+    }
+
+    // Instances of each translucent mesh are sorted by view depth; the meshes
+    // themselves go by their instances' mean depth, so instances of two
+    // translucent meshes never interleave.
+    fn draw_two_phase(&mut self) -> Result<(), JsValue> {
+        let mut order = std::mem::take(&mut self.draw_order);
+        order.clear();
+        let layer = self.draw_layer;
+        let drawn = |mesh: &GpuMesh| layer.is_none_or(|layer| mesh.layer == layer);
+        order.extend(self.meshes.iter().enumerate().filter_map(|(index, mesh)| {
+            mesh.as_ref()
+                .filter(|mesh| drawn(mesh) && mesh.opaque)
+                .map(|_| index)
+        }));
+
+        self.gl.disable(Gl::BLEND);
+        let mut result = Ok(());
+        for &mesh_index in &order {
+            result = self.draw_mesh_instances(mesh_index);
+            if result.is_err() {
+                break;
+            }
+        }
+        self.gl.enable(Gl::BLEND);
+
+        if result.is_ok() {
+            let view = self.view_matrix;
+            let mut translucent = Vec::new();
+            for (index, mesh) in self.meshes.iter().enumerate() {
+                let (Some(mesh), Some(instances)) = (mesh, &mut self.mesh_instances[index]) else {
+                    continue;
+                };
+                if !drawn(mesh) || mesh.opaque {
+                    continue;
+                }
+                let format = mesh.format;
+                let mean_depth = instances.sort_for_draw(|values| {
+                    let [x, y, z] = instance_origin(format, values);
+                    view[2] * x + view[6] * y + view[10] * z + view[14]
+                });
+                if let Some(depth) = mean_depth {
+                    translucent.push((depth, index));
+                }
+            }
+            translucent.sort_by(|a, b| a.0.total_cmp(&b.0));
+            self.gl.depth_mask(false);
+            for &(_, mesh_index) in &translucent {
+                result = self.draw_mesh_instances(mesh_index);
+                if result.is_err() {
+                    break;
+                }
+            }
+            self.gl.depth_mask(true);
+        }
+        self.gl.bind_vertex_array(None);
+        self.draw_order = order;
+        result
+    }
+
+    fn reset_pipeline_state(&mut self) {
+        self.viewport = None;
+        self.alpha_to_coverage = false;
+        for mesh in self.meshes.iter_mut().flatten() {
+            mesh.primitive_override = None;
+        }
+        if self.gl.is_context_lost() {
+            return;
+        }
+        // state no pass changes, but custom GL code sharing the context might
+        self.gl.depth_mask(true);
+        self.gl.depth_range(0.0, 1.0);
+        self.gl.cull_face(Gl::BACK);
+        self.gl.front_face(Gl::CCW);
+        self.gl.blend_equation(Gl::FUNC_ADD);
+        self.gl.color_mask(true, true, true, true);
+        self.gl.disable(Gl::SAMPLE_ALPHA_TO_COVERAGE);
+        self.gl.bind_vertex_array(None);
+        self.context.reset_viewport();
+        self.prepare_pipeline();
+    }
+
+    fn prepare_pipeline(&self) {
+        self.gl.use_program(Some(&self.program));
+        self.gl.enable(Gl::DEPTH_TEST);
+        self.gl.depth_func(Gl::LEQUAL);
+        self.gl.enable(Gl::CULL_FACE);
+        self.gl.enable(Gl::BLEND);
+        self.gl.blend_func(Gl::SRC_ALPHA, Gl::ONE_MINUS_SRC_ALPHA);
+        self.bind_globals();
+    }
+
+    pub(crate) fn register_mesh(
+        &mut self,
+        vertices: &Float32Array,
+        format: InstanceFormat,
+        options: MeshOptions,
+    ) -> Result<u32, JsValue> {
+        let data = array_to_vec(vertices);
+        let mesh = Mesh::new(data).map_err(error)?;
+        self.upload_mesh(&mesh, format, options)
+    }
+
+    pub(crate) fn upload_mesh(
+        &mut self,
+        mesh: &Mesh,
+        format: InstanceFormat,
+        options: MeshOptions,
+    ) -> Result<u32, JsValue> {
+        let vertex_count = (mesh.raw().len() / MESH_VERTEX_STRIDE) as i32;
+        options
+            .primitive
+            .check_vertex_count(vertex_count as usize)
+            .map_err(error)?;
+        let attributes = vertex_attributes(format, options.interpolated, options.extra_columns);
+        if attributes > self.max_vertex_attribs {
+            return Err(error(&format!(
+                "mesh layout needs {attributes} vertex attributes but the device supports {}",
+                self.max_vertex_attribs
+            )));
+        }
+
+        let vao = VertexArray::new(&self.gl)?;
+        let vertex_buffer = GlBuffer::new(&self.gl)?;
+        let stride =
+            format.stride() + previous_floats(options.interpolated) + options.extra_columns * 4;
+        let mesh_instances = MeshInstances::new(
+            &self.gl,
+            stride,
+            INITIAL_INSTANCE_HINT,
+            self.instance_growth_factor,
+        )?;
 
         self.gl.bind_vertex_array(Some(vao.handle()));
         vertex_buffer.bind_array_buffer();
+        let usage = if options.dynamic {
+            Gl::DYNAMIC_DRAW
+        } else {
+            Gl::STATIC_DRAW
+        };
         let vertex_view = unsafe { Float32Array::view(mesh.raw()) };
         self.gl
-            .buffer_data_with_array_buffer_view(Gl::ARRAY_BUFFER, &vertex_view, Gl::STATIC_DRAW);
+            .buffer_data_with_array_buffer_view(Gl::ARRAY_BUFFER, &vertex_view, usage);
         self.configure_mesh_attributes();
 
         self.gl.bind_buffer(
             Gl::ARRAY_BUFFER,
             Some(mesh_instances.buffer_handle().handle()),
         );
-        self.configure_instance_attributes();
+        self.configure_instance_attributes(format, options.interpolated, options.extra_columns);
         self.gl.bind_vertex_array(None);
 
+        self.meshes.push(Some(GpuMesh {
+            vao,
+            vertex_buffer,
+            vertex_count,
+            format,
+            opaque: false,
+            depth_test: options.depth_test,
+            extra_columns: options.extra_columns,
+            dynamic: options.dynamic,
+            interpolated: options.interpolated,
+            layer: 0,
+            primitive: options.primitive,
+            primitive_override: None,
+            bounds: mesh.bounds(),
+            sphere: mesh.bounding_sphere(),
+            cpu_data: self.retain_mesh_data.then(|| mesh.clone()),
+        }));
+        self.mesh_instances.push(Some(mesh_instances));
+        Ok(MeshHandle::from_index(self.meshes.len() - 1).to_js())
+    }
+
+    pub(crate) fn reset_meshes(&mut self) {
+        // dropping GpuMesh/MeshInstances deletes their VAOs and buffers
+        self.gl.bind_vertex_array(None);
+        self.meshes.clear();
+        self.mesh_instances.clear();
+        self.instance_store = InstanceStore::new();
+        self.transient_instances.clear();
+        self.sticky_instances.clear();
+    }
+
+    // The slot is left empty rather than shifted, so every other mesh handle
+    // keeps pointing at its mesh; the freed handle is never reused.
+    pub(crate) fn remove_mesh(&mut self, mesh_handle: MeshHandle) -> Result<(), JsValue> {
+        let index = mesh_handle.index();
+        let instances = self
+            .mesh_instances
+            .get_mut(index)
+            .and_then(Option::take)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        let handles: Vec<u32> = (0..instances.len())
+            .map(|slot| instances.handle_at(slot))
+            .collect();
+        for &handle in &handles {
+            self.instance_store.remove(handle);
+        }
+        self.transient_instances
+            .retain(|handle| !handles.contains(handle));
+        self.sticky_instances
+            .retain(|handle| !handles.contains(handle));
+        // dropping GpuMesh/MeshInstances deletes their VAO and buffers
+        self.gl.bind_vertex_array(None);
+        self.meshes[index] = None;
+        Ok(())
+    }
+
+    pub(crate) fn retained_mesh(&self, mesh_handle: MeshHandle) -> Result<&Mesh, JsValue> {
         self.meshes
-            .push(GpuMesh { vao, _vertex_buffer: vertex_buffer, vertex_count });
-        self.mesh_instances.push(mesh_instances);
-        Ok((self.meshes.len() - 1) as u32)
+            .get(mesh_handle.index())
+            .and_then(Option::as_ref)
+            .ok_or_else(|| error("invalid mesh handle"))?
+            .cpu_data
+            .as_ref()
+            .ok_or_else(|| {
+                error("mesh data was not retained; call set_retain_mesh_data(true) before registering")
+            })
+    }
+
+    pub(crate) fn update_mesh_vertices(
+        &mut self,
+        mesh_handle: MeshHandle,
+        vertices: &Float32Array,
+    ) -> Result<(), JsValue> {
+        let mesh = self
+            .meshes
+            .get_mut(mesh_handle.index())
+            .and_then(Option::as_mut)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        if !mesh.dynamic {
+            return Err(error("mesh was not registered with dynamic: true"));
+        }
+        if vertices.length() as usize != mesh.vertex_count as usize * MESH_VERTEX_STRIDE {
+            return Err(error(
+                "vertex update must match the mesh's vertex count and stride",
+            ));
+        }
+        let data = Mesh::new(array_to_vec(vertices)).map_err(error)?;
+        mesh.vertex_buffer.bind_array_buffer();
+        let vertex_view = unsafe { Float32Array::view(data.raw()) };
+        self.gl
+            .buffer_sub_data_with_i32_and_array_buffer_view(Gl::ARRAY_BUFFER, 0, &vertex_view);
+        mesh.bounds = data.bounds();
+        mesh.sphere = data.bounding_sphere();
+        if mesh.cpu_data.is_some() {
+            mesh.cpu_data = Some(data);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn draw_mesh_as(
+        &mut self,
+        mesh_handle: MeshHandle,
+        primitive: Primitive,
+    ) -> Result<(), JsValue> {
+        let mesh = self
+            .meshes
+            .get_mut(mesh_handle.index())
+            .and_then(Option::as_mut)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        primitive
+            .check_vertex_count(mesh.vertex_count as usize)
+            .map_err(error)?;
+        mesh.primitive_override = Some(primitive);
+        Ok(())
+    }
+
+    pub(crate) fn set_mesh_layer(
+        &mut self,
+        mesh_handle: MeshHandle,
+        layer: u32,
+    ) -> Result<(), JsValue> {
+        let mesh = self
+            .meshes
+            .get_mut(mesh_handle.index())
+            .and_then(Option::as_mut)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        mesh.layer = layer;
+        Ok(())
+    }
+
+    pub(crate) fn set_mesh_opaque(
+        &mut self,
+        mesh_handle: MeshHandle,
+        opaque: bool,
+    ) -> Result<(), JsValue> {
+        let mesh = self
+            .meshes
+            .get_mut(mesh_handle.index())
+            .and_then(Option::as_mut)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        mesh.opaque = opaque;
+        Ok(())
+    }
+
+    pub(crate) fn mesh_triangle_count(&self, mesh_handle: MeshHandle) -> Result<u32, JsValue> {
+        let mesh = self
+            .meshes
+            .get(mesh_handle.index())
+            .and_then(Option::as_ref)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        Ok(mesh.primitive.triangle_count(mesh.vertex_count))
+    }
+
+    fn instances_for(&self, mesh_handle: MeshHandle) -> Result<&MeshInstances, JsValue> {
+        self.mesh_instances
+            .get(mesh_handle.index())
+            .and_then(Option::as_ref)
+            .ok_or_else(|| error("invalid mesh handle"))
     }
 
     pub(crate) fn create_instance(
         &mut self,
-        mesh_handle: u32,
+        mesh_handle: MeshHandle,
         transform: &Float32Array,
     ) -> Result<u32, JsValue> {
-        let mesh_index = mesh_handle as usize;
-        let matrix = matrix_from_array(transform)?;
+        let matrix = matrix_from_array(transform, self.matrix_layout)?;
+        self.insert_instance(mesh_handle, InstanceFormat::Matrix, &matrix, matrix)
+    }
+
+    pub(crate) fn create_billboard(
+        &mut self,
+        mesh_handle: MeshHandle,
+        billboard: [f32; BILLBOARD_FLOATS],
+    ) -> Result<u32, JsValue> {
+        let transform = validated_billboard(billboard)?;
+        self.insert_instance(
+            mesh_handle,
+            InstanceFormat::Billboard,
+            &billboard,
+            transform,
+        )
+    }
+
+    pub(crate) fn create_sprite(
+        &mut self,
+        mesh_handle: MeshHandle,
+        sprite: [f32; SPRITE_FLOATS],
+    ) -> Result<u32, JsValue> {
+        let transform = validated_sprite(sprite)?;
+        self.insert_instance(mesh_handle, InstanceFormat::Sprite, &sprite, transform)
+    }
+
+    fn insert_instance(
+        &mut self,
+        mesh_handle: MeshHandle,
+        format: InstanceFormat,
+        values: &[f32],
+        transform: [f32; MATRIX_FLOATS],
+    ) -> Result<u32, JsValue> {
+        if self.at_instance_budget() {
+            return Err(error("instance budget reached"));
+        }
+        let mesh_index = mesh_handle.index();
+        let mesh = self
+            .meshes
+            .get(mesh_index)
+            .and_then(Option::as_ref)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        check_format(mesh.format, format)?;
         let mesh_instances = self
             .mesh_instances
             .get_mut(mesh_index)
+            .and_then(Option::as_mut)
             .ok_or_else(|| error("invalid mesh handle"))?;
-        let slot = mesh_instances.allocate(&self.gl, &matrix)?;
-        let handle = self.instance_store.insert(mesh_index, slot, matrix);
+        check_draw_budget(mesh_instances.len() + 1, self.max_instances_per_draw)?;
+        if self.instance_store.next_handle() > MAX_PICK_HANDLE {
+            return Err(error(
+                "too many live instances: handles past 16777214 cannot be picked",
+            ));
+        }
+        let stride = mesh.instance_stride();
+        let mut data = Vec::with_capacity(stride);
+        data.extend_from_slice(values);
+        data.extend_from_slice(&DEFAULT_INSTANCE_PARAMS);
+        if mesh.interpolated {
+            data.extend_from_slice(values); // nothing to blend from yet
+        }
+        data.resize(stride, 0.0); // extras start at zero
+        let slot = mesh_instances.allocate(&self.gl, &data)?;
+        let handle = self.instance_store.insert(mesh_index, slot, transform);
         mesh_instances.set_handle(slot, handle);
+        mesh_instances.update_slot(slot, format.floats() + PARAM_PICK_ID, &[handle as f32])?;
         Ok(handle)
     }
 
     pub(crate) fn set_instance_transform(
         &mut self,
-        instance_handle: u32,
+        instance_handle: InstanceHandle,
         transform: &Float32Array,
     ) -> Result<(), JsValue> {
-        let matrix = matrix_from_array(transform)?;
+        let matrix = matrix_from_array(transform, self.matrix_layout)?;
+        self.update_instance(instance_handle, InstanceFormat::Matrix, &matrix, matrix)
+    }
+
+    pub(crate) fn set_billboard(
+        &mut self,
+        instance_handle: InstanceHandle,
+        billboard: [f32; BILLBOARD_FLOATS],
+    ) -> Result<(), JsValue> {
+        let transform = validated_billboard(billboard)?;
+        self.update_instance(
+            instance_handle,
+            InstanceFormat::Billboard,
+            &billboard,
+            transform,
+        )
+    }
+
+    pub(crate) fn set_sprite(
+        &mut self,
+        instance_handle: InstanceHandle,
+        sprite: [f32; SPRITE_FLOATS],
+    ) -> Result<(), JsValue> {
+        let transform = validated_sprite(sprite)?;
+        self.update_instance(instance_handle, InstanceFormat::Sprite, &sprite, transform)
+    }
+
+    fn update_instance(
+        &mut self,
+        instance_handle: InstanceHandle,
+        format: InstanceFormat,
+        values: &[f32],
+        transform: [f32; MATRIX_FLOATS],
+    ) -> Result<(), JsValue> {
         let record = self
             .instance_store
-            .get_mut(instance_handle)
+            .get_mut(instance_handle.raw())
             .ok_or_else(|| error("invalid instance handle"))?;
-        record.transform = matrix;
+        let mesh = self
+            .meshes
+            .get(record.mesh_index)
+            .and_then(Option::as_ref)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        check_format(mesh.format, format)?;
+        record.transform = transform;
         let instances = self
             .mesh_instances
             .get_mut(record.mesh_index)
+            .and_then(Option::as_mut)
             .ok_or_else(|| error("invalid mesh handle"))?;
-        instances.update_slot(record.slot_index, &matrix)?;
+        instances.update_slot(record.slot_index, 0, values)?;
+        if mesh.interpolated {
+            // a plain update snaps instead of blending from a stale state
+            instances.update_slot(record.slot_index, mesh.previous_offset(), values)?;
+        }
         Ok(())
     }
 
-    pub(crate) fn remove_instance(&mut self, instance_handle: u32) -> Result<(), JsValue> {
-        if self.remove_instance_internal(instance_handle)? {
+    pub(crate) fn set_instance_transform_pair(
+        &mut self,
+        instance_handle: InstanceHandle,
+        previous: &Float32Array,
+        next: &Float32Array,
+    ) -> Result<(), JsValue> {
+        let previous = matrix_from_array(previous, self.matrix_layout)?;
+        let next = matrix_from_array(next, self.matrix_layout)?;
+        let record = self
+            .instance_store
+            .get_mut(instance_handle.raw())
+            .ok_or_else(|| error("invalid instance handle"))?;
+        let mesh = self
+            .meshes
+            .get(record.mesh_index)
+            .and_then(Option::as_ref)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        if !mesh.interpolated {
+            return Err(error("mesh was registered without interpolated: true"));
+        }
+        record.transform = next;
+        let instances = self
+            .mesh_instances
+            .get_mut(record.mesh_index)
+            .and_then(Option::as_mut)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        instances.update_slot(record.slot_index, 0, &next)?;
+        instances.update_slot(record.slot_index, mesh.previous_offset(), &previous)
+    }
+
+    pub(crate) fn set_interpolation_alpha(&mut self, alpha: f32) -> Result<(), JsValue> {
+        if !alpha.is_finite() {
+            return Err(error("interpolation alpha must be finite"));
+        }
+        self.interpolation_alpha = clamp_unit(alpha);
+        self.gl.use_program(Some(&self.program));
+        self.upload_interpolation_alpha();
+        Ok(())
+    }
+
+    pub(crate) fn set_instance_depth_bias(
+        &mut self,
+        instance_handle: InstanceHandle,
+        bias: f32,
+    ) -> Result<(), JsValue> {
+        if !bias.is_finite() {
+            return Err(error("depth bias must be finite"));
+        }
+        self.set_instance_param(instance_handle, PARAM_DEPTH_BIAS, &[bias])
+    }
+
+    pub(crate) fn set_instance_color(
+        &mut self,
+        instance_handle: InstanceHandle,
+        color: [f32; COLOR_COMPONENTS],
+    ) -> Result<(), JsValue> {
+        if color.iter().any(|channel| !channel.is_finite()) {
+            return Err(error("instance color must be finite"));
+        }
+        self.set_instance_param(instance_handle, PARAM_COLOR, &color.map(clamp_unit))
+    }
+
+    pub(crate) fn set_instance_param(
+        &mut self,
+        instance_handle: InstanceHandle,
+        param: usize,
+        values: &[f32],
+    ) -> Result<(), JsValue> {
+        let record = self
+            .instance_store
+            .get(instance_handle.raw())
+            .ok_or_else(|| error("invalid instance handle"))?;
+        let mesh = self
+            .meshes
+            .get(record.mesh_index)
+            .and_then(Option::as_ref)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        let offset = mesh.format.floats() + param;
+        let instances = self
+            .mesh_instances
+            .get_mut(record.mesh_index)
+            .and_then(Option::as_mut)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        instances.update_slot(record.slot_index, offset, values)
+    }
+
+    pub(crate) fn set_instance_visible(
+        &mut self,
+        instance_handle: InstanceHandle,
+        visible: bool,
+    ) -> Result<(), JsValue> {
+        let record = self
+            .instance_store
+            .get(instance_handle.raw())
+            .ok_or_else(|| error("invalid instance handle"))?;
+        let instances = self
+            .mesh_instances
+            .get_mut(record.mesh_index)
+            .and_then(Option::as_mut)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        instances.set_visible(record.slot_index, visible)
+    }
+
+    pub(crate) fn set_instance_extra(
+        &mut self,
+        instance_handle: InstanceHandle,
+        values: &[f32],
+    ) -> Result<(), JsValue> {
+        let record = self
+            .instance_store
+            .get(instance_handle.raw())
+            .ok_or_else(|| error("invalid instance handle"))?;
+        let mesh = self
+            .meshes
+            .get(record.mesh_index)
+            .and_then(Option::as_ref)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        if mesh.extra_columns == 0 {
+            return Err(error("mesh was registered without extra instance columns"));
+        }
+        if values.len() != mesh.extra_columns * 4 {
+            return Err(error("extra data must provide 4 floats per extra column"));
+        }
+        let offset = mesh.extras_offset();
+        let instances = self
+            .mesh_instances
+            .get_mut(record.mesh_index)
+            .and_then(Option::as_mut)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        instances.update_slot(record.slot_index, offset, values)
+    }
+
+    pub(crate) fn remove_instance(
+        &mut self,
+        instance_handle: InstanceHandle,
+    ) -> Result<(), JsValue> {
+        if self.remove_instance_internal(instance_handle.raw())? {
             self.transient_instances
-                .retain(|handle| *handle != instance_handle);
+                .retain(|handle| *handle != instance_handle.raw());
+            self.sticky_instances
+                .retain(|handle| *handle != instance_handle.raw());
             Ok(())
         } else {
             Err(error("invalid instance handle"))
         }
     }
 
+    pub(crate) fn instance_mesh(&self, instance_handle: InstanceHandle) -> Result<u32, JsValue> {
+        self.instance_store
+            .get(instance_handle.raw())
+            .map(|record| MeshHandle::from_index(record.mesh_index).to_js())
+            .ok_or_else(|| error("invalid instance handle"))
+    }
+
     pub(crate) fn queue_instance(
         &mut self,
-        mesh_handle: u32,
+        mesh_handle: MeshHandle,
         transform: &Float32Array,
     ) -> Result<(), JsValue> {
+        if self.at_instance_budget() {
+            // dropping keeps bursty emitters from throwing every frame
+            self.transients_dropped += 1;
+            return Ok(());
+        }
         let handle = self.create_instance(mesh_handle, transform)?;
         self.transient_instances.push(handle);
         Ok(())
     }
 
+    fn at_instance_budget(&self) -> bool {
+        self.max_total_instances
+            .is_some_and(|max| self.instance_store.len() >= max as usize)
+    }
+
+    pub(crate) fn queue_sticky_instance(
+        &mut self,
+        mesh_handle: MeshHandle,
+        transform: &Float32Array,
+    ) -> Result<(), JsValue> {
+        let handle = self.create_instance(mesh_handle, transform)?;
+        self.sticky_instances.push(handle);
+        Ok(())
+    }
+
+    pub(crate) fn clear_sticky(&mut self) {
+        let handles = std::mem::take(&mut self.sticky_instances);
+        for handle in handles {
+            let _ = self.remove_instance_internal(handle);
+        }
+    }
+
     pub(crate) fn set_view_matrix(&mut self, matrix: &Float32Array) -> Result<(), JsValue> {
-        copy_into_matrix(&mut self.view_matrix, matrix)?;
+        self.view_matrix = matrix_from_array(matrix, self.matrix_layout)?;
         self.gl.use_program(Some(&self.program));
         self.upload_view_matrix();
         Ok(())
     }
 
     pub(crate) fn set_projection_matrix(&mut self, matrix: &Float32Array) -> Result<(), JsValue> {
-        copy_into_matrix(&mut self.projection_matrix, matrix)?;
+        self.projection_matrix = matrix_from_array(matrix, self.matrix_layout)?;
         self.gl.use_program(Some(&self.program));
         self.upload_projection_matrix();
         Ok(())
     }
 
+    pub(crate) fn commit_camera(&self) -> Result<(), JsValue> {
+        if self.gl.is_context_lost() {
+            return Err(error(
+                "cannot commit the camera while the WebGL context is lost",
+            ));
+        }
+        self.gl.use_program(Some(&self.program));
+        self.bind_globals();
+        self.report_gl_error("commit_camera");
+        Ok(())
+    }
+
+    pub(crate) fn set_projection(&mut self, matrix: [f32; MATRIX_FLOATS]) {
+        self.projection_matrix = matrix;
+        self.gl.use_program(Some(&self.program));
+        self.upload_projection_matrix();
+    }
+
+    pub(crate) fn set_point_size(&mut self, size: f32) -> Result<(), JsValue> {
+        if !size.is_finite() || size <= 0.0 {
+            return Err(error("point size must be positive"));
+        }
+        let [min, max] = self.point_size_limits;
+        let clamped = size.clamp(min, max);
+        if clamped != size {
+            log(&format!(
+                "point size {size} clamped to {clamped}; this driver supports {min}..{max}"
+            ));
+        }
+        self.point_size = clamped;
+        self.gl.use_program(Some(&self.program));
+        self.upload_point_size();
+        Ok(())
+    }
+
+    pub(crate) fn set_billboard_facing(&mut self, enabled: bool) {
+        self.billboard_facing = enabled;
+        self.gl.use_program(Some(&self.program));
+        self.upload_billboard_facing();
+    }
+
     pub(crate) fn max_instances(&self) -> u32 {
         self.max_instances_per_draw as u32
     }
@@ -350,20 +1891,313 @@ impl BatchedRendererInner {
         self.instance_store.len() as u32
     }
 
+    // Counts queued instances like instance_count; culling is not hiding.
+    pub(crate) fn visible_instance_count(&self) -> u32 {
+        self.mesh_instances
+            .iter()
+            .flatten()
+            .map(MeshInstances::visible_count)
+            .sum::<usize>() as u32
+    }
+
+    pub(crate) fn instance_screen_bounds(
+        &self,
+        instance_handle: InstanceHandle,
+    ) -> Result<[f32; 4], JsValue> {
+        let record = self
+            .instance_store
+            .get(instance_handle.raw())
+            .ok_or_else(|| error("invalid instance handle"))?;
+        let mesh = self
+            .meshes
+            .get(record.mesh_index)
+            .and_then(Option::as_ref)
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        let model = self.instance_model(record.transform, mesh.format);
+        let clip = multiply(
+            &self.projection_matrix,
+            &multiply(&self.view_matrix, &model),
+        );
+        let target = self.target();
+        let (width, height) = target.size();
+        let rect = screen_bounds(&clip, mesh.bounds, (width as f32, height as f32))
+            .ok_or_else(|| error("instance is entirely behind the camera"))?;
+        // shift from the viewport's top-left corner to the canvas's
+        let left = target.x as f32;
+        let top = self.context.size().1 as f32 - (target.y + target.height) as f32;
+        Ok([rect[0] + left, rect[1] + top, rect[2] + left, rect[3] + top])
+    }
+
+    fn target(&self) -> Viewport {
+        self.viewport
+            .unwrap_or_else(|| Viewport::full(self.context.size()))
+    }
+
+    // Union of every persistent instance's transformed mesh bounds.
+    pub(crate) fn world_bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        self.instance_store
+            .records()
+            .filter_map(|record| {
+                let mesh = self.meshes.get(record.mesh_index)?.as_ref()?;
+                let model = self.instance_model(record.transform, mesh.format);
+                Some(transformed_bounds(&model, mesh.bounds))
+            })
+            .reduce(|(min_a, max_a), (min_b, max_b)| {
+                (
+                    std::array::from_fn(|axis| min_a[axis].min(min_b[axis])),
+                    std::array::from_fn(|axis| max_a[axis].max(max_b[axis])),
+                )
+            })
+    }
+
+    pub(crate) fn frame_all(
+        &mut self,
+        fov_y_radians: f32,
+        near: f32,
+        far: f32,
+        up: UpAxis,
+    ) -> Result<(f32, [f32; 3]), JsValue> {
+        let (min, max) = self
+            .world_bounds()
+            .ok_or_else(|| error("no instances to frame"))?;
+        let target: [f32; 3] = std::array::from_fn(|axis| (min[axis] + max[axis]) * 0.5);
+        let extent: [f32; 3] = std::array::from_fn(|axis| max[axis] - min[axis]);
+        let radius = 0.5 * extent.iter().map(|e| e * e).sum::<f32>().sqrt();
+        let (width, height) = self.target().size();
+        let aspect = width as f32 / height.max(1) as f32;
+        let distance = fit_distance(radius, fov_y_radians, aspect).map_err(error)?;
+        let projection = perspective_matrix(fov_y_radians, aspect, near, far).map_err(error)?;
+        self.view_matrix =
+            orbit_view_matrix(target, FRAME_YAW, FRAME_PITCH, distance, up).map_err(error)?;
+        self.set_projection(projection);
+        self.upload_view_matrix();
+        Ok((distance, target))
+    }
+
+    fn instance_model(
+        &self,
+        transform: [f32; MATRIX_FLOATS],
+        format: InstanceFormat,
+    ) -> [f32; MATRIX_FLOATS] {
+        if format == InstanceFormat::Billboard && self.billboard_facing {
+            facing_billboard_matrix(&transform, &self.view_matrix)
+        } else {
+            transform
+        }
+    }
+
+    // Keep in sync with prepare_pipeline and the state draw_all_meshes and
+    // draw_scene restore after toggling it per mesh.
+    fn pipeline_state(&self) -> PipelineState {
+        PipelineState {
+            depth_test: true,
+            depth_write: true,
+            cull_mode: "back",
+            blend_mode: "alphaOver",
+            blend_equation: "add",
+        }
+    }
+
+    fn attribute_budget(&self) -> AttributeBudget {
+        let used = self
+            .meshes
+            .iter()
+            .flatten()
+            .map(|mesh| vertex_attributes(mesh.format, mesh.interpolated, mesh.extra_columns))
+            .max()
+            .unwrap_or(0);
+        AttributeBudget {
+            max_vertex_attribs: self.max_vertex_attribs as u32,
+            used: used as u32,
+        }
+    }
+
+    fn scene_summary(&self) -> SceneSummary {
+        SceneSummary {
+            meshes: self.meshes.iter().flatten().count() as u32,
+            instances: self.instance_count(),
+            queued: self.queued_instances(),
+            draw_calls_last_frame: self.frame_stats.draw_calls,
+            triangles_last_frame: self.frame_stats.triangles,
+        }
+    }
+
     pub(crate) fn queued_instances(&self) -> u32 {
         self.transient_instances.len() as u32
     }
 
+    pub(crate) fn last_transient_drawn(&self) -> u32 {
+        self.frame_stats.transient_drawn
+    }
+
     pub(crate) fn defragment_instances(&mut self) {
-        for instances in &mut self.mesh_instances {
+        for instances in self.mesh_instances.iter_mut().flatten() {
             instances.flush_pending(&self.gl);
             instances.defragment(&self.gl);
         }
     }
 
+    fn clear(&self, color: [f32; 4]) {
+        self.clear_color.set(color);
+        self.context.clear(color, Some(self.clear_depth), None);
+    }
+
+    fn scene_document(&self) -> Result<SceneDocument, JsValue> {
+        let mut meshes = Vec::with_capacity(self.meshes.len());
+        for mesh in &self.meshes {
+            // the dump numbers meshes by position, so removed ones stay as gaps
+            let Some(mesh) = mesh else {
+                meshes.push(None);
+                continue;
+            };
+            let data = mesh.cpu_data.as_ref().ok_or_else(|| {
+                error("to_json needs every mesh retained; call set_retain_mesh_data(true) before registering")
+            })?;
+            meshes.push(Some(SceneMesh {
+                format: mesh.format.into(),
+                vertices: data.raw().to_vec(),
+                depth_test: mesh.depth_test,
+                opaque: mesh.opaque,
+                extra_columns: mesh.extra_columns,
+                dynamic: mesh.dynamic,
+                interpolated: mesh.interpolated,
+                primitive: mesh.primitive.into(),
+                layer: mesh.layer,
+            }));
+        }
+        Ok(SceneDocument {
+            version: SCENE_VERSION,
+            meshes,
+            instances: self.snapshot().instances,
+            view: self.view_matrix.to_vec(),
+            projection: self.projection_matrix.to_vec(),
+            clear_color: self.clear_color.get(),
+        })
+    }
+
+    // Expects a validated document and a renderer without meshes.
+    fn load_scene(&mut self, document: SceneDocument) -> Result<(), JsValue> {
+        self.retain_mesh_data = true; // so the scene can be dumped again
+        for scene_mesh in document.meshes {
+            let Some(scene_mesh) = scene_mesh else {
+                // a removed mesh keeps its handle unusable after the reload
+                self.meshes.push(None);
+                self.mesh_instances.push(None);
+                continue;
+            };
+            let mesh = Mesh::new(scene_mesh.vertices).map_err(error)?;
+            let options = MeshOptions {
+                depth_test: scene_mesh.depth_test,
+                extra_columns: scene_mesh.extra_columns,
+                dynamic: scene_mesh.dynamic,
+                interpolated: scene_mesh.interpolated,
+                primitive: scene_mesh.primitive.into(),
+            };
+            self.upload_mesh(&mesh, scene_mesh.format.into(), options)?;
+            if let Some(Some(uploaded)) = self.meshes.last_mut() {
+                uploaded.opaque = scene_mesh.opaque;
+                uploaded.layer = scene_mesh.layer;
+            }
+        }
+        self.restore(RendererSnapshot {
+            instances: document.instances,
+        })?;
+        self.view_matrix.copy_from_slice(&document.view);
+        self.projection_matrix.copy_from_slice(&document.projection);
+        self.gl.use_program(Some(&self.program));
+        self.bind_globals();
+        self.clear(document.clear_color);
+        Ok(())
+    }
+
+    pub(crate) fn snapshot(&self) -> RendererSnapshot {
+        let mut instances = Vec::with_capacity(self.instance_store.len());
+        for (mesh_index, mesh_instances) in self.mesh_instances.iter().enumerate() {
+            let Some(mesh_instances) = mesh_instances else {
+                continue;
+            };
+            for slot in 0..mesh_instances.len() {
+                let handle = mesh_instances.handle_at(slot);
+                // queued instances are not part of the persistent scene
+                if self.transient_instances.contains(&handle)
+                    || self.sticky_instances.contains(&handle)
+                {
+                    continue;
+                }
+                instances.push(InstanceSnapshot {
+                    handle,
+                    mesh: MeshHandle::from_index(mesh_index).to_js(),
+                    values: mesh_instances.slot_values(slot).to_vec(),
+                });
+            }
+        }
+        RendererSnapshot { instances }
+    }
+
+    pub(crate) fn restore(&mut self, mut snapshot: RendererSnapshot) -> Result<(), JsValue> {
+        // snapshots carry JS-facing handles; validation works on mesh indices
+        for instance in &mut snapshot.instances {
+            instance.mesh = MeshHandle::from_js(instance.mesh)?.index() as u32;
+            InstanceHandle::from_js(instance.handle)?;
+            if matches!(self.meshes.get(instance.mesh as usize), Some(None)) {
+                return Err(error("snapshot references a mesh that was removed"));
+            }
+        }
+        let formats: Vec<Option<InstanceFormat>> = self
+            .meshes
+            .iter()
+            .map(|mesh| mesh.as_ref().map(|mesh| mesh.format))
+            .collect();
+        let strides: Vec<usize> = self
+            .meshes
+            .iter()
+            .map(|mesh| mesh.as_ref().map_or(0, GpuMesh::instance_stride))
+            .collect();
+        snapshot.validate(&strides).map_err(error)?;
+        let mut per_mesh = vec![0usize; formats.len()];
+        for instance in &snapshot.instances {
+            per_mesh[instance.mesh as usize] += 1;
+        }
+        for count in per_mesh {
+            check_draw_budget(count, self.max_instances_per_draw)?;
+        }
+
+        for instances in self.mesh_instances.iter_mut().flatten() {
+            instances.clear();
+        }
+        self.instance_store = InstanceStore::new();
+        self.transient_instances.clear();
+        self.sticky_instances.clear();
+
+        for instance in snapshot.instances {
+            let mesh_index = instance.mesh as usize;
+            let (Some(format), Some(mesh_instances)) =
+                (formats[mesh_index], &mut self.mesh_instances[mesh_index])
+            else {
+                continue; // removed meshes were rejected above
+            };
+            let transform = format.transform(&instance.values);
+            let slot = mesh_instances.allocate(&self.gl, &instance.values)?;
+            mesh_instances.set_handle(slot, instance.handle);
+            let pick_id = [instance.handle as f32];
+            mesh_instances.update_slot(slot, format.floats() + PARAM_PICK_ID, &pick_id)?;
+            let inserted =
+                self.instance_store
+                    .insert_at(instance.handle, mesh_index, slot, transform);
+            debug_assert!(inserted, "snapshot handles are validated as unique");
+        }
+        self.instance_store.rebuild_free_list();
+        Ok(())
+    }
+
+    // Every pass re-binds these, so defaults such as the 1.0 point size reach
+    // the shader without a setter call; an unset u_point_size would read 0.
     fn bind_globals(&self) {
         self.upload_view_matrix();
         self.upload_projection_matrix();
+        self.upload_billboard_facing();
+        self.upload_point_size();
+        self.upload_interpolation_alpha();
     }
 
     fn configure_mesh_attributes(&self) {
@@ -390,42 +2224,108 @@ impl BatchedRendererInner {
         );
     }
 
-    fn configure_instance_attributes(&self) {
-        let stride = (MATRIX_FLOATS * std::mem::size_of::<f32>()) as i32;
-        for (index, &location) in self.instance_locations.iter().enumerate() {
+    fn configure_instance_attributes(
+        &self,
+        format: InstanceFormat,
+        interpolated: bool,
+        extra_columns: usize,
+    ) {
+        let float_bytes = std::mem::size_of::<f32>();
+        let extras_start = format.stride() + previous_floats(interpolated);
+        let stride = ((extras_start + extra_columns * 4) * float_bytes) as i32;
+        let columns = format.floats() / 4;
+        for (index, &location) in self.instance_locations.iter().take(columns).enumerate() {
             let offset = (index * 4 * std::mem::size_of::<f32>()) as i32;
-            self.gl.enable_vertex_attrib_array(location);
-            self.gl
-                .vertex_attrib_pointer_with_i32(location, 4, Gl::FLOAT, false, stride, offset);
-            self.gl.vertex_attrib_divisor(location, 1);
+            self.enable_instance_attribute(location, stride, offset);
+        }
+        let params_offset = (format.floats() * std::mem::size_of::<f32>()) as i32;
+        self.enable_instance_attribute(self.params_location, stride, params_offset);
+        let color_offset = ((format.floats() + PARAM_COLOR) * float_bytes) as i32;
+        self.enable_instance_attribute(self.instance_color_location, stride, color_offset);
+        if interpolated {
+            for (index, &location) in self.previous_locations.iter().enumerate() {
+                let offset = ((format.stride() + index * 4) * float_bytes) as i32;
+                self.enable_instance_attribute(location, stride, offset);
+            }
+        }
+        // the default shader ignores the extras, so their attributes may be compiled out
+        for (index, location) in self.extra_locations.iter().take(extra_columns).enumerate() {
+            if let Some(location) = *location {
+                let offset = ((extras_start + index * 4) * float_bytes) as i32;
+                self.enable_instance_attribute(location, stride, offset);
+            }
         }
     }
 
+    fn enable_instance_attribute(&self, location: u32, stride: i32, offset: i32) {
+        self.gl.enable_vertex_attrib_array(location);
+        self.gl
+            .vertex_attrib_pointer_with_i32(location, 4, Gl::FLOAT, false, stride, offset);
+        self.gl.vertex_attrib_divisor(location, 1);
+    }
+
     fn draw_mesh_instances(&mut self, mesh_index: usize) -> Result<(), JsValue> {
         let mesh = self
             .meshes
             .get(mesh_index)
+            .and_then(Option::as_ref)
             .ok_or_else(|| error("mesh not found"))?;
         let instances = self
             .mesh_instances
             .get_mut(mesh_index)
+            .and_then(Option::as_mut)
             .ok_or_else(|| error("mesh not found"))?;
         instances.flush_pending(&self.gl);
-        if instances.len() == 0 {
+        if instances.draw_count() == 0 {
             return Ok(());
         }
         self.gl.bind_vertex_array(Some(mesh.vao.handle()));
-        self.gl.draw_arrays_instanced(
-            Gl::TRIANGLES,
-            0,
-            mesh.vertex_count,
-            instances.len() as i32,
+        self.gl.uniform1i(
+            Some(&self.instance_format_location),
+            mesh.format.shader_id(),
         );
+        self.gl
+            .uniform1i(Some(&self.interpolate_location), mesh.interpolated as i32);
+        if !mesh.depth_test {
+            self.gl.disable(Gl::DEPTH_TEST);
+        }
+        let draw_count = instances.draw_count();
+        let primitive = mesh.primitive_override.unwrap_or(mesh.primitive);
+        self.gl
+            .draw_arrays_instanced(primitive.gl_mode(), 0, mesh.vertex_count, draw_count as i32);
+        if !mesh.depth_test {
+            self.gl.enable(Gl::DEPTH_TEST);
+        }
+        self.report_gl_error("draw_arrays_instanced");
+        self.frame_stats.draw_calls += 1;
+        self.frame_stats.triangles +=
+            primitive.triangle_count(mesh.vertex_count) * draw_count as u32;
         Ok(())
     }
 
+    fn report_gl_error(&self, operation: &str) {
+        if !self.check_gl_errors {
+            return;
+        }
+        let code = self.gl.get_error();
+        if code != Gl::NO_ERROR {
+            log(&format!("GL error 0x{code:04x} after {operation}"));
+        }
+    }
+
+    // With a layer filter active only the queued instances that were drawn go.
     fn remove_transient_instances(&mut self) {
-        let handles: Vec<u32> = self.transient_instances.drain(..).collect();
+        let mut handles = std::mem::take(&mut self.transient_instances);
+        if let Some(layer) = self.draw_layer {
+            let (drawn, kept) = handles.into_iter().partition(|&handle| {
+                self.instance_store
+                    .get(handle)
+                    .and_then(|record| self.meshes[record.mesh_index].as_ref())
+                    .is_none_or(|mesh| mesh.layer == layer)
+            });
+            handles = drawn;
+            self.transient_instances = kept;
+        }
         for handle in handles {
             let _ = self.remove_instance_internal(handle);
         }
@@ -436,12 +2336,15 @@ impl BatchedRendererInner {
             Some(record) => (record.mesh_index, record.slot_index),
             None => return Ok(false),
         };
-        let moved_handle = self.mesh_instances[mesh_index].remove_slot(slot_index)?;
+        let mesh_instances = self.mesh_instances[mesh_index]
+            .as_mut()
+            .ok_or_else(|| error("invalid mesh handle"))?;
+        let moved_handle = mesh_instances.remove_slot(slot_index)?;
         if let Some(moved) = moved_handle {
             if let Some(record) = self.instance_store.get_mut(moved) {
                 record.slot_index = slot_index;
             }
-            self.mesh_instances[mesh_index].set_handle(slot_index, moved);
+            mesh_instances.set_handle(slot_index, moved);
         }
         self.instance_store.remove(handle);
         Ok(true)
@@ -455,6 +2358,25 @@ impl BatchedRendererInner {
         );
     }
 
+    fn upload_billboard_facing(&self) {
+        self.gl.uniform1i(
+            Some(&self.billboard_facing_location),
+            self.billboard_facing as i32,
+        );
+    }
+
+    fn upload_interpolation_alpha(&self) {
+        self.gl.uniform1f(
+            Some(&self.interpolation_alpha_location),
+            self.interpolation_alpha,
+        );
+    }
+
+    fn upload_point_size(&self) {
+        self.gl
+            .uniform1f(Some(&self.point_size_location), self.point_size);
+    }
+
     fn upload_projection_matrix(&self) {
         self.gl.uniform_matrix4fv_with_f32_array(
             Some(&self.projection_location),
@@ -466,11 +2388,195 @@ impl BatchedRendererInner {
 
 struct GpuMesh {
     vao: VertexArray,
-    _vertex_buffer: GlBuffer,
+    vertex_buffer: GlBuffer,
     vertex_count: i32,
+    format: InstanceFormat,
+    opaque: bool,
+    depth_test: bool,
+    extra_columns: usize, // optional vec4s after the parameter block
+    dynamic: bool,        // vertex buffer is DYNAMIC_DRAW and may be rewritten
+    interpolated: bool,   // a previous matrix follows the parameter block
+    layer: u32,           // render_layer filter; every layer draws in render_pass
+    primitive: Primitive, // from registration
+    primitive_override: Option<Primitive>, // one-shot, cleared after the next pass drawing it
+    bounds: ([f32; 3], [f32; 3]), // local-space AABB, always retained
+    sphere: ([f32; 3], f32), // local-space (center, radius) for culling
+    cpu_data: Option<Mesh>, // kept only when retain_mesh_data is on
+}
+
+impl GpuMesh {
+    fn instance_stride(&self) -> usize {
+        self.extras_offset() + self.extra_columns * 4
+    }
+
+    fn previous_offset(&self) -> usize {
+        self.format.stride()
+    }
+
+    fn extras_offset(&self) -> usize {
+        self.previous_offset() + previous_floats(self.interpolated)
+    }
+}
+
+fn previous_floats(interpolated: bool) -> usize {
+    if interpolated { MATRIX_FLOATS } else { 0 }
+}
+
+#[derive(Clone, Copy, Default)]
+struct FrameStats {
+    draw_calls: u32,
+    triangles: u32,
+    transient_drawn: u32,
+    transient_dropped: u32, // queued past the budget since the previous pass
+    culled: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SceneSummary {
+    meshes: u32,
+    instances: u32,
+    queued: u32,
+    draw_calls_last_frame: u32,
+    triangles_last_frame: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AttributeBudget {
+    max_vertex_attribs: u32,
+    used: u32, // by the widest registered mesh layout
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PipelineState {
+    depth_test: bool,
+    depth_write: bool,
+    cull_mode: &'static str,
+    blend_mode: &'static str, // named after BlendMode variants
+    blend_equation: &'static str,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct MeshOptions {
+    depth_test: bool,
+    extra_columns: usize,
+    dynamic: bool,
+    interpolated: bool,
+    primitive: Primitive,
+}
+
+impl Default for MeshOptions {
+    fn default() -> Self {
+        MeshOptions {
+            depth_test: true,
+            extra_columns: 0,
+            dynamic: false,
+            interpolated: false,
+            primitive: Primitive::Triangles,
+        }
+    }
+}
+
+fn mesh_options(options: &JsValue) -> Result<MeshOptions, JsValue> {
+    let read =
+        |key: &str| Reflect::get(options, &JsValue::from_str(key)).unwrap_or(JsValue::UNDEFINED);
+    let mut parsed = MeshOptions::default();
+    if let Some(depth_test) = read("depthTest").as_bool() {
+        parsed.depth_test = depth_test;
+    }
+    if let Some(dynamic) = read("dynamic").as_bool() {
+        parsed.dynamic = dynamic;
+    }
+    if let Some(interpolated) = read("interpolated").as_bool() {
+        parsed.interpolated = interpolated;
+    }
+    let extra = read("extraColumns");
+    if !extra.is_undefined() {
+        let columns = extra
+            .as_f64()
+            .filter(|value| {
+                value.fract() == 0.0 && (0.0..=MAX_EXTRA_COLUMNS as f64).contains(value)
+            })
+            .ok_or_else(|| error("extraColumns must be an integer between 0 and 4"))?;
+        parsed.extra_columns = columns as usize;
+    }
+    let primitive = read("primitive");
+    if !primitive.is_undefined() {
+        parsed.primitive = match primitive.as_f64() {
+            Some(value) if value == Primitive::Triangles as u32 as f64 => Primitive::Triangles,
+            Some(value) if value == Primitive::Lines as u32 as f64 => Primitive::Lines,
+            Some(value) if value == Primitive::Points as u32 as f64 => Primitive::Points,
+            _ => return Err(error("primitive must be a Primitive value")),
+        };
+    }
+    Ok(parsed)
 }
 
 const INITIAL_INSTANCE_HINT: usize = 256;
+// frame_all looks from the front-right, slightly above
+const FRAME_YAW: f32 = std::f32::consts::FRAC_PI_4;
+const FRAME_PITCH: f32 = 0.5;
+
+fn check_format(mesh_format: InstanceFormat, requested: InstanceFormat) -> Result<(), JsValue> {
+    if mesh_format == requested {
+        return Ok(());
+    }
+    Err(error(match mesh_format {
+        InstanceFormat::Matrix => {
+            "mesh expects matrix instances; use create_instance/set_instance_transform"
+        }
+        InstanceFormat::Billboard => {
+            "mesh was registered for billboards; use create_billboard/set_billboard"
+        }
+        InstanceFormat::Sprite => "mesh was registered for sprites; use create_sprite/set_sprite",
+    }))
+}
+
+fn validated_billboard(
+    billboard: [f32; BILLBOARD_FLOATS],
+) -> Result<[f32; MATRIX_FLOATS], JsValue> {
+    if billboard.iter().any(|value| !value.is_finite()) {
+        return Err(error("billboard position and scale must be finite"));
+    }
+    let [x, y, z, scale] = billboard;
+    Ok(billboard_matrix(x, y, z, scale))
+}
+
+// Mirrors the vertex shader: the billboard's offset is rotated by the inverse
+// view rotation, whose columns are the rows of the view matrix.
+fn facing_billboard_matrix(
+    billboard: &[f32; MATRIX_FLOATS],
+    view: &[f32; MATRIX_FLOATS],
+) -> [f32; MATRIX_FLOATS] {
+    let scale = billboard[0];
+    let mut out = *billboard;
+    for axis in 0..3 {
+        for component in 0..3 {
+            out[axis * 4 + component] = view[component * 4 + axis] * scale;
+        }
+    }
+    out
+}
+
+fn validated_sprite(sprite: [f32; SPRITE_FLOATS]) -> Result<[f32; MATRIX_FLOATS], JsValue> {
+    if sprite.iter().any(|value| !value.is_finite()) {
+        return Err(error("sprite position, rotation, and scale must be finite"));
+    }
+    let [x, y, rotation, scale] = sprite;
+    Ok(sprite_matrix(x, y, rotation, scale))
+}
+
+// Interim guard until draws are split into budget-sized batches.
+fn check_draw_budget(count: usize, budget: usize) -> Result<(), JsValue> {
+    if count > budget {
+        return Err(error(&format!(
+            "a mesh can hold at most {budget} instances (max_instances); remove instances and call defragment_instances, or spread them across more meshes"
+        )));
+    }
+    Ok(())
+}
 
 fn get_i32_parameter(gl: &Gl, param: u32) -> Result<i32, JsValue> {
     Ok(gl
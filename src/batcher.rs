@@ -4,6 +4,91 @@ pub const POSITION_COMPONENTS: usize = 3;
 pub const COLOR_COMPONENTS: usize = 4;
 pub const MESH_VERTEX_STRIDE: usize = POSITION_COMPONENTS + COLOR_COMPONENTS;
 pub const MATRIX_FLOATS: usize = 16;
+pub const MAX_EXTRA_COLUMNS: usize = 4; // optional per-instance vec4s
+pub const BILLBOARD_FLOATS: usize = 4;
+pub const SPRITE_FLOATS: usize = 4;
+// depth bias, flat flag, pick id, reserved, then an rgba instance color
+pub const INSTANCE_PARAM_FLOATS: usize = 8;
+pub const PARAM_DEPTH_BIAS: usize = 0;
+pub const PARAM_FLAT: usize = 1;
+pub const PARAM_PICK_ID: usize = 2; // the instance's own handle
+// handle + 1 has to fit the 24 RGB bits the pick pass writes
+pub const MAX_PICK_HANDLE: u32 = (1 << 24) - 2;
+pub const PARAM_COLOR: usize = 4;
+// white, so unflagged instances keep their vertex colors unchanged
+pub const DEFAULT_INSTANCE_PARAMS: [f32; INSTANCE_PARAM_FLOATS] =
+    [0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstanceFormat {
+    Matrix,    // full column-major mat4
+    Billboard, // (x, y, z, uniform scale)
+    Sprite,    // (x, y, rotation in radians, uniform scale)
+}
+
+impl InstanceFormat {
+    pub fn floats(self) -> usize {
+        match self {
+            InstanceFormat::Matrix => MATRIX_FLOATS,
+            InstanceFormat::Billboard => BILLBOARD_FLOATS,
+            InstanceFormat::Sprite => SPRITE_FLOATS,
+        }
+    }
+
+    // format data followed by the shared per-instance parameters
+    pub fn stride(self) -> usize {
+        self.floats() + INSTANCE_PARAM_FLOATS
+    }
+
+    pub fn shader_id(self) -> i32 {
+        match self {
+            InstanceFormat::Matrix => 0,
+            InstanceFormat::Billboard => 1,
+            InstanceFormat::Sprite => 2,
+        }
+    }
+
+    // expects at least `self.floats()` values
+    pub fn transform(self, values: &[f32]) -> [f32; MATRIX_FLOATS] {
+        match self {
+            InstanceFormat::Matrix => {
+                let mut out = [0.0; MATRIX_FLOATS];
+                out.copy_from_slice(&values[..MATRIX_FLOATS]);
+                out
+            }
+            InstanceFormat::Billboard => {
+                billboard_matrix(values[0], values[1], values[2], values[3])
+            }
+            InstanceFormat::Sprite => sprite_matrix(values[0], values[1], values[2], values[3]),
+        }
+    }
+}
+
+pub fn billboard_matrix(x: f32, y: f32, z: f32, scale: f32) -> [f32; MATRIX_FLOATS] {
+    let mut out = [0.0; MATRIX_FLOATS];
+    out[0] = scale;
+    out[5] = scale;
+    out[10] = scale;
+    out[12] = x;
+    out[13] = y;
+    out[14] = z;
+    out[15] = 1.0;
+    out
+}
+
+pub fn sprite_matrix(x: f32, y: f32, rotation: f32, scale: f32) -> [f32; MATRIX_FLOATS] {
+    let (sin, cos) = rotation.sin_cos();
+    let mut out = [0.0; MATRIX_FLOATS];
+    out[0] = cos * scale;
+    out[1] = sin * scale;
+    out[4] = -sin * scale;
+    out[5] = cos * scale;
+    out[10] = 1.0;
+    out[12] = x;
+    out[13] = y;
+    out[15] = 1.0;
+    out
+}
 
 #[derive(Clone)]
 pub struct Mesh {
@@ -15,7 +100,7 @@ impl Mesh {
         if data.is_empty() {
             return Err("mesh requires at least one vertex");
         }
-        if data.len() % MESH_VERTEX_STRIDE != 0 {
+        if !data.len().is_multiple_of(MESH_VERTEX_STRIDE) {
             return Err("mesh vertices must be (x, y, z, r, g, b, a)");
         }
         Ok(Self { data })
@@ -25,6 +110,154 @@ impl Mesh {
     pub fn raw(&self) -> &[f32] {
         &self.data
     }
+
+    // Axis-aligned (min, max) of the vertex positions.
+    pub fn bounds(&self) -> ([f32; 3], [f32; 3]) {
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        for vertex in self.data.chunks_exact(MESH_VERTEX_STRIDE) {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(vertex[axis]);
+                max[axis] = max[axis].max(vertex[axis]);
+            }
+        }
+        (min, max)
+    }
+
+    // Sphere around the AABB center that holds every vertex.
+    pub fn bounding_sphere(&self) -> ([f32; 3], f32) {
+        let (min, max) = self.bounds();
+        let center: [f32; 3] = std::array::from_fn(|axis| (min[axis] + max[axis]) * 0.5);
+        let radius_sq = self
+            .data
+            .chunks_exact(MESH_VERTEX_STRIDE)
+            .map(|vertex| {
+                (0..3)
+                    .map(|axis| (vertex[axis] - center[axis]).powi(2))
+                    .sum::<f32>()
+            })
+            .fold(0.0, f32::max);
+        (center, radius_sq.sqrt())
+    }
+}
+
+// Bakes every (mesh, transform) pair into one mesh. Transforms that mirror
+// the geometry also reverse its triangles so front faces stay CCW.
+pub fn merge_meshes(parts: &[(&Mesh, [f32; MATRIX_FLOATS])]) -> Result<Mesh, &'static str> {
+    if parts.is_empty() {
+        return Err("merge requires at least one mesh");
+    }
+    let total = parts.iter().map(|(mesh, _)| mesh.raw().len()).sum();
+    let mut out = Vec::with_capacity(total);
+    for (mesh, m) in parts {
+        let start = out.len();
+        for vertex in mesh.raw().chunks_exact(MESH_VERTEX_STRIDE) {
+            let (x, y, z) = (vertex[0], vertex[1], vertex[2]);
+            for axis in 0..3 {
+                out.push(m[axis] * x + m[4 + axis] * y + m[8 + axis] * z + m[12 + axis]);
+            }
+            out.extend_from_slice(&vertex[POSITION_COMPONENTS..]);
+        }
+        if out[start..].iter().any(|value| !value.is_finite()) {
+            return Err("merge transforms must produce finite vertices");
+        }
+        if determinant3(m) < 0.0 {
+            for triangle in out[start..].chunks_exact_mut(3 * MESH_VERTEX_STRIDE) {
+                let (_, rest) = triangle.split_at_mut(MESH_VERTEX_STRIDE);
+                let (b, c) = rest.split_at_mut(MESH_VERTEX_STRIDE);
+                b.swap_with_slice(c);
+            }
+        }
+    }
+    Mesh::new(out)
+}
+
+fn determinant3(m: &[f32; MATRIX_FLOATS]) -> f32 {
+    m[0] * (m[5] * m[10] - m[9] * m[6]) - m[4] * (m[1] * m[10] - m[9] * m[2])
+        + m[8] * (m[1] * m[6] - m[5] * m[2])
+}
+
+// World-space position of an instance, for depth sorting.
+pub fn instance_origin(format: InstanceFormat, values: &[f32]) -> [f32; 3] {
+    match format {
+        InstanceFormat::Matrix => [values[12], values[13], values[14]],
+        InstanceFormat::Billboard => [values[0], values[1], values[2]],
+        InstanceFormat::Sprite => [values[0], values[1], 0.0],
+    }
+}
+
+// World-space bounding sphere of one instance of a mesh whose local sphere is
+// (center, radius). Billboards that face the camera turn with it, so theirs
+// is centered on the instance and wide enough for any turn.
+pub fn instance_sphere(
+    format: InstanceFormat,
+    values: &[f32],
+    (center, radius): ([f32; 3], f32),
+    facing: bool,
+) -> ([f32; 3], f32) {
+    if format == InstanceFormat::Billboard && facing {
+        let reach = center.iter().map(|c| c * c).sum::<f32>().sqrt() + radius;
+        return (instance_origin(format, values), reach * values[3].abs());
+    }
+    let model = format.transform(values);
+    let world = std::array::from_fn(|axis| {
+        model[axis] * center[0]
+            + model[4 + axis] * center[1]
+            + model[8 + axis] * center[2]
+            + model[12 + axis]
+    });
+    let scale = (0..3)
+        .map(|col| {
+            model[col * 4..col * 4 + 3]
+                .iter()
+                .map(|v| v * v)
+                .sum::<f32>()
+                .sqrt()
+        })
+        .fold(0.0, f32::max);
+    (world, radius * scale)
+}
+
+// Inverse of the pick shader's encoding: handle + 1 in the RGB bytes, with
+// 0 (the cleared background) meaning no instance.
+pub fn decode_pick_id([r, g, b, _]: [u8; 4]) -> Option<u32> {
+    let id = r as u32 | (g as u32) << 8 | (b as u32) << 16;
+    id.checked_sub(1)
+}
+
+// Vertex attribute slots one mesh's VAO binds: position and color, one vec4
+// per four instance floats, the previous matrix when interpolated, and the
+// extra columns.
+pub fn vertex_attributes(
+    format: InstanceFormat,
+    interpolated: bool,
+    extra_columns: usize,
+) -> usize {
+    let previous = if interpolated { MATRIX_FLOATS / 4 } else { 0 };
+    2 + format.stride() / 4 + previous + extra_columns
+}
+
+pub fn interleave(positions: &[f32], colors: &[f32]) -> Result<Vec<f32>, &'static str> {
+    if !positions.len().is_multiple_of(POSITION_COMPONENTS) {
+        return Err("positions must contain (x, y, z) per vertex");
+    }
+    if !colors.len().is_multiple_of(COLOR_COMPONENTS) {
+        return Err("colors must contain (r, g, b, a) per vertex");
+    }
+    let vertex_count = positions.len() / POSITION_COMPONENTS;
+    if vertex_count != colors.len() / COLOR_COMPONENTS {
+        return Err("positions and colors must describe the same number of vertices");
+    }
+
+    let mut out = Vec::with_capacity(vertex_count * MESH_VERTEX_STRIDE);
+    for (position, color) in positions
+        .chunks_exact(POSITION_COMPONENTS)
+        .zip(colors.chunks_exact(COLOR_COMPONENTS))
+    {
+        out.extend_from_slice(position);
+        out.extend_from_slice(color);
+    }
+    Ok(out)
 }
 
 #[cfg(test)]
@@ -46,4 +279,122 @@ mod tests {
         assert!(Mesh::new(vec![0.0; 5]).is_err()); // not stride-aligned
         assert!(Mesh::new(sample_vertex_data()).is_ok());
     }
+
+    #[test]
+    fn attribute_slots_per_layout() {
+        assert_eq!(vertex_attributes(InstanceFormat::Matrix, false, 0), 8);
+        assert_eq!(vertex_attributes(InstanceFormat::Billboard, false, 0), 5);
+        // the widest layout exactly fills WebGL2's guaranteed 16 attributes
+        assert_eq!(
+            vertex_attributes(InstanceFormat::Matrix, true, MAX_EXTRA_COLUMNS),
+            16
+        );
+    }
+
+    #[test]
+    fn instance_origins_per_format() {
+        let mut matrix = [0.0; MATRIX_FLOATS];
+        matrix[12..15].copy_from_slice(&[1.0, 2.0, 3.0]);
+        assert_eq!(
+            instance_origin(InstanceFormat::Matrix, &matrix),
+            [1.0, 2.0, 3.0]
+        );
+        let packed = [4.0, 5.0, 6.0, 2.0];
+        assert_eq!(
+            instance_origin(InstanceFormat::Billboard, &packed),
+            [4.0, 5.0, 6.0]
+        );
+        assert_eq!(
+            instance_origin(InstanceFormat::Sprite, &packed),
+            [4.0, 5.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn instance_spheres_follow_the_transform() {
+        let mesh = Mesh::new(sample_vertex_data()).unwrap();
+        let (center, radius) = mesh.bounding_sphere();
+        assert_eq!(center, [0.5, 0.5, 0.0]);
+        assert!((radius - 0.5f32.sqrt()).abs() < 1e-6);
+
+        let sphere = ([1.0, 0.0, 0.0], 1.0);
+        let billboard = [4.0, 5.0, 6.0, 2.0];
+        assert_eq!(
+            instance_sphere(InstanceFormat::Billboard, &billboard, sphere, false),
+            ([6.0, 5.0, 6.0], 2.0)
+        );
+        // facing billboards may turn the offset anywhere around the instance
+        assert_eq!(
+            instance_sphere(InstanceFormat::Billboard, &billboard, sphere, true),
+            ([4.0, 5.0, 6.0], 4.0)
+        );
+        let mut stretched = [0.0; MATRIX_FLOATS];
+        stretched[0] = 3.0;
+        stretched[5] = 1.0;
+        stretched[10] = 1.0;
+        stretched[15] = 1.0;
+        assert_eq!(
+            instance_sphere(InstanceFormat::Matrix, &stretched, sphere, false),
+            ([3.0, 0.0, 0.0], 3.0)
+        );
+    }
+
+    #[test]
+    fn pick_colors_decode_to_handles() {
+        assert_eq!(decode_pick_id([0, 0, 0, 0]), None);
+        assert_eq!(decode_pick_id([1, 0, 0, 255]), Some(0));
+        assert_eq!(decode_pick_id([0, 1, 0, 255]), Some(255));
+        assert_eq!(decode_pick_id([2, 1, 1, 255]), Some(65_537 + 256));
+        assert_eq!(decode_pick_id([255, 255, 255, 255]), Some(MAX_PICK_HANDLE));
+    }
+
+    #[test]
+    fn interleave_layout() {
+        let positions = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let colors = [
+            1.0, 0.0, 0.0, 1.0, //
+            0.0, 1.0, 0.0, 1.0, //
+            0.0, 0.0, 1.0, 1.0, //
+        ];
+        let data = interleave(&positions, &colors).unwrap();
+        assert_eq!(data, sample_vertex_data());
+        assert!(Mesh::new(data).is_ok());
+
+        assert!(interleave(&positions[..8], &colors).is_err());
+        assert!(interleave(&positions, &colors[..11]).is_err());
+        assert!(interleave(&positions[..6], &colors).is_err());
+    }
+
+    #[test]
+    fn merge_bakes_transforms() {
+        let mesh = Mesh::new(sample_vertex_data()).unwrap();
+        let translate = billboard_matrix(2.0, 0.0, 0.0, 1.0);
+        let mut mirror = billboard_matrix(0.0, 0.0, 0.0, 1.0);
+        mirror[0] = -1.0;
+        let merged = merge_meshes(&[(&mesh, translate), (&mesh, mirror)]).unwrap();
+        let data = merged.raw();
+        assert_eq!(data.len(), 2 * sample_vertex_data().len());
+        // first copy: shifted, colors untouched
+        assert_eq!(&data[7..14], &[3.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0]);
+        // mirrored copy: x negated and the last two vertices swapped back to CCW
+        let mirrored = &data[21..];
+        assert_eq!(&mirrored[7..10], &[0.0, 1.0, 0.0]);
+        assert_eq!(&mirrored[14..17], &[-1.0, 0.0, 0.0]);
+
+        assert!(merge_meshes(&[]).is_err());
+        let mut broken = translate;
+        broken[12] = f32::NAN;
+        assert!(merge_meshes(&[(&mesh, broken)]).is_err());
+    }
+
+    #[test]
+    fn sprite_matrix_rotates_then_translates() {
+        let matrix = sprite_matrix(2.0, 3.0, std::f32::consts::FRAC_PI_2, 2.0);
+        // local (1, 0) -> rotated to (0, 2) -> translated to (2, 5)
+        let x = matrix[0] + matrix[12];
+        let y = matrix[1] + matrix[13];
+        assert!((x - 2.0).abs() < 1e-5);
+        assert!((y - 5.0).abs() < 1e-5);
+        assert_eq!(matrix[10], 1.0);
+    }
 }
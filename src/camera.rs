@@ -1,8 +1,47 @@
+use std::f32::consts::PI;
+
+use wasm_bindgen::prelude::*;
+
 use crate::batcher::MATRIX_FLOATS;
+use crate::coords::ndc_to_pixel;
 
 pub const MIN_CAMERA_DISTANCE: f32 = 0.01;
 const MAX_PITCH_ABS: f32 = 1.553343; // ~ +/-89 degrees
 
+// Which world axis points up for orbit cameras. Y-up orbits yaw in the XZ
+// plane (from +X towards +Z); Z-up orbits yaw in the XY plane (from +X
+// towards +Y), as GIS/CAD data expects.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpAxis {
+    YUp,
+    ZUp,
+}
+
+impl UpAxis {
+    fn vector(self) -> [f32; 3] {
+        match self {
+            UpAxis::YUp => [0.0, 1.0, 0.0],
+            UpAxis::ZUp => [0.0, 0.0, 1.0],
+        }
+    }
+
+    // (along the yaw origin, along yaw = 90 degrees, up) -> world
+    fn orbit_to_world(self, [a, b, up]: [f32; 3]) -> [f32; 3] {
+        match self {
+            UpAxis::YUp => [a, up, b],
+            UpAxis::ZUp => [a, b, up],
+        }
+    }
+
+    fn world_to_orbit(self, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        match self {
+            UpAxis::YUp => [x, z, y],
+            UpAxis::ZUp => [x, y, z],
+        }
+    }
+}
+
 pub fn perspective_matrix(
     fov_y_radians: f32,
     aspect: f32,
@@ -30,25 +69,307 @@ pub fn perspective_matrix(
     Ok(out)
 }
 
+// Recovers (near, far) from a matrix built like `perspective_matrix`. Returns
+// None for projections without a perspective divide (orthographic).
+pub fn perspective_depth_range(projection: &[f32; MATRIX_FLOATS]) -> Option<(f32, f32)> {
+    if projection[11] == 0.0 {
+        return None;
+    }
+    let (a, b) = (projection[10], projection[14]);
+    let near = b / (a - 1.0);
+    let far = b / (a + 1.0);
+    (near.is_finite() && far.is_finite() && near > 0.0 && far > near).then_some((near, far))
+}
+
 pub fn orbit_view_matrix(
     target: [f32; 3],
     yaw: f32,
     pitch: f32,
     distance: f32,
+    up: UpAxis,
 ) -> Result<[f32; MATRIX_FLOATS], &'static str> {
     let distance = distance.max(MIN_CAMERA_DISTANCE);
+    // the clamp also keeps the eye off the up axis' poles, where the look-at
+    // basis would be degenerate
     let clamped_pitch = pitch.clamp(-MAX_PITCH_ABS, MAX_PITCH_ABS);
     let cos_pitch = clamped_pitch.cos();
+    let offset = up.orbit_to_world([
+        distance * cos_pitch * yaw.cos(),
+        distance * cos_pitch * yaw.sin(),
+        distance * clamped_pitch.sin(),
+    ]);
     let eye = [
-        target[0] + distance * cos_pitch * yaw.cos(),
-        target[1] + distance * clamped_pitch.sin(),
-        target[2] + distance * cos_pitch * yaw.sin(),
+        target[0] + offset[0],
+        target[1] + offset[1],
+        target[2] + offset[2],
+    ];
+    look_at_matrix(eye, target, up.vector())
+}
+
+// Column-major a * b.
+pub fn multiply(a: &[f32; MATRIX_FLOATS], b: &[f32; MATRIX_FLOATS]) -> [f32; MATRIX_FLOATS] {
+    let mut out = [0.0; MATRIX_FLOATS];
+    for col in 0..4 {
+        for row in 0..4 {
+            out[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+    out
+}
+
+// General 4x4 inverse by Gauss-Jordan elimination with partial pivoting,
+// in f64 so view-projection products keep their precision.
+pub fn invert_matrix(m: &[f32; MATRIX_FLOATS]) -> Result<[f32; MATRIX_FLOATS], &'static str> {
+    const SINGULAR: &str = "matrix is not invertible (determinant is zero)";
+    let scale = m.iter().fold(0.0f64, |max, v| max.max(v.abs() as f64));
+    // rows of [m | identity]
+    let mut a: [[f64; 8]; 4] = std::array::from_fn(|row| {
+        std::array::from_fn(|col| match col {
+            0..4 => m[col * 4 + row] as f64,
+            _ if col - 4 == row => 1.0,
+            _ => 0.0,
+        })
+    });
+    for col in 0..4 {
+        let pivot = (col..4)
+            .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+            .unwrap_or(col);
+        if a[pivot][col].abs() <= scale * f64::EPSILON {
+            return Err(SINGULAR);
+        }
+        a.swap(col, pivot);
+        let recip = a[col][col].recip();
+        a[col].iter_mut().for_each(|value| *value *= recip);
+        let pivot_row = a[col];
+        for (row, values) in a.iter_mut().enumerate() {
+            let factor = values[col];
+            if row != col && factor != 0.0 {
+                values
+                    .iter_mut()
+                    .zip(pivot_row)
+                    .for_each(|(value, p)| *value -= factor * p);
+            }
+        }
+    }
+    let out: [f32; MATRIX_FLOATS] = std::array::from_fn(|i| a[i % 4][4 + i / 4] as f32);
+    if out.iter().any(|value| !value.is_finite()) {
+        return Err(SINGULAR);
+    }
+    Ok(out)
+}
+
+// World-space ray [origin, unit direction] through an NDC point. The origin
+// lies on the near plane and the direction points towards the far plane.
+pub fn ndc_ray(
+    view: &[f32; MATRIX_FLOATS],
+    projection: &[f32; MATRIX_FLOATS],
+    (x, y): (f32, f32),
+) -> Result<[f32; 6], &'static str> {
+    let inverse = invert_matrix(&multiply(projection, view))?;
+    let unproject = |z: f32| {
+        let p: [f32; 4] = std::array::from_fn(|row| {
+            inverse[row] * x + inverse[4 + row] * y + inverse[8 + row] * z + inverse[12 + row]
+        });
+        if p[3].abs() <= f32::EPSILON {
+            return Err("projection has no finite far plane to unproject onto");
+        }
+        Ok([p[0] / p[3], p[1] / p[3], p[2] / p[3]])
+    };
+    let near = unproject(-1.0)?;
+    let direction = normalize(sub(unproject(1.0)?, near))?;
+    Ok([
+        near[0],
+        near[1],
+        near[2],
+        direction[0],
+        direction[1],
+        direction[2],
+    ])
+}
+
+// Rotation-only column-major matrix for a quaternion [x, y, z, w], which is
+// normalised first; translation is zero.
+pub fn quat_to_matrix(q: [f32; 4]) -> Result<[f32; MATRIX_FLOATS], &'static str> {
+    let len_sq: f32 = q.iter().map(|c| c * c).sum();
+    if !len_sq.is_finite() || len_sq <= f32::EPSILON {
+        return Err("quaternion length must be > 0");
+    }
+    let inv_len = len_sq.sqrt().recip();
+    let [x, y, z, w] = q.map(|c| c * inv_len);
+    Ok([
+        1.0 - 2.0 * (y * y + z * z),
+        2.0 * (x * y + z * w),
+        2.0 * (x * z - y * w),
+        0.0,
+        2.0 * (x * y - z * w),
+        1.0 - 2.0 * (x * x + z * z),
+        2.0 * (y * z + x * w),
+        0.0,
+        2.0 * (x * z + y * w),
+        2.0 * (y * z - x * w),
+        1.0 - 2.0 * (x * x + y * y),
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    ])
+}
+
+// Projects an AABB through `clip` and returns the pixel rect [x0, y0, x1, y1]
+// with a top-left origin. The box is clipped against the near plane first:
+// corners behind it are replaced by the points where the box's edges cross
+// it, so a box straddling the camera still gets its full extent. None when
+// the whole box is behind the near plane.
+pub fn screen_bounds(
+    clip: &[f32; MATRIX_FLOATS],
+    (min, max): ([f32; 3], [f32; 3]),
+    (width, height): (f32, f32),
+) -> Option<[f32; 4]> {
+    let corners: [[f32; 4]; 8] = std::array::from_fn(|corner| {
+        let p = aabb_corner((min, max), corner);
+        std::array::from_fn(|row| {
+            clip[row] * p[0] + clip[4 + row] * p[1] + clip[8 + row] * p[2] + clip[12 + row]
+        })
+    });
+    // signed distance to the GL near plane, z = -w
+    let near = |p: &[f32; 4]| p[2] + p[3];
+
+    let mut rect = [
+        f32::INFINITY,
+        f32::INFINITY,
+        f32::NEG_INFINITY,
+        f32::NEG_INFINITY,
     ];
-    let up = [0.0, 1.0, 0.0];
-    look_at_matrix(eye, target, up)
+    let mut visible = false;
+    let mut include = |p: [f32; 4]| {
+        if p[3] <= f32::EPSILON {
+            return;
+        }
+        let (x, y) = ndc_to_pixel(p[0] / p[3], p[1] / p[3], width, height);
+        rect = [
+            rect[0].min(x),
+            rect[1].min(y),
+            rect[2].max(x),
+            rect[3].max(y),
+        ];
+        visible = true;
+    };
+    for (corner, p) in corners.iter().enumerate() {
+        if near(p) >= 0.0 {
+            include(*p);
+        }
+        // each of the 12 edges once, from its lower corner along one axis
+        for axis in [1, 2, 4] {
+            if corner & axis != 0 {
+                continue;
+            }
+            let q = &corners[corner | axis];
+            let (dp, dq) = (near(p), near(q));
+            if (dp < 0.0) != (dq < 0.0) {
+                let t = dp / (dp - dq);
+                include(std::array::from_fn(|i| p[i] + (q[i] - p[i]) * t));
+            }
+        }
+    }
+    visible.then_some(rect)
+}
+
+// World-space AABB of a local AABB after an affine transform.
+pub fn transformed_bounds(
+    model: &[f32; MATRIX_FLOATS],
+    bounds: ([f32; 3], [f32; 3]),
+) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::INFINITY; 3];
+    let mut max = [f32::NEG_INFINITY; 3];
+    for corner in 0..8 {
+        let p = aabb_corner(bounds, corner);
+        for axis in 0..3 {
+            let value = model[axis] * p[0]
+                + model[4 + axis] * p[1]
+                + model[8 + axis] * p[2]
+                + model[12 + axis];
+            min[axis] = min[axis].min(value);
+            max[axis] = max[axis].max(value);
+        }
+    }
+    (min, max)
+}
+
+// Planes (left, right, bottom, top, near, far) of a clip matrix as
+// [a, b, c, d], with a*x + b*y + c*z + d >= 0 inside. Normalized, so the
+// value is a world-space distance.
+pub fn frustum_planes(clip: &[f32; MATRIX_FLOATS]) -> [[f32; 4]; 6] {
+    let row = |r: usize| [clip[r], clip[4 + r], clip[8 + r], clip[12 + r]];
+    let w = row(3);
+    std::array::from_fn(|plane| {
+        let axis = row(plane / 2);
+        let sign = if plane % 2 == 0 { 1.0 } else { -1.0 };
+        let p: [f32; 4] = std::array::from_fn(|i| w[i] + sign * axis[i]);
+        let len = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+        if len > f32::EPSILON {
+            p.map(|c| c / len)
+        } else {
+            p
+        }
+    })
 }
 
-fn look_at_matrix(
+// Conservative: a sphere just outside a frustum corner still passes.
+pub fn sphere_in_frustum(planes: &[[f32; 4]; 6], center: [f32; 3], radius: f32) -> bool {
+    planes
+        .iter()
+        .all(|p| p[0] * center[0] + p[1] * center[1] + p[2] * center[2] + p[3] >= -radius)
+}
+
+// Camera distance at which a sphere of `radius` fits the narrower of the
+// vertical and horizontal fields of view.
+pub fn fit_distance(radius: f32, fov_y_radians: f32, aspect: f32) -> Result<f32, &'static str> {
+    if !fov_y_radians.is_finite() || fov_y_radians <= 0.0 || fov_y_radians >= PI {
+        return Err("fov_y_radians must be between 0 and pi");
+    }
+    if !aspect.is_finite() || aspect <= 0.0 {
+        return Err("aspect ratio must be positive");
+    }
+    let half_y = fov_y_radians * 0.5;
+    let half_x = (half_y.tan() * aspect).atan();
+    Ok((radius / half_y.min(half_x).sin()).max(MIN_CAMERA_DISTANCE))
+}
+
+fn aabb_corner((min, max): ([f32; 3], [f32; 3]), corner: usize) -> [f32; 3] {
+    [
+        if corner & 1 == 0 { min[0] } else { max[0] },
+        if corner & 2 == 0 { min[1] } else { max[1] },
+        if corner & 4 == 0 { min[2] } else { max[2] },
+    ]
+}
+
+// Inverse of `orbit_view_matrix` for a known target: recovers the eye from the
+// view's rotation and translation, then reads yaw/pitch/distance off the
+// eye's offset from the target.
+pub fn decompose_orbit(
+    view: &[f32; MATRIX_FLOATS],
+    target: [f32; 3],
+    up: UpAxis,
+) -> Result<(f32, f32, f32), &'static str> {
+    let translation = [view[12], view[13], view[14]];
+    // eye = -R^T * t, where the rows of R are right / up / back
+    let eye: [f32; 3] = std::array::from_fn(|axis| {
+        -(view[axis * 4] * translation[0]
+            + view[axis * 4 + 1] * translation[1]
+            + view[axis * 4 + 2] * translation[2])
+    });
+    let offset = up.world_to_orbit(sub(eye, target));
+    let distance = dot(offset, offset).sqrt();
+    if !distance.is_finite() || distance <= f32::EPSILON {
+        return Err("view matrix places the eye on the target");
+    }
+    let pitch = (offset[2] / distance).clamp(-1.0, 1.0).asin();
+    let yaw = offset[1].atan2(offset[0]);
+    Ok((yaw, pitch, distance))
+}
+
+pub fn look_at_matrix(
     eye: [f32; 3],
     target: [f32; 3],
     up: [f32; 3],
@@ -99,3 +420,200 @@ fn normalize(v: [f32; 3]) -> Result<[f32; 3], &'static str> {
     let inv_len = len_sq.sqrt().recip();
     Ok([v[0] * inv_len, v[1] * inv_len, v[2] * inv_len])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_range_round_trip() {
+        let projection = perspective_matrix(1.0, 1.5, 0.1, 250.0).unwrap();
+        let (near, far) = perspective_depth_range(&projection).unwrap();
+        assert!((near - 0.1).abs() < 1e-4);
+        assert!((far - 250.0).abs() / 250.0 < 1e-3);
+
+        let mut orthographic = [0.0; MATRIX_FLOATS];
+        orthographic[15] = 1.0;
+        assert_eq!(perspective_depth_range(&orthographic), None);
+    }
+
+    #[test]
+    fn screen_bounds_project_corners() {
+        let mut identity = [0.0; MATRIX_FLOATS];
+        for i in 0..4 {
+            identity[i * 5] = 1.0;
+        }
+        let half = ([-0.5, -0.5, -0.5], [0.5, 0.5, 0.5]);
+        assert_eq!(
+            screen_bounds(&identity, half, (200.0, 100.0)),
+            Some([50.0, 25.0, 150.0, 75.0])
+        );
+
+        // a box straddling the camera is clipped at the near plane, where it
+        // fills far more than the corners in front alone would cover
+        let projection = perspective_matrix(1.0, 1.0, 0.1, 100.0).unwrap();
+        let straddling = ([-1.0, -1.0, -5.0], [1.0, 1.0, 5.0]);
+        let rect = screen_bounds(&projection, straddling, (100.0, 100.0)).unwrap();
+        assert!(rect.iter().all(|v| v.is_finite()));
+        assert!(rect[0] < 0.0 && rect[1] < 0.0 && rect[2] > 100.0 && rect[3] > 100.0);
+        let behind = ([-1.0, -1.0, 1.0], [1.0, 1.0, 5.0]);
+        assert_eq!(screen_bounds(&projection, behind, (100.0, 100.0)), None);
+
+        let translate = {
+            let mut m = identity;
+            m[12] = 0.5;
+            m
+        };
+        assert_eq!(multiply(&identity, &translate), translate);
+    }
+
+    #[test]
+    fn quaternions_become_rotations() {
+        let mut identity = [0.0; MATRIX_FLOATS];
+        for i in 0..4 {
+            identity[i * 5] = 1.0;
+        }
+        assert_eq!(quat_to_matrix([0.0, 0.0, 0.0, 1.0]), Ok(identity));
+        // the length is normalised away
+        assert_eq!(quat_to_matrix([0.0, 0.0, 0.0, 3.0]), Ok(identity));
+
+        // 180 degrees about Y flips X and Z and keeps Y
+        let turned = quat_to_matrix([0.0, 1.0, 0.0, 0.0]).unwrap();
+        assert_eq!(&turned[0..3], &[-1.0, 0.0, 0.0]);
+        assert_eq!(&turned[4..7], &[0.0, 1.0, 0.0]);
+        assert_eq!(&turned[8..11], &[0.0, 0.0, -1.0]);
+        assert_eq!(&turned[12..16], &[0.0, 0.0, 0.0, 1.0]);
+
+        // 90 degrees about Z sends +X to +Y
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let quarter = quat_to_matrix([0.0, 0.0, half, half]).unwrap();
+        assert!((quarter[1] - 1.0).abs() < 1e-6 && quarter[0].abs() < 1e-6);
+
+        assert!(quat_to_matrix([0.0; 4]).is_err());
+    }
+
+    #[test]
+    fn inverses_undo_the_matrix() {
+        let mut identity = [0.0; MATRIX_FLOATS];
+        for i in 0..4 {
+            identity[i * 5] = 1.0;
+        }
+        assert_eq!(invert_matrix(&identity), Ok(identity));
+
+        let view = orbit_view_matrix([1.0, -2.0, 0.5], 0.3, 0.4, 5.0, UpAxis::YUp).unwrap();
+        let projection = perspective_matrix(1.0, 1.5, 0.1, 250.0).unwrap();
+        let clip = multiply(&projection, &view);
+        let product = multiply(&clip, &invert_matrix(&clip).unwrap());
+        for (value, expected) in product.iter().zip(identity) {
+            assert!((value - expected).abs() < 1e-4, "{product:?}");
+        }
+
+        assert!(invert_matrix(&[0.0; MATRIX_FLOATS]).is_err());
+        let mut flat = identity;
+        flat[10] = 0.0; // squashes z
+        assert!(invert_matrix(&flat).is_err());
+    }
+
+    #[test]
+    fn rays_leave_through_the_pixel() {
+        let view = look_at_matrix([0.0, 0.0, 5.0], [0.0; 3], [0.0, 1.0, 0.0]).unwrap();
+        let projection = perspective_matrix(1.0, 1.0, 0.1, 100.0).unwrap();
+        let ray = ndc_ray(&view, &projection, (0.0, 0.0)).unwrap();
+        let expected = [0.0, 0.0, 4.9, 0.0, 0.0, -1.0];
+        for (value, expected) in ray.iter().zip(expected) {
+            assert!((value - expected).abs() < 1e-4, "{ray:?}");
+        }
+        // the top-right corner ray heads up and to the right
+        let corner = ndc_ray(&view, &projection, (1.0, 1.0)).unwrap();
+        assert!(corner[3] > 0.0 && corner[4] > 0.0 && corner[5] < 0.0);
+    }
+
+    #[test]
+    fn look_at_from_positive_z() {
+        let view = look_at_matrix([0.0, 0.0, 5.0], [0.0; 3], [0.0, 1.0, 0.0]).unwrap();
+        let mut expected = [0.0; MATRIX_FLOATS];
+        for i in 0..4 {
+            expected[i * 5] = 1.0;
+        }
+        expected[14] = -5.0;
+        assert_eq!(view, expected);
+        // an up vector along the view direction has no well-defined right axis
+        assert!(look_at_matrix([0.0, 0.0, 5.0], [0.0; 3], [0.0, 0.0, 1.0]).is_err());
+        assert!(look_at_matrix([1.0; 3], [1.0; 3], [0.0, 1.0, 0.0]).is_err());
+    }
+
+    #[test]
+    fn orbit_round_trip() {
+        let target = [1.0, -2.0, 0.5];
+        for up in [UpAxis::YUp, UpAxis::ZUp] {
+            for (yaw, pitch, distance) in [(0.3, 0.4, 5.0), (-2.5, -1.2, 0.75), (3.0, 0.0, 120.0)] {
+                let view = orbit_view_matrix(target, yaw, pitch, distance, up).unwrap();
+                let (y, p, d) = decompose_orbit(&view, target, up).unwrap();
+                assert!((y - yaw).abs() < 1e-4, "yaw {y} != {yaw}");
+                assert!((p - pitch).abs() < 1e-4, "pitch {p} != {pitch}");
+                assert!(
+                    (d - distance).abs() / distance < 1e-4,
+                    "distance {d} != {distance}"
+                );
+            }
+        }
+        let view = orbit_view_matrix(target, 0.0, 0.0, 2.0, UpAxis::YUp).unwrap();
+        let eye = [target[0] + 2.0, target[1], target[2]];
+        assert!(decompose_orbit(&view, eye, UpAxis::YUp).is_err());
+    }
+
+    #[test]
+    fn z_up_orbit_keeps_z_vertical() {
+        let view = orbit_view_matrix([0.0; 3], 0.7, 0.3, 4.0, UpAxis::ZUp).unwrap();
+        // world +Z maps onto screen-up, and the screen's right axis stays level
+        let z_in_view = [view[8], view[9], view[10]];
+        assert!(
+            z_in_view[1] > 0.9,
+            "z should point up on screen: {z_in_view:?}"
+        );
+        assert!(
+            z_in_view[0].abs() < 1e-6,
+            "the right axis must stay horizontal"
+        );
+        // straight overhead is clamped instead of producing a degenerate basis
+        assert!(orbit_view_matrix([0.0; 3], 0.0, PI / 2.0, 4.0, UpAxis::ZUp).is_ok());
+    }
+
+    #[test]
+    fn fitting_frames_bounds() {
+        let mut model = [0.0; MATRIX_FLOATS];
+        model[0] = 2.0;
+        model[5] = 1.0;
+        model[10] = 1.0;
+        model[12] = 3.0;
+        model[15] = 1.0;
+        let (min, max) = transformed_bounds(&model, ([-1.0; 3], [1.0; 3]));
+        assert_eq!((min, max), ([1.0, -1.0, -1.0], [5.0, 1.0, 1.0]));
+
+        let vertical = fit_distance(1.0, PI / 3.0, 2.0).unwrap();
+        assert!((vertical - 2.0).abs() < 1e-4);
+        // a tall viewport is limited by the horizontal field of view
+        assert!(fit_distance(1.0, PI / 3.0, 0.5).unwrap() > vertical);
+        assert!(fit_distance(1.0, PI, 1.0).is_err());
+    }
+
+    #[test]
+    fn spheres_against_the_frustum() {
+        let mut identity = [0.0; MATRIX_FLOATS];
+        for i in 0..4 {
+            identity[i * 5] = 1.0;
+        }
+        let cube = frustum_planes(&identity);
+        assert!(sphere_in_frustum(&cube, [0.0; 3], 0.0));
+        assert!(!sphere_in_frustum(&cube, [2.0, 0.0, 0.0], 0.5));
+        assert!(sphere_in_frustum(&cube, [2.0, 0.0, 0.0], 1.5));
+        assert!(!sphere_in_frustum(&cube, [0.0, 0.0, -3.0], 1.0));
+
+        let projection = perspective_matrix(1.0, 1.0, 0.1, 100.0).unwrap();
+        let planes = frustum_planes(&projection);
+        assert!(sphere_in_frustum(&planes, [0.0, 0.0, -5.0], 0.1));
+        assert!(!sphere_in_frustum(&planes, [0.0, 0.0, 5.0], 1.0));
+        assert!(!sphere_in_frustum(&planes, [0.0, 0.0, -200.0], 1.0));
+        assert!(!sphere_in_frustum(&planes, [50.0, 0.0, -5.0], 1.0));
+    }
+}
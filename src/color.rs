@@ -0,0 +1,154 @@
+#![cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+
+// Parses `#rgb`, `#rgba`, `#rrggbb`, and `#rrggbbaa` into 0..1 floats.
+pub fn parse_hex(input: &str) -> Result<[f32; 4], &'static str> {
+    let digits = input
+        .trim()
+        .strip_prefix('#')
+        .ok_or("hex colors must start with '#'")?;
+    if !digits.is_ascii() {
+        return Err("hex colors may only contain 0-9 and a-f");
+    }
+    let channels: Vec<u8> = match digits.len() {
+        3 | 4 => digits
+            .chars()
+            .map(|c| hex_digit(c).map(|value| value * 17))
+            .collect::<Result<_, _>>()?,
+        6 | 8 => (0..digits.len())
+            .step_by(2)
+            .map(|i| {
+                let bytes = digits.as_bytes();
+                Ok(hex_digit(bytes[i] as char)? * 16 + hex_digit(bytes[i + 1] as char)?)
+            })
+            .collect::<Result<_, &'static str>>()?,
+        _ => return Err("hex colors must have 3, 4, 6, or 8 digits"),
+    };
+    let mut color = [1.0; 4];
+    for (slot, value) in color.iter_mut().zip(&channels) {
+        *slot = *value as f32 / 255.0;
+    }
+    Ok(color)
+}
+
+fn hex_digit(c: char) -> Result<u8, &'static str> {
+    c.to_digit(16)
+        .map(|value| value as u8)
+        .ok_or("hex colors may only contain 0-9 and a-f")
+}
+
+// Hue in degrees (wrapped), saturation and lightness in 0..1 (clamped).
+pub fn hsl_to_rgba(h: f32, s: f32, l: f32) -> Result<[f32; 4], &'static str> {
+    check_finite(h, s, l)?;
+    Ok(from_hsl_unchecked(h, s.clamp(0.0, 1.0), l.clamp(0.0, 1.0)))
+}
+
+// Hue in degrees (wrapped), saturation and value in 0..1 (clamped).
+pub fn hsv_to_rgba(h: f32, s: f32, v: f32) -> Result<[f32; 4], &'static str> {
+    check_finite(h, s, v)?;
+    let (s, v) = (s.clamp(0.0, 1.0), v.clamp(0.0, 1.0));
+    let chroma = v * s;
+    Ok(from_chroma(h, chroma, v - chroma))
+}
+
+const GOLDEN_RATIO_CONJUGATE: f32 = 0.618_034;
+const MAX_PALETTE: usize = 1000;
+
+// The `index`th color of the default palette. Hues step by the golden ratio
+// around the wheel, so neighbours always land far apart whatever the count.
+pub fn palette_color(index: usize) -> [f32; 4] {
+    let hue = (index as f32 * GOLDEN_RATIO_CONJUGATE).fract() * 360.0;
+    from_hsl_unchecked(hue, 0.65, 0.55)
+}
+
+pub fn palette(count: usize) -> Result<Vec<[f32; 4]>, &'static str> {
+    if count > MAX_PALETTE {
+        return Err("palette count must be at most 1000");
+    }
+    Ok((0..count).map(palette_color).collect())
+}
+
+fn from_hsl_unchecked(h: f32, s: f32, l: f32) -> [f32; 4] {
+    let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    from_chroma(h, chroma, l - chroma / 2.0)
+}
+
+fn check_finite(h: f32, a: f32, b: f32) -> Result<(), &'static str> {
+    if !(h.is_finite() && a.is_finite() && b.is_finite()) {
+        return Err("color components must be finite");
+    }
+    Ok(())
+}
+
+fn from_chroma(h: f32, chroma: f32, offset: f32) -> [f32; 4] {
+    let sector = h.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    [r + offset, g + offset, b + offset, 1.0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_forms() {
+        assert_eq!(parse_hex("#ffffff"), Ok([1.0, 1.0, 1.0, 1.0]));
+        assert_eq!(parse_hex("#000"), Ok([0.0, 0.0, 0.0, 1.0]));
+        assert_eq!(parse_hex("#f00c"), parse_hex("#ff0000cc"));
+        let [r, g, b, a] = parse_hex(" #3366CC80 ").unwrap();
+        assert_eq!((r, g, b), (0.2, 0.4, 0.8));
+        assert!((a - 128.0 / 255.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn hsl_and_hsv_known_colors() {
+        assert_eq!(hsl_to_rgba(0.0, 1.0, 0.5), Ok([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(hsl_to_rgba(120.0, 1.0, 0.5), Ok([0.0, 1.0, 0.0, 1.0]));
+        assert_eq!(hsl_to_rgba(-120.0, 1.0, 0.5), Ok([0.0, 0.0, 1.0, 1.0]));
+        assert_eq!(hsl_to_rgba(200.0, 0.0, 0.25), Ok([0.25, 0.25, 0.25, 1.0]));
+        assert_eq!(hsl_to_rgba(60.0, 1.0, 1.0), Ok([1.0, 1.0, 1.0, 1.0]));
+        assert_eq!(hsv_to_rgba(360.0, 1.0, 1.0), Ok([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(hsv_to_rgba(60.0, 1.0, 1.0), Ok([1.0, 1.0, 0.0, 1.0]));
+        assert_eq!(hsv_to_rgba(300.0, 0.5, 1.0), Ok([1.0, 0.5, 1.0, 1.0]));
+        assert!(hsl_to_rgba(f32::NAN, 1.0, 0.5).is_err());
+    }
+
+    #[test]
+    fn palette_is_deterministic_and_spread() {
+        let colors = palette(8).unwrap();
+        assert_eq!(colors, palette(8).unwrap());
+        assert_eq!(colors[3], palette_color(3));
+        assert_eq!(colors[0], hsl_to_rgba(0.0, 0.65, 0.55).unwrap());
+        for (index, color) in colors.iter().enumerate() {
+            assert_eq!(color[3], 1.0);
+            assert!(color[..3].iter().all(|c| (0.0..=1.0).contains(c)));
+            // adjacent entries differ clearly in at least one channel
+            if let Some(next) = colors.get(index + 1) {
+                let spread = (0..3)
+                    .map(|c| (color[c] - next[c]).abs())
+                    .fold(0.0, f32::max);
+                assert!(spread > 0.3, "{index}: {spread}");
+            }
+        }
+        assert_eq!(palette(1000).map(|colors| colors.len()), Ok(1000));
+        assert!(palette(1001).is_err());
+        assert!(palette(u32::MAX as usize).is_err());
+    }
+
+    #[test]
+    fn hex_rejects_malformed() {
+        assert!(parse_hex("ffffff").is_err());
+        assert!(parse_hex("#ffff").is_ok());
+        assert!(parse_hex("#fffff").is_err());
+        assert!(parse_hex("#ggg").is_err());
+        assert!(parse_hex("#").is_err());
+        assert!(parse_hex("#ééé").is_err());
+    }
+}
@@ -1,76 +1,375 @@
-use std::cell::RefCell;
+use std::cell::{RefCell, RefMut};
 use std::rc::{Rc, Weak};
 
+use js_sys::{Float32Array, Function};
 use wasm_bindgen::prelude::*;
+use web_sys::{OffscreenCanvas, WebGlTexture};
 
 use crate::batched::{BatchedRenderer, BatchedRendererInner};
-use crate::context::{shared_context, SharedContext};
+use crate::color;
+use crate::context::{SharedContext, Viewport, shared_context, shared_context_offscreen};
+use crate::frame_loop::FrameLoop;
+use crate::fullscreen::FullscreenQuad;
 use crate::timeseries::{TimeSeriesRenderer, TimeSeriesRendererInner};
-use crate::utils::{clamp_unit, error};
+use crate::utils::{clamp_unit, error, read_fixed};
 
 #[wasm_bindgen]
 pub struct CanvasComposer {
-    context: SharedContext,
-    passes: Vec<RenderPass>,
-    clear_color: [f32; 4],
-    clear_depth: f32,
+    inner: Rc<RefCell<ComposerInner>>,
 }
 
 #[wasm_bindgen]
 impl CanvasComposer {
     #[wasm_bindgen(constructor)]
     pub fn new(canvas_id: &str) -> Result<CanvasComposer, JsValue> {
-        let context = shared_context(canvas_id)?;
-        Ok(CanvasComposer {
-            context,
-            passes: Vec::new(),
-            clear_color: [0.02, 0.02, 0.05, 1.0],
-            clear_depth: 1.0,
-        })
+        Self::with_context(shared_context(canvas_id)?)
     }
 
-    pub fn add_batched_pass(&mut self) -> Result<BatchedRenderer, JsValue> {
-        let renderer = BatchedRenderer::with_shared_context(self.context.clone())?;
-        self.passes
-            .push(RenderPass::Batched(PassHandle::new(&renderer.inner())));
+    pub fn from_offscreen(canvas: OffscreenCanvas) -> Result<CanvasComposer, JsValue> {
+        Self::with_context(shared_context_offscreen(canvas)?)
+    }
+
+    pub fn add_batched_pass(&self) -> Result<BatchedRenderer, JsValue> {
+        let mut inner = self.inner_mut();
+        let renderer = BatchedRenderer::with_shared_context(inner.context.clone())?;
+        inner.passes.push(Some(RenderPass::Batched(PassHandle::new(
+            &renderer.inner(),
+        ))));
         Ok(renderer)
     }
 
-    pub fn add_timeseries_pass(&mut self) -> Result<TimeSeriesRenderer, JsValue> {
-        let renderer = TimeSeriesRenderer::with_shared_context(self.context.clone())?;
-        self.passes
-            .push(RenderPass::TimeSeries(PassHandle::new(&renderer.inner())));
+    // Schedules one more pass over an existing batched renderer that draws
+    // only its meshes tagged with `layer` (see `set_mesh_layer`).
+    pub fn add_layer_pass(&self, renderer: &BatchedRenderer, layer: u32) {
+        self.inner_mut().passes.push(Some(RenderPass::BatchedLayer(
+            PassHandle::new(&renderer.inner()),
+            layer,
+        )));
+    }
+
+    // Schedules a renderer created on its own (e.g. `new BatchedRenderer`)
+    // as a pass. It must draw to this composer's canvas.
+    pub fn adopt_batched(&self, renderer: &BatchedRenderer) -> Result<(), JsValue> {
+        let mut inner = self.inner_mut();
+        inner.check_adoptable(&renderer.context_handle())?;
+        let renderer = renderer.inner();
+        let scheduled = inner.passes.iter().flatten().any(|pass| match pass {
+            RenderPass::Batched(handle) => handle.points_to(&renderer),
+            _ => false,
+        });
+        if scheduled {
+            return Err(error("renderer is already a pass of this composer"));
+        }
+        inner
+            .passes
+            .push(Some(RenderPass::Batched(PassHandle::new(&renderer))));
+        Ok(())
+    }
+
+    pub fn adopt_timeseries(&self, renderer: &TimeSeriesRenderer) -> Result<(), JsValue> {
+        let mut inner = self.inner_mut();
+        inner.check_adoptable(&renderer.context_handle())?;
+        let renderer = renderer.inner();
+        let scheduled = inner.passes.iter().flatten().any(|pass| match pass {
+            RenderPass::TimeSeries(handle) => handle.points_to(&renderer),
+            _ => false,
+        });
+        if scheduled {
+            return Err(error("renderer is already a pass of this composer"));
+        }
+        inner
+            .passes
+            .push(Some(RenderPass::TimeSeries(PassHandle::new(&renderer))));
+        Ok(())
+    }
+
+    pub fn add_timeseries_pass(&self) -> Result<TimeSeriesRenderer, JsValue> {
+        let mut inner = self.inner_mut();
+        let renderer = TimeSeriesRenderer::with_shared_context(inner.context.clone())?;
+        inner
+            .passes
+            .push(Some(RenderPass::TimeSeries(PassHandle::new(
+                &renderer.inner(),
+            ))));
         Ok(renderer)
     }
 
-    pub fn set_clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
-        self.clear_color = [clamp_unit(r), clamp_unit(g), clamp_unit(b), clamp_unit(a)];
+    pub fn set_clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.inner_mut().clear_color = [clamp_unit(r), clamp_unit(g), clamp_unit(b), clamp_unit(a)];
+    }
+
+    pub fn set_clear_color_hex(&self, hex: &str) -> Result<(), JsValue> {
+        self.inner_mut().clear_color = color::parse_hex(hex).map_err(error)?;
+        Ok(())
+    }
+
+    // The texture stays owned by the caller; pass undefined to go back to
+    // the solid clear color.
+    pub fn set_clear_texture(&self, texture: Option<WebGlTexture>) -> Result<(), JsValue> {
+        let mut inner = self.inner_mut();
+        if texture.is_some() && inner.fullscreen.is_none() {
+            inner.fullscreen = Some(FullscreenQuad::new(&inner.context.gl_clone())?);
+        }
+        inner.clear_texture = texture;
+        Ok(())
     }
 
-    pub fn set_clear_depth(&mut self, depth: f32) -> Result<(), JsValue> {
+    pub fn set_clear_depth(&self, depth: f32) -> Result<(), JsValue> {
         if !depth.is_finite() {
             return Err(error("clear depth must be finite"));
         }
-        self.clear_depth = depth.clamp(0.0, 1.0);
+        self.inner_mut().clear_depth = depth.clamp(0.0, 1.0);
         Ok(())
     }
 
+    pub fn set_clear_stencil(&self, stencil: Option<i32>) {
+        self.inner_mut().clear_stencil = stencil;
+    }
+
+    pub fn set_pass_enabled(&self, index: u32, enabled: bool) -> Result<(), JsValue> {
+        self.inner_mut().pass_mut(index)?.set_enabled(enabled);
+        Ok(())
+    }
+
+    // Buffers to clear right before pass `index` draws, limited to the
+    // pass's viewport if it has one; undefined leaves that buffer alone.
+    pub fn set_pass_clear(
+        &self,
+        index: u32,
+        color: Option<Float32Array>,
+        depth: Option<f32>,
+    ) -> Result<(), JsValue> {
+        let color = color
+            .map(|color| read_fixed::<4>(&color, "pass clear color"))
+            .transpose()?
+            .map(|color| color.map(clamp_unit));
+        if depth.is_some_and(|depth| !depth.is_finite()) {
+            return Err(error("clear depth must be finite"));
+        }
+        let clear = PassClear {
+            color,
+            depth: depth.map(|depth| depth.clamp(0.0, 1.0)),
+        };
+        self.inner_mut().pass_mut(index)?.set_clear(clear);
+        Ok(())
+    }
+
+    // Every pass ever scheduled, freed ones included, since pass indices
+    // never shift.
+    pub fn pass_count(&self) -> u32 {
+        self.inner.borrow().passes.len() as u32
+    }
+
     pub fn resize(&self, width: u32, height: u32) {
-        self.context.resize(width, height);
+        self.inner.borrow().context.resize(width, height);
+    }
+
+    pub fn set_max_dimension(&self, max: u32) -> Result<(), JsValue> {
+        self.inner.borrow().context.set_max_dimension(max)
+    }
+
+    pub fn is_usable(&self) -> bool {
+        self.inner.borrow().context.is_usable()
+    }
+
+    pub fn reset_viewport(&self) {
+        self.inner.borrow().context.reset_viewport();
+    }
+
+    pub fn flush(&self) {
+        self.inner.borrow().context.flush();
+    }
+
+    pub fn finish(&self) {
+        self.inner.borrow().context.finish();
+    }
+
+    pub fn render(&self) -> Result<(), JsValue> {
+        self.inner.borrow_mut().render()
+    }
+
+    // Renders only if a pass, the composer's own settings, or the canvas size
+    // changed since the last render. Returns whether a frame was drawn.
+    pub fn render_if_dirty(&self) -> Result<bool, JsValue> {
+        let mut inner = self.inner.borrow_mut();
+        if !inner.is_dirty() {
+            return Ok(false);
+        }
+        inner.render()?;
+        Ok(true)
+    }
+
+    // Drives `callback(timestamp)` followed by `render()` from
+    // requestAnimationFrame until `stop_loop` is called with the returned
+    // handle. A throwing callback or pass stops the loop.
+    pub fn start_loop(&self, callback: Function) -> Result<u32, JsValue> {
+        let mut inner = self.inner.borrow_mut();
+        let handle = inner.next_loop;
+        inner.next_loop += 1;
+        let frame_loop = FrameLoop::start(Rc::downgrade(&self.inner), handle, callback)?;
+        inner.loops.push(frame_loop);
+        Ok(handle)
+    }
+
+    // Caps how often `start_loop` loops run their callback and render;
+    // `undefined` runs them on every animation frame.
+    pub fn set_target_fps(&self, fps: Option<u32>) -> Result<(), JsValue> {
+        if fps == Some(0) {
+            return Err(error("target fps must be positive"));
+        }
+        self.inner.borrow_mut().target_fps = fps;
+        Ok(())
+    }
+
+    pub fn stop_loop(&self, handle: u32) -> Result<(), JsValue> {
+        let mut inner = self.inner.borrow_mut();
+        let index = inner
+            .loops
+            .iter()
+            .position(|frame_loop| frame_loop.handle() == handle)
+            .ok_or_else(|| error("invalid loop handle"))?;
+        inner.loops.remove(index).stop();
+        Ok(())
+    }
+}
+
+impl CanvasComposer {
+    fn inner_mut(&self) -> RefMut<'_, ComposerInner> {
+        let mut inner = self.inner.borrow_mut();
+        inner.dirty = true;
+        inner
+    }
+
+    fn with_context(context: SharedContext) -> Result<CanvasComposer, JsValue> {
+        let inner = ComposerInner {
+            context,
+            passes: Vec::new(),
+            clear_color: [0.02, 0.02, 0.05, 1.0],
+            clear_depth: 1.0,
+            clear_stencil: None,
+            clear_texture: None,
+            fullscreen: None,
+            loops: Vec::new(),
+            next_loop: 1,
+            target_fps: None,
+            dirty: true,
+            rendered_size: None,
+        };
+        Ok(CanvasComposer {
+            inner: Rc::new(RefCell::new(inner)),
+        })
+    }
+}
+
+pub(crate) struct ComposerInner {
+    context: SharedContext,
+    passes: Vec<Option<RenderPass>>, // None once a pass's renderer is freed
+    clear_color: [f32; 4],
+    clear_depth: f32,
+    clear_stencil: Option<i32>,
+    clear_texture: Option<WebGlTexture>,
+    fullscreen: Option<FullscreenQuad>, // built on first use
+    loops: Vec<FrameLoop>,
+    next_loop: u32,
+    target_fps: Option<u32>,
+    dirty: bool, // composer settings changed since the last render
+    rendered_size: Option<(u32, u32)>,
+}
+
+impl ComposerInner {
+    pub(crate) fn render(&mut self) -> Result<(), JsValue> {
+        if !self.context.check_usable() {
+            return Ok(());
+        }
+        self.context
+            .clear(self.clear_color, Some(self.clear_depth), self.clear_stencil);
+        if let (Some(texture), Some(fullscreen)) = (&self.clear_texture, &self.fullscreen) {
+            fullscreen.draw_texture(texture);
+        }
+        self.passes
+            .iter()
+            .flatten()
+            .for_each(|pass| pass.set_composing(true));
+        let result = self
+            .passes
+            .iter()
+            .flatten()
+            .filter(|pass| pass.is_enabled())
+            .try_for_each(|pass| {
+                let clear = pass.clear();
+                if clear.color.is_some() || clear.depth.is_some() {
+                    self.context
+                        .clear_region(pass.viewport(), clear.color, clear.depth);
+                }
+                pass.render()
+            });
+        self.passes
+            .iter()
+            .flatten()
+            .for_each(|pass| pass.set_composing(false));
+        result?;
+        // the slot stays, so later passes keep their indices
+        for slot in &mut self.passes {
+            if slot.as_ref().is_some_and(|pass| !pass.is_alive()) {
+                *slot = None;
+            }
+        }
+        self.dirty = false;
+        self.rendered_size = Some(self.context.size());
+        Ok(())
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty
+            || self.rendered_size != Some(self.context.size())
+            || self
+                .passes
+                .iter()
+                .flatten()
+                .any(|pass| !pass.is_alive() || (pass.is_enabled() && pass.is_dirty()))
     }
 
-    pub fn render(&mut self) -> Result<(), JsValue> {
-        self.context.clear(self.clear_color, Some(self.clear_depth));
-        for pass in &self.passes {
-            pass.render()?;
+    // Freed passes throw like unknown ones, whether or not a render has
+    // cleared their slot yet.
+    fn pass_mut(&mut self, index: u32) -> Result<&mut RenderPass, JsValue> {
+        self.passes
+            .get_mut(index as usize)
+            .and_then(Option::as_mut)
+            .filter(|pass| pass.is_alive())
+            .ok_or_else(|| error("invalid pass index"))
+    }
+
+    fn check_adoptable(&self, context: &SharedContext) -> Result<(), JsValue> {
+        if !self.context.same_canvas(context) {
+            return Err(error(
+                "renderer draws to a different canvas than the composer",
+            ));
         }
-        self.passes.retain(|pass| pass.is_alive());
         Ok(())
     }
+
+    pub(crate) fn target_fps(&self) -> Option<u32> {
+        self.target_fps
+    }
+
+    // A loop that failed on its own removes itself here.
+    pub(crate) fn forget_loop(&mut self, handle: u32) {
+        self.loops
+            .retain(|frame_loop| frame_loop.handle() != handle);
+    }
+}
+
+impl Drop for ComposerInner {
+    fn drop(&mut self) {
+        for frame_loop in self.loops.drain(..) {
+            frame_loop.stop();
+        }
+    }
 }
 
 enum RenderPass {
     Batched(PassHandle<BatchedRendererInner>),
+    BatchedLayer(PassHandle<BatchedRendererInner>, u32),
     TimeSeries(PassHandle<TimeSeriesRendererInner>),
 }
 
@@ -78,26 +377,105 @@ impl RenderPass {
     fn render(&self) -> Result<(), JsValue> {
         match self {
             RenderPass::Batched(handle) => handle.render(|inner| inner.render_pass()),
+            RenderPass::BatchedLayer(handle, layer) => {
+                handle.render(|inner| inner.render_layer(*layer))
+            }
             RenderPass::TimeSeries(handle) => handle.render(|inner| inner.render_pass()),
         }
     }
 
+    // Batched renderers sum their frame counters over every pass of a frame.
+    fn set_composing(&self, composing: bool) {
+        if let RenderPass::Batched(handle) | RenderPass::BatchedLayer(handle, _) = self {
+            handle.update(|inner| {
+                if composing {
+                    inner.begin_composed_frame();
+                } else {
+                    inner.end_composed_frame();
+                }
+            });
+        }
+    }
+
     fn is_alive(&self) -> bool {
         match self {
-            RenderPass::Batched(handle) => handle.is_alive(),
+            RenderPass::Batched(handle) | RenderPass::BatchedLayer(handle, _) => handle.is_alive(),
             RenderPass::TimeSeries(handle) => handle.is_alive(),
         }
     }
+
+    fn is_dirty(&self) -> bool {
+        match self {
+            RenderPass::Batched(handle) | RenderPass::BatchedLayer(handle, _) => {
+                handle.inspect(|inner| inner.is_dirty())
+            }
+            RenderPass::TimeSeries(handle) => handle.inspect(|inner| inner.is_dirty()),
+        }
+        .unwrap_or(false)
+    }
+
+    fn is_enabled(&self) -> bool {
+        match self {
+            RenderPass::Batched(handle) | RenderPass::BatchedLayer(handle, _) => handle.enabled,
+            RenderPass::TimeSeries(handle) => handle.enabled,
+        }
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        match self {
+            RenderPass::Batched(handle) | RenderPass::BatchedLayer(handle, _) => {
+                handle.enabled = enabled
+            }
+            RenderPass::TimeSeries(handle) => handle.enabled = enabled,
+        }
+    }
+
+    fn clear(&self) -> PassClear {
+        match self {
+            RenderPass::Batched(handle) | RenderPass::BatchedLayer(handle, _) => handle.clear,
+            RenderPass::TimeSeries(handle) => handle.clear,
+        }
+    }
+
+    fn set_clear(&mut self, clear: PassClear) {
+        match self {
+            RenderPass::Batched(handle) | RenderPass::BatchedLayer(handle, _) => {
+                handle.clear = clear
+            }
+            RenderPass::TimeSeries(handle) => handle.clear = clear,
+        }
+    }
+
+    fn viewport(&self) -> Option<Viewport> {
+        match self {
+            RenderPass::Batched(handle) | RenderPass::BatchedLayer(handle, _) => {
+                handle.inspect(|inner| inner.viewport())
+            }
+            RenderPass::TimeSeries(handle) => handle.inspect(|inner| inner.viewport()),
+        }
+        .flatten()
+    }
+}
+
+// What a pass clears before it draws; nothing by default.
+#[derive(Clone, Copy, Default)]
+struct PassClear {
+    color: Option<[f32; 4]>,
+    depth: Option<f32>,
 }
 
 struct PassHandle<T> {
     inner: Weak<RefCell<T>>,
+    enabled: bool,
+    clear: PassClear,
 }
 
 impl<T> PassHandle<T> {
     fn new(inner: &Rc<RefCell<T>>) -> Self {
         Self {
             inner: Rc::downgrade(inner),
+            enabled: true,
+            clear: PassClear::default(),
         }
     }
 
@@ -112,7 +490,21 @@ impl<T> PassHandle<T> {
         }
     }
 
+    fn inspect<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.inner.upgrade().map(|inner| f(&inner.borrow()))
+    }
+
+    fn update(&self, f: impl FnOnce(&mut T)) {
+        if let Some(inner) = self.inner.upgrade() {
+            f(&mut inner.borrow_mut());
+        }
+    }
+
     fn is_alive(&self) -> bool {
         self.inner.strong_count() > 0
     }
+
+    fn points_to(&self, inner: &Rc<RefCell<T>>) -> bool {
+        std::ptr::eq(self.inner.as_ptr(), Rc::as_ptr(inner))
+    }
 }
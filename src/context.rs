@@ -1,15 +1,76 @@
+use std::cell::Cell;
 use std::rc::Rc;
 
 use wasm_bindgen::{JsCast, JsValue};
-use web_sys::{HtmlCanvasElement, WebGl2RenderingContext as Gl};
+use web_sys::{HtmlCanvasElement, OffscreenCanvas, WebGl2RenderingContext as Gl};
 
-use crate::utils::error;
+use crate::coords::pixel_to_ndc;
+use crate::utils::{error, log};
+
+pub(crate) const DEFAULT_MAX_DIMENSION: u32 = 8192;
 
 pub(crate) type SharedContext = Rc<CanvasContext>;
 
+// The drawable a context renders into: a DOM canvas looked up by id, or an
+// OffscreenCanvas handed over directly (e.g. inside a worker).
+enum CanvasSurface {
+    Element(HtmlCanvasElement),
+    Offscreen(OffscreenCanvas),
+}
+
+impl CanvasSurface {
+    fn width(&self) -> u32 {
+        match self {
+            CanvasSurface::Element(canvas) => canvas.width(),
+            CanvasSurface::Offscreen(canvas) => canvas.width(),
+        }
+    }
+
+    fn height(&self) -> u32 {
+        match self {
+            CanvasSurface::Element(canvas) => canvas.height(),
+            CanvasSurface::Offscreen(canvas) => canvas.height(),
+        }
+    }
+
+    fn set_size(&self, width: u32, height: u32) {
+        match self {
+            CanvasSurface::Element(canvas) => {
+                canvas.set_width(width);
+                canvas.set_height(height);
+            }
+            CanvasSurface::Offscreen(canvas) => {
+                canvas.set_width(width);
+                canvas.set_height(height);
+            }
+        }
+    }
+
+    // offscreen canvases have no document to be detached from
+    fn is_connected(&self) -> bool {
+        match self {
+            CanvasSurface::Element(canvas) => canvas.is_connected(),
+            CanvasSurface::Offscreen(_) => true,
+        }
+    }
+
+    fn webgl2_context(&self) -> Result<Gl, JsValue> {
+        let context = match self {
+            CanvasSurface::Element(canvas) => canvas.get_context("webgl2")?,
+            CanvasSurface::Offscreen(canvas) => canvas.get_context("webgl2")?,
+        };
+        context
+            .ok_or_else(|| error("webgl2 context unavailable"))?
+            .dyn_into()
+            .map_err(|_| error("failed to cast WebGL2 context"))
+    }
+}
+
 pub(crate) struct CanvasContext {
-    canvas: HtmlCanvasElement,
+    canvas: CanvasSurface,
     gl: Gl,
+    max_dimension: Cell<u32>,
+    reported_unusable: Cell<bool>,
 }
 
 impl CanvasContext {
@@ -22,45 +83,254 @@ impl CanvasContext {
         let canvas = element
             .dyn_into::<HtmlCanvasElement>()
             .map_err(|_| error("element is not a canvas"))?;
+        Self::with_surface(CanvasSurface::Element(canvas))
+    }
 
-        let gl: Gl = canvas
-            .get_context("webgl2")?
-            .ok_or_else(|| error("webgl2 context unavailable"))?
-            .dyn_into()
-            .map_err(|_| error("failed to cast WebGL2 context"))?;
+    pub(crate) fn from_offscreen(canvas: OffscreenCanvas) -> Result<Self, JsValue> {
+        Self::with_surface(CanvasSurface::Offscreen(canvas))
+    }
 
-        let context = CanvasContext { canvas, gl };
+    fn with_surface(canvas: CanvasSurface) -> Result<Self, JsValue> {
+        let gl = canvas.webgl2_context()?;
+        let context = CanvasContext {
+            canvas,
+            gl,
+            max_dimension: Cell::new(DEFAULT_MAX_DIMENSION),
+            reported_unusable: Cell::new(false),
+        };
         let width = context.canvas.width().max(1);
         let height = context.canvas.height().max(1);
-        context
-            .gl
-            .viewport(0, 0, width as i32, height as i32);
+        context.gl.viewport(0, 0, width as i32, height as i32);
         Ok(context)
     }
 
+    // False once the canvas is detached from the document, has a zero-sized
+    // drawable buffer, or lost its context; drawing then becomes a no-op.
+    pub(crate) fn is_usable(&self) -> bool {
+        self.canvas.is_connected()
+            && self.canvas.width() > 0
+            && self.canvas.height() > 0
+            && !self.gl.is_context_lost()
+    }
+
+    // Like `is_usable`, but logs the first time a draw is skipped.
+    pub(crate) fn check_usable(&self) -> bool {
+        let usable = self.is_usable();
+        if !usable && !self.reported_unusable.get() {
+            log("canvas is detached or has no drawable area; skipping draws");
+        }
+        self.reported_unusable.set(!usable);
+        usable
+    }
+
+    // getContext hands back the same WebGL2 context for a canvas, so two
+    // handles on one canvas share their GL object even if opened separately.
+    pub(crate) fn same_canvas(self: &Rc<Self>, other: &Rc<Self>) -> bool {
+        Rc::ptr_eq(self, other) || self.gl == other.gl
+    }
+
     pub(crate) fn gl_clone(&self) -> Gl {
         self.gl.clone()
     }
 
+    pub(crate) fn size(&self) -> (u32, u32) {
+        (self.canvas.width().max(1), self.canvas.height().max(1))
+    }
+
+    pub(crate) fn aspect_ratio(&self) -> f32 {
+        let (width, height) = self.size();
+        width as f32 / height as f32
+    }
+
+    pub(crate) fn set_max_dimension(&self, max: u32) -> Result<(), JsValue> {
+        if max == 0 {
+            return Err(error("max canvas dimension must be positive"));
+        }
+        self.max_dimension.set(max);
+        Ok(())
+    }
+
     pub(crate) fn resize(&self, width: u32, height: u32) {
-        let width = width.max(1);
-        let height = height.max(1);
-        self.canvas.set_width(width);
-        self.canvas.set_height(height);
+        let requested = (width.max(1), height.max(1));
+        let max = self.max_dimension.get();
+        let (width, height) = clamp_dimensions(requested.0, requested.1, max);
+        if (width, height) != requested {
+            log(&format!(
+                "canvas resize to {}x{} clamped to {width}x{height} (max dimension {max})",
+                requested.0, requested.1
+            ));
+        }
+        self.canvas.set_size(width, height);
+        self.gl.viewport(0, 0, width as i32, height as i32);
+    }
+
+    // Renderers that draw into a sub-rectangle call this on exit so the canvas
+    // is always left with a full viewport and the scissor test disabled.
+    pub(crate) fn reset_viewport(&self) {
+        let (width, height) = self.size();
         self.gl.viewport(0, 0, width as i32, height as i32);
+        self.gl.disable(Gl::SCISSOR_TEST);
     }
 
-    pub(crate) fn clear(&self, color: [f32; 4], depth: Option<f32>) {
+    pub(crate) fn apply_viewport(&self, viewport: Viewport) {
+        let (x, y) = (viewport.x as i32, viewport.y as i32);
+        let (width, height) = (viewport.width as i32, viewport.height as i32);
+        self.gl.viewport(x, y, width, height);
+        self.gl.enable(Gl::SCISSOR_TEST);
+        self.gl.scissor(x, y, width, height);
+    }
+
+    pub(crate) fn flush(&self) {
+        if self.check_usable() {
+            self.gl.flush();
+        }
+    }
+
+    pub(crate) fn finish(&self) {
+        if self.check_usable() {
+            self.gl.finish();
+        }
+    }
+
+    pub(crate) fn clear(&self, color: [f32; 4], depth: Option<f32>, stencil: Option<i32>) {
         self.gl.clear_color(color[0], color[1], color[2], color[3]);
+        let mut mask = Gl::COLOR_BUFFER_BIT;
+        if let Some(depth) = depth {
+            self.gl.clear_depth(depth);
+            mask |= Gl::DEPTH_BUFFER_BIT;
+        }
+        if let Some(stencil) = stencil {
+            self.gl.clear_stencil(stencil);
+            mask |= Gl::STENCIL_BUFFER_BIT;
+        }
+        self.gl.clear(mask);
+    }
+
+    // Clears only the given buffers, limited to `viewport` when there is one.
+    pub(crate) fn clear_region(
+        &self,
+        viewport: Option<Viewport>,
+        color: Option<[f32; 4]>,
+        depth: Option<f32>,
+    ) {
+        let mut mask = 0;
+        if let Some(color) = color {
+            self.gl.clear_color(color[0], color[1], color[2], color[3]);
+            mask |= Gl::COLOR_BUFFER_BIT;
+        }
         if let Some(depth) = depth {
             self.gl.clear_depth(depth);
-            self.gl.clear(Gl::COLOR_BUFFER_BIT | Gl::DEPTH_BUFFER_BIT);
-        } else {
-            self.gl.clear(Gl::COLOR_BUFFER_BIT);
+            mask |= Gl::DEPTH_BUFFER_BIT;
+        }
+        if mask == 0 {
+            return;
+        }
+        if let Some(viewport) = viewport {
+            self.apply_viewport(viewport);
+        }
+        self.gl.clear(mask);
+        if viewport.is_some() {
+            self.reset_viewport();
         }
     }
 }
 
+// Scales both sides down together so the larger one fits within `max`.
+fn clamp_dimensions(width: u32, height: u32, max: u32) -> (u32, u32) {
+    let largest = width.max(height);
+    if largest <= max {
+        return (width, height);
+    }
+    let scale = max as f64 / largest as f64;
+    let fit = |side: u32| ((side as f64 * scale).round() as u32).clamp(1, max);
+    (fit(width), fit(height))
+}
+
 pub(crate) fn shared_context(canvas_id: &str) -> Result<SharedContext, JsValue> {
     Ok(Rc::new(CanvasContext::new(canvas_id)?))
 }
+
+pub(crate) fn shared_context_offscreen(canvas: OffscreenCanvas) -> Result<SharedContext, JsValue> {
+    Ok(Rc::new(CanvasContext::from_offscreen(canvas)?))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Viewport {
+    pub(crate) x: u32, // GL convention: origin at the bottom-left of the canvas
+    pub(crate) y: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+impl Viewport {
+    pub(crate) fn new(x: u32, y: u32, width: u32, height: u32) -> Result<Self, &'static str> {
+        if width == 0 || height == 0 {
+            return Err("viewport must have a non-zero size");
+        }
+        Ok(Viewport {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    pub(crate) fn full((width, height): (u32, u32)) -> Self {
+        Viewport {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    pub(crate) fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // `x`/`y` are canvas pixels with a top-left origin, as DOM events report them
+    pub(crate) fn pixel_to_ndc(&self, x: f32, y: f32, canvas_height: u32) -> (f32, f32) {
+        let top = canvas_height as f32 - (self.y + self.height) as f32;
+        pixel_to_ndc(
+            x - self.x as f32,
+            y - top,
+            self.width as f32,
+            self.height as f32,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dimension_clamp_keeps_aspect() {
+        assert_eq!(clamp_dimensions(800, 600, 8192), (800, 600));
+        assert_eq!(clamp_dimensions(16384, 8192, 8192), (8192, 4096));
+        assert_eq!(clamp_dimensions(1000, 100_000, 1000), (10, 1000));
+        assert_eq!(clamp_dimensions(u32::MAX, 1, 4096), (4096, 1));
+    }
+
+    #[test]
+    fn viewport_pixel_mapping() {
+        let full = Viewport {
+            x: 0,
+            y: 0,
+            width: 200,
+            height: 100,
+        };
+        assert_eq!(full.pixel_to_ndc(0.0, 0.0, 100), (-1.0, 1.0));
+        assert_eq!(full.pixel_to_ndc(100.0, 50.0, 100), (0.0, 0.0));
+
+        // lower-right quadrant of a 200x100 canvas
+        let corner = Viewport {
+            x: 100,
+            y: 0,
+            width: 100,
+            height: 50,
+        };
+        assert_eq!(corner.pixel_to_ndc(100.0, 50.0, 100), (-1.0, 1.0));
+        assert_eq!(corner.pixel_to_ndc(200.0, 100.0, 100), (1.0, -1.0));
+    }
+}
@@ -0,0 +1,28 @@
+// Conversions between normalized device coordinates and canvas pixels. Pixels
+// use the DOM convention (origin top-left, y down); NDC has +y up, so every
+// conversion flips y here rather than at each call site.
+
+pub fn pixel_to_ndc(px: f32, py: f32, width: f32, height: f32) -> (f32, f32) {
+    (px / width * 2.0 - 1.0, 1.0 - py / height * 2.0)
+}
+
+pub fn ndc_to_pixel(x: f32, y: f32, width: f32, height: f32) -> (f32, f32) {
+    ((x * 0.5 + 0.5) * width, (0.5 - y * 0.5) * height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_ndc_round_trip() {
+        assert_eq!(pixel_to_ndc(0.0, 0.0, 200.0, 100.0), (-1.0, 1.0));
+        assert_eq!(pixel_to_ndc(200.0, 100.0, 200.0, 100.0), (1.0, -1.0));
+        assert_eq!(pixel_to_ndc(100.0, 50.0, 200.0, 100.0), (0.0, 0.0));
+        assert_eq!(ndc_to_pixel(-1.0, 1.0, 200.0, 100.0), (0.0, 0.0));
+        assert_eq!(ndc_to_pixel(0.5, -0.5, 200.0, 100.0), (150.0, 75.0));
+        let (x, y) = pixel_to_ndc(37.0, 81.0, 640.0, 480.0);
+        let (px, py) = ndc_to_pixel(x, y, 640.0, 480.0);
+        assert!((px - 37.0).abs() < 1e-3 && (py - 81.0).abs() < 1e-3);
+    }
+}
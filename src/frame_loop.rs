@@ -0,0 +1,137 @@
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+use js_sys::Function;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::prelude::*;
+
+use crate::composer::ComposerInner;
+use crate::utils::{error, log};
+
+type FrameCallback = Closure<dyn FnMut(f64)>;
+
+// One requestAnimationFrame chain owned by a composer. The closure lives in
+// the loop state and re-schedules itself, so stopping has to both cancel the
+// pending frame and drop the closure.
+pub(crate) struct FrameLoop {
+    state: Rc<LoopState>,
+}
+
+struct LoopState {
+    handle: u32,
+    stopped: Cell<bool>,
+    pending_frame: Cell<Option<i32>>,
+    last_render: Cell<Option<f64>>, // rAF timestamp of the last frame that ran
+    closure: RefCell<Option<FrameCallback>>,
+}
+
+impl FrameLoop {
+    pub(crate) fn start(
+        composer: Weak<RefCell<ComposerInner>>,
+        handle: u32,
+        callback: Function,
+    ) -> Result<Self, JsValue> {
+        let state = Rc::new(LoopState {
+            handle,
+            stopped: Cell::new(false),
+            pending_frame: Cell::new(None),
+            last_render: Cell::new(None),
+            closure: RefCell::new(None),
+        });
+        let weak_state = Rc::downgrade(&state);
+        let closure = Closure::<dyn FnMut(f64)>::new(move |timestamp: f64| {
+            let Some(state) = weak_state.upgrade() else {
+                return;
+            };
+            state.pending_frame.set(None);
+            if state.stopped.get() {
+                return;
+            }
+            let Some(composer) = composer.upgrade() else {
+                return;
+            };
+            let target_fps = composer.borrow().target_fps();
+            let result = if frame_due(state.last_render.get(), timestamp, target_fps) {
+                state.last_render.set(Some(timestamp));
+                // no borrow is held while JS runs, so the callback may use the composer
+                callback
+                    .call1(&JsValue::NULL, &JsValue::from_f64(timestamp))
+                    .and_then(|_| {
+                        // a callback that stops its own loop skips this frame's render too
+                        if state.stopped.get() {
+                            Ok(())
+                        } else {
+                            composer.borrow_mut().render()
+                        }
+                    })
+            } else {
+                Ok(())
+            };
+            let result = result.and_then(|_| state.schedule());
+            if let Err(err) = result {
+                log(&format!("animation loop {} stopped: {err:?}", state.handle));
+                state.stopped.set(true);
+                composer.borrow_mut().forget_loop(state.handle);
+            }
+        });
+        *state.closure.borrow_mut() = Some(closure);
+        state.schedule()?;
+        Ok(Self { state })
+    }
+
+    pub(crate) fn handle(&self) -> u32 {
+        self.state.handle
+    }
+
+    pub(crate) fn stop(self) {
+        self.state.stopped.set(true);
+        if let (Some(frame), Some(window)) = (self.state.pending_frame.take(), web_sys::window()) {
+            let _ = window.cancel_animation_frame(frame);
+        }
+        // wasm-bindgen defers the free if this runs inside the loop's own frame
+        self.state.closure.borrow_mut().take();
+    }
+}
+
+// Frames arrive at the display rate, so a frame slightly early for the cap
+// still runs; otherwise 30 fps on a 60 Hz display would drop to 20.
+fn frame_due(last_render: Option<f64>, now: f64, target_fps: Option<u32>) -> bool {
+    match (last_render, target_fps) {
+        (Some(last), Some(fps)) => {
+            let interval = 1000.0 / fps as f64;
+            now - last >= interval * 0.9
+        }
+        _ => true,
+    }
+}
+
+impl LoopState {
+    fn schedule(&self) -> Result<(), JsValue> {
+        if self.stopped.get() {
+            return Ok(());
+        }
+        let window = web_sys::window().ok_or_else(|| error("missing window"))?;
+        let closure = self.closure.borrow();
+        let closure = closure
+            .as_ref()
+            .ok_or_else(|| error("animation loop was stopped"))?;
+        let frame = window.request_animation_frame(closure.as_ref().unchecked_ref())?;
+        self.pending_frame.set(Some(frame));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_cap_skips_early_frames() {
+        assert!(frame_due(None, 0.0, Some(30)));
+        assert!(frame_due(Some(0.0), 16.7, None));
+        assert!(!frame_due(Some(0.0), 16.7, Some(30)));
+        assert!(frame_due(Some(0.0), 32.9, Some(30)));
+        assert!(!frame_due(Some(0.0), 8.3, Some(60)));
+        assert!(frame_due(Some(0.0), 16.6, Some(60)));
+    }
+}
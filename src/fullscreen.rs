@@ -0,0 +1,80 @@
+use js_sys::Float32Array;
+use wasm_bindgen::JsValue;
+use web_sys::{WebGl2RenderingContext as Gl, WebGlProgram, WebGlTexture, WebGlUniformLocation};
+
+use crate::gpu::{GlBuffer, VertexArray};
+use crate::shader::{
+    compile_shader, fullscreen_fragment_shader_source, fullscreen_vertex_shader_source,
+    link_program, shader_precision, with_precision,
+};
+use crate::utils::error;
+
+// One oversized triangle covers the whole viewport without a diagonal seam.
+const FULLSCREEN_TRIANGLE: [f32; 6] = [-1.0, -1.0, 3.0, -1.0, -1.0, 3.0];
+
+pub(crate) struct FullscreenQuad {
+    gl: Gl,
+    program: WebGlProgram,
+    vao: VertexArray,
+    _buffer: GlBuffer,
+    texture_location: WebGlUniformLocation,
+}
+
+impl FullscreenQuad {
+    pub(crate) fn new(gl: &Gl) -> Result<Self, JsValue> {
+        let precision = shader_precision();
+        let vert_shader = compile_shader(
+            gl,
+            Gl::VERTEX_SHADER,
+            &with_precision(fullscreen_vertex_shader_source(), precision),
+        )?;
+        let frag_shader = compile_shader(
+            gl,
+            Gl::FRAGMENT_SHADER,
+            &with_precision(fullscreen_fragment_shader_source(), precision),
+        )?;
+        let program = link_program(gl, &vert_shader, &frag_shader)?;
+        let position_location: u32 = gl
+            .get_attrib_location(&program, "a_position")
+            .try_into()
+            .map_err(|_| error("a_position attribute missing"))?;
+        let texture_location = gl
+            .get_uniform_location(&program, "u_texture")
+            .ok_or_else(|| error("u_texture uniform missing"))?;
+
+        let vao = VertexArray::new(gl)?;
+        let buffer = GlBuffer::new(gl)?;
+        gl.bind_vertex_array(Some(vao.handle()));
+        buffer.bind_array_buffer();
+        let view = unsafe { Float32Array::view(&FULLSCREEN_TRIANGLE) };
+        gl.buffer_data_with_array_buffer_view(Gl::ARRAY_BUFFER, &view, Gl::STATIC_DRAW);
+        gl.enable_vertex_attrib_array(position_location);
+        gl.vertex_attrib_pointer_with_i32(position_location, 2, Gl::FLOAT, false, 0, 0);
+        gl.bind_vertex_array(None);
+
+        Ok(Self {
+            gl: gl.clone(),
+            program,
+            vao,
+            _buffer: buffer,
+            texture_location,
+        })
+    }
+
+    // Covers the current viewport with `texture`, ignoring depth and blending.
+    // Passes set up their own pipeline state, so nothing is restored.
+    pub(crate) fn draw_texture(&self, texture: &WebGlTexture) {
+        let gl = &self.gl;
+        gl.use_program(Some(&self.program));
+        gl.disable(Gl::DEPTH_TEST);
+        gl.disable(Gl::CULL_FACE);
+        gl.disable(Gl::BLEND);
+        gl.active_texture(Gl::TEXTURE0);
+        gl.bind_texture(Gl::TEXTURE_2D, Some(texture));
+        gl.uniform1i(Some(&self.texture_location), 0);
+        gl.bind_vertex_array(Some(self.vao.handle()));
+        gl.draw_arrays(Gl::TRIANGLES, 0, 3);
+        gl.bind_vertex_array(None);
+        gl.bind_texture(Gl::TEXTURE_2D, None);
+    }
+}
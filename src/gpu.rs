@@ -1,8 +1,92 @@
+use js_sys::Array;
 use wasm_bindgen::JsValue;
-use web_sys::{WebGl2RenderingContext as Gl, WebGlBuffer, WebGlVertexArrayObject};
+use wasm_bindgen::prelude::*;
+use web_sys::{
+    WebGl2RenderingContext as Gl, WebGlBuffer, WebGlFramebuffer, WebGlRenderbuffer, WebGlTexture,
+    WebGlVertexArrayObject,
+};
 
 use crate::utils::error;
 
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    AlphaOver,
+    Additive,
+}
+
+impl BlendMode {
+    pub(crate) fn apply(self, gl: &Gl) {
+        gl.enable(Gl::BLEND);
+        match self {
+            BlendMode::AlphaOver => gl.blend_func(Gl::SRC_ALPHA, Gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Additive => gl.blend_func(Gl::SRC_ALPHA, Gl::ONE),
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Primitive {
+    Triangles,
+    Lines,
+    Points,
+}
+
+impl Primitive {
+    pub(crate) fn gl_mode(self) -> u32 {
+        match self {
+            Primitive::Triangles => Gl::TRIANGLES,
+            Primitive::Lines => Gl::LINES,
+            Primitive::Points => Gl::POINTS,
+        }
+    }
+
+    // Lines and points draw no triangles, whatever their vertex count.
+    pub(crate) fn triangle_count(self, vertex_count: i32) -> u32 {
+        match self {
+            Primitive::Triangles => (vertex_count / 3) as u32,
+            Primitive::Lines | Primitive::Points => 0,
+        }
+    }
+
+    // Fewer vertices than one primitive pass validation but draw nothing.
+    pub(crate) fn check_vertex_count(self, vertex_count: usize) -> Result<(), &'static str> {
+        let (minimum, message) = match self {
+            Primitive::Triangles => (3, "a triangle mesh needs at least 3 vertices"),
+            Primitive::Lines => (2, "a line mesh needs at least 2 vertices"),
+            Primitive::Points => (1, "mesh requires at least one vertex"),
+        };
+        if vertex_count < minimum {
+            return Err(message);
+        }
+        Ok(())
+    }
+}
+
+// Reads a `[min, max]` size parameter such as ALIASED_LINE_WIDTH_RANGE,
+// falling back to `[1, 1]` when the driver reports nothing usable.
+pub(crate) fn query_size_range(gl: &Gl, param: u32) -> [f32; 2] {
+    let raw = gl.get_parameter(param);
+    if let Ok(value) = raw {
+        let array = Array::from(&value);
+        let min = array
+            .get(0)
+            .as_f64()
+            .map(|v| v as f32)
+            .filter(|v| v.is_finite() && *v > 0.0)
+            .unwrap_or(1.0);
+        let max = array
+            .get(1)
+            .as_f64()
+            .map(|v| v as f32)
+            .filter(|v| v.is_finite() && *v >= min)
+            .unwrap_or(min);
+        return [min, max.max(min)];
+    }
+    [1.0, 1.0]
+}
+
 pub(crate) struct GlBuffer {
     gl: Gl,
     handle: WebGlBuffer,
@@ -60,3 +144,186 @@ impl Drop for VertexArray {
         self.gl.delete_vertex_array(Some(&self.handle));
     }
 }
+
+pub(crate) struct Texture {
+    gl: Gl,
+    handle: WebGlTexture,
+}
+
+impl Texture {
+    pub(crate) fn new_rgba(gl: &Gl, width: u32, height: u32) -> Result<Self, JsValue> {
+        let handle = gl
+            .create_texture()
+            .ok_or_else(|| error("failed to create texture"))?;
+        gl.bind_texture(Gl::TEXTURE_2D, Some(&handle));
+        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            Gl::TEXTURE_2D,
+            0,
+            Gl::RGBA8 as i32,
+            width as i32,
+            height as i32,
+            0,
+            Gl::RGBA,
+            Gl::UNSIGNED_BYTE,
+            None,
+        )?;
+        gl.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_MIN_FILTER, Gl::NEAREST as i32);
+        gl.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_MAG_FILTER, Gl::NEAREST as i32);
+        gl.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_WRAP_S, Gl::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_WRAP_T, Gl::CLAMP_TO_EDGE as i32);
+        gl.bind_texture(Gl::TEXTURE_2D, None);
+        Ok(Self {
+            gl: gl.clone(),
+            handle,
+        })
+    }
+
+    pub(crate) fn handle(&self) -> &WebGlTexture {
+        &self.handle
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        self.gl.delete_texture(Some(&self.handle));
+    }
+}
+
+pub(crate) struct Renderbuffer {
+    gl: Gl,
+    handle: WebGlRenderbuffer,
+}
+
+impl Renderbuffer {
+    pub(crate) fn new_depth(gl: &Gl, width: u32, height: u32) -> Result<Self, JsValue> {
+        let handle = gl
+            .create_renderbuffer()
+            .ok_or_else(|| error("failed to create renderbuffer"))?;
+        gl.bind_renderbuffer(Gl::RENDERBUFFER, Some(&handle));
+        gl.renderbuffer_storage(
+            Gl::RENDERBUFFER,
+            Gl::DEPTH_COMPONENT24,
+            width as i32,
+            height as i32,
+        );
+        gl.bind_renderbuffer(Gl::RENDERBUFFER, None);
+        Ok(Self {
+            gl: gl.clone(),
+            handle,
+        })
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        self.gl.delete_renderbuffer(Some(&self.handle));
+    }
+}
+
+pub(crate) struct Framebuffer {
+    gl: Gl,
+    handle: WebGlFramebuffer,
+}
+
+impl Framebuffer {
+    pub(crate) fn new(gl: &Gl) -> Result<Self, JsValue> {
+        let handle = gl
+            .create_framebuffer()
+            .ok_or_else(|| error("failed to create framebuffer"))?;
+        Ok(Self {
+            gl: gl.clone(),
+            handle,
+        })
+    }
+
+    pub(crate) fn bind(&self) {
+        self.gl
+            .bind_framebuffer(Gl::FRAMEBUFFER, Some(&self.handle));
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        self.gl.delete_framebuffer(Some(&self.handle));
+    }
+}
+
+// An offscreen color texture with a depth renderbuffer, sized in pixels.
+pub(crate) struct RenderTarget {
+    framebuffer: Framebuffer,
+    color: Texture,
+    _depth: Renderbuffer,
+    size: (u32, u32),
+}
+
+impl RenderTarget {
+    pub(crate) fn new(gl: &Gl, width: u32, height: u32) -> Result<Self, JsValue> {
+        let color = Texture::new_rgba(gl, width, height)?;
+        let depth = Renderbuffer::new_depth(gl, width, height)?;
+        let framebuffer = Framebuffer::new(gl)?;
+        framebuffer.bind();
+        gl.framebuffer_texture_2d(
+            Gl::FRAMEBUFFER,
+            Gl::COLOR_ATTACHMENT0,
+            Gl::TEXTURE_2D,
+            Some(color.handle()),
+            0,
+        );
+        gl.framebuffer_renderbuffer(
+            Gl::FRAMEBUFFER,
+            Gl::DEPTH_ATTACHMENT,
+            Gl::RENDERBUFFER,
+            Some(&depth.handle),
+        );
+        let status = gl.check_framebuffer_status(Gl::FRAMEBUFFER);
+        gl.bind_framebuffer(Gl::FRAMEBUFFER, None);
+        if status != Gl::FRAMEBUFFER_COMPLETE {
+            return Err(error(&format!("framebuffer incomplete (0x{status:04x})")));
+        }
+        Ok(Self {
+            framebuffer,
+            color,
+            _depth: depth,
+            size: (width, height),
+        })
+    }
+
+    pub(crate) fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    pub(crate) fn texture(&self) -> &WebGlTexture {
+        self.color.handle()
+    }
+
+    pub(crate) fn bind(&self) {
+        self.framebuffer.bind();
+        let (width, height) = self.size;
+        self.framebuffer
+            .gl
+            .viewport(0, 0, width as i32, height as i32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn degenerate_meshes_are_rejected() {
+        assert!(Primitive::Triangles.check_vertex_count(1).is_err());
+        assert!(Primitive::Triangles.check_vertex_count(2).is_err());
+        assert!(Primitive::Triangles.check_vertex_count(3).is_ok());
+        assert!(Primitive::Lines.check_vertex_count(1).is_err());
+        assert!(Primitive::Lines.check_vertex_count(2).is_ok());
+        assert!(Primitive::Points.check_vertex_count(1).is_ok());
+        assert!(Primitive::Points.check_vertex_count(0).is_err());
+    }
+
+    #[test]
+    fn only_triangle_meshes_count_triangles() {
+        assert_eq!(Primitive::Triangles.triangle_count(7), 2);
+        assert_eq!(Primitive::Lines.triangle_count(6), 0);
+        assert_eq!(Primitive::Points.triangle_count(6), 0);
+    }
+}
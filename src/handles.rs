@@ -0,0 +1,71 @@
+use wasm_bindgen::JsValue;
+
+use crate::utils::error;
+
+// Mesh and instance handles are both bare u32s on the JS side. Mesh handles
+// carry this tag bit so passing one where the other is expected fails loudly
+// instead of resolving to an unrelated mesh or instance.
+const MESH_TAG: u32 = 1 << 31;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct MeshHandle(u32); // index into the renderer's meshes
+
+impl MeshHandle {
+    pub(crate) fn from_index(index: usize) -> Self {
+        MeshHandle(index as u32)
+    }
+
+    pub(crate) fn from_js(raw: u32) -> Result<Self, JsValue> {
+        decode_mesh(raw).map(MeshHandle).map_err(error)
+    }
+
+    pub(crate) fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub(crate) fn to_js(self) -> u32 {
+        self.0 | MESH_TAG
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct InstanceHandle(u32);
+
+impl InstanceHandle {
+    pub(crate) fn from_js(raw: u32) -> Result<Self, JsValue> {
+        decode_instance(raw).map(InstanceHandle).map_err(error)
+    }
+
+    pub(crate) fn raw(self) -> u32 {
+        self.0
+    }
+}
+
+fn decode_mesh(raw: u32) -> Result<u32, &'static str> {
+    if raw & MESH_TAG == 0 {
+        return Err("expected a mesh handle but got an instance handle");
+    }
+    Ok(raw & !MESH_TAG)
+}
+
+fn decode_instance(raw: u32) -> Result<u32, &'static str> {
+    if raw & MESH_TAG != 0 {
+        return Err("expected an instance handle but got a mesh handle");
+    }
+    Ok(raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handles_reject_the_other_kind() {
+        let mesh = MeshHandle::from_index(3).to_js();
+        assert_eq!(decode_mesh(mesh), Ok(3));
+        assert!(decode_instance(mesh).is_err());
+        assert_eq!(decode_instance(3), Ok(3));
+        assert!(decode_mesh(3).is_err());
+        assert!(decode_mesh(0).is_err());
+    }
+}
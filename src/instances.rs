@@ -49,6 +49,48 @@ impl InstanceStore {
         handle
     }
 
+    // The handle the next `insert` will hand out.
+    pub(crate) fn next_handle(&self) -> u32 {
+        self.free_list
+            .last()
+            .copied()
+            .unwrap_or(self.entries.len() as u32)
+    }
+
+    // Used when restoring a snapshot: the caller picks the handle, and must call
+    // `rebuild_free_list` once every record is back in place.
+    pub(crate) fn insert_at(
+        &mut self,
+        handle: u32,
+        mesh_index: usize,
+        slot_index: usize,
+        transform: [f32; MATRIX_FLOATS],
+    ) -> bool {
+        let index = handle as usize;
+        if index >= self.entries.len() {
+            self.entries.resize_with(index + 1, || None);
+        }
+        if self.entries[index].is_some() {
+            return false;
+        }
+        let slot = self.active_handles.len();
+        self.active_handles.push(handle);
+        self.entries[index] = Some(InstanceRecord {
+            mesh_index,
+            slot_index,
+            transform,
+            active_slot: slot,
+        });
+        true
+    }
+
+    pub(crate) fn rebuild_free_list(&mut self) {
+        self.free_list = (0..self.entries.len() as u32)
+            .rev()
+            .filter(|handle| self.entries[*handle as usize].is_none())
+            .collect();
+    }
+
     pub(crate) fn get(&self, handle: u32) -> Option<&InstanceRecord> {
         self.entries.get(handle as usize)?.as_ref()
     }
@@ -68,14 +110,14 @@ impl InstanceStore {
         };
 
         let slot = record.active_slot;
-        if let Some(last_handle) = self.active_handles.pop() {
-            if last_handle != handle {
-                if let Some(target_slot) = self.active_handles.get_mut(slot) {
-                    *target_slot = last_handle;
-                }
-                if let Some(last_record) = self.entries[last_handle as usize].as_mut() {
-                    last_record.active_slot = slot;
-                }
+        if let Some(last_handle) = self.active_handles.pop()
+            && last_handle != handle
+        {
+            if let Some(target_slot) = self.active_handles.get_mut(slot) {
+                *target_slot = last_handle;
+            }
+            if let Some(last_record) = self.entries[last_handle as usize].as_mut() {
+                last_record.active_slot = slot;
             }
         }
 
@@ -91,4 +133,9 @@ impl InstanceStore {
         self.active_handles.is_empty()
     }
 
+    pub(crate) fn records(&self) -> impl Iterator<Item = &InstanceRecord> {
+        self.active_handles
+            .iter()
+            .filter_map(|&handle| self.entries[handle as usize].as_ref())
+    }
 }
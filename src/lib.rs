@@ -1,21 +1,36 @@
-use js_sys::Float32Array;
+use js_sys::{Array, Float32Array};
 use wasm_bindgen::prelude::*;
 
-mod batcher;
 mod batched;
+mod batcher;
 mod camera;
+mod color;
 mod composer;
 mod context;
+mod coords;
+mod frame_loop;
+mod fullscreen;
 mod gpu;
+mod handles;
 mod instances;
 mod mesh_instances;
+mod mesh_library;
+mod primitives;
+mod scene;
 mod shader;
+mod snapshot;
+mod ticks;
 mod timeseries;
 mod utils;
 
 pub use batched::BatchedRenderer;
+pub use camera::UpAxis;
 pub use composer::CanvasComposer;
-pub use timeseries::TimeSeriesRenderer;
+pub use gpu::{BlendMode, Primitive};
+pub use mesh_library::MeshLibrary;
+pub use shader::ShaderPrecision;
+pub use timeseries::{AxisScale, LineJoin, TimeSeriesRenderer};
+pub use utils::MatrixLayout;
 
 #[wasm_bindgen]
 pub fn test_wasm() -> JsValue {
@@ -23,6 +38,11 @@ pub fn test_wasm() -> JsValue {
     JsValue::TRUE
 }
 
+#[wasm_bindgen]
+pub fn set_shader_precision(precision: ShaderPrecision) {
+    shader::set_shader_precision(precision);
+}
+
 #[wasm_bindgen]
 pub fn build_perspective(
     fov_y_radians: f32,
@@ -30,7 +50,8 @@ pub fn build_perspective(
     near: f32,
     far: f32,
 ) -> Result<Float32Array, JsValue> {
-    let matrix = camera::perspective_matrix(fov_y_radians, aspect, near, far).map_err(utils::error)?;
+    let matrix =
+        camera::perspective_matrix(fov_y_radians, aspect, near, far).map_err(utils::error)?;
     Ok(Float32Array::from(matrix.as_slice()))
 }
 
@@ -40,8 +61,152 @@ pub fn build_orbit_view(
     yaw: f32,
     pitch: f32,
     distance: f32,
+    up: Option<UpAxis>,
 ) -> Result<Float32Array, JsValue> {
     let target_vec = utils::vec3_from_array(target)?;
-    let view = camera::orbit_view_matrix(target_vec, yaw, pitch, distance).map_err(utils::error)?;
+    let up = up.unwrap_or(UpAxis::YUp);
+    let view =
+        camera::orbit_view_matrix(target_vec, yaw, pitch, distance, up).map_err(utils::error)?;
     Ok(Float32Array::from(view.as_slice()))
 }
+
+// View matrix for a free camera at `eye` looking at `target`.
+#[wasm_bindgen]
+pub fn build_look_at(
+    eye: &Float32Array,
+    target: &Float32Array,
+    up: &Float32Array,
+) -> Result<Float32Array, JsValue> {
+    let eye = utils::vec3_from_array(eye)?;
+    let target = utils::vec3_from_array(target)?;
+    let up = utils::vec3_from_array(up)?;
+    let view = camera::look_at_matrix(eye, target, up).map_err(utils::error)?;
+    Ok(Float32Array::from(view.as_slice()))
+}
+
+// Ray [ox, oy, oz, dx, dy, dz] through a canvas pixel (top-left origin). It
+// starts on the near plane and the direction is normalized.
+#[wasm_bindgen]
+pub fn screen_to_ray(
+    view: &Float32Array,
+    projection: &Float32Array,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+) -> Result<Float32Array, JsValue> {
+    check_canvas_size(width, height)?;
+    let view = utils::read_fixed(view, "view matrix")?;
+    let projection = utils::read_fixed(projection, "projection matrix")?;
+    let ndc = coords::pixel_to_ndc(x, y, width, height);
+    let ray = camera::ndc_ray(&view, &projection, ndc).map_err(utils::error)?;
+    Ok(Float32Array::from(ray.as_slice()))
+}
+
+#[wasm_bindgen]
+pub fn decompose_orbit_view(
+    view: &Float32Array,
+    target: &Float32Array,
+    up: Option<UpAxis>,
+) -> Result<Float32Array, JsValue> {
+    let view = utils::read_fixed(view, "view matrix")?;
+    let target_vec = utils::vec3_from_array(target)?;
+    let up = up.unwrap_or(UpAxis::YUp);
+    let (yaw, pitch, distance) =
+        camera::decompose_orbit(&view, target_vec, up).map_err(utils::error)?;
+    Ok(Float32Array::from(&[yaw, pitch, distance][..]))
+}
+
+// Rotation-only column-major mat4 for a quaternion [x, y, z, w].
+#[wasm_bindgen]
+pub fn matrix_from_quat(q: &Float32Array) -> Result<Float32Array, JsValue> {
+    let q = utils::read_fixed(q, "quaternion")?;
+    let matrix = camera::quat_to_matrix(q).map_err(utils::error)?;
+    Ok(Float32Array::from(matrix.as_slice()))
+}
+
+// Human-friendly tick values (1, 2 or 5 x 10^n apart) for axis labels.
+#[wasm_bindgen]
+pub fn nice_ticks(min: f32, max: f32, target_count: u32) -> Result<Float32Array, JsValue> {
+    let ticks = ticks::nice_ticks(min, max, target_count).map_err(utils::error)?;
+    Ok(Float32Array::from(ticks.as_slice()))
+}
+
+// `count` distinct RGBA colors; series without a `color` use the same ones.
+#[wasm_bindgen]
+pub fn palette(count: u32) -> Result<Array, JsValue> {
+    let colors = color::palette(count as usize).map_err(utils::error)?;
+    Ok(colors
+        .iter()
+        .map(|color| JsValue::from(Float32Array::from(&color[..])))
+        .collect())
+}
+
+// RGBA (alpha 1) from hue in degrees and saturation/lightness in 0..1.
+#[wasm_bindgen]
+pub fn color_from_hsl(h: f32, s: f32, l: f32) -> Result<Float32Array, JsValue> {
+    let color = color::hsl_to_rgba(h, s, l).map_err(utils::error)?;
+    Ok(Float32Array::from(&color[..]))
+}
+
+#[wasm_bindgen]
+pub fn color_from_hsv(h: f32, s: f32, v: f32) -> Result<Float32Array, JsValue> {
+    let color = color::hsv_to_rgba(h, s, v).map_err(utils::error)?;
+    Ok(Float32Array::from(&color[..]))
+}
+
+// [x, y] in NDC for a canvas pixel with a top-left origin.
+#[wasm_bindgen]
+pub fn pixel_to_ndc(px: f32, py: f32, width: f32, height: f32) -> Result<Float32Array, JsValue> {
+    check_canvas_size(width, height)?;
+    let (x, y) = coords::pixel_to_ndc(px, py, width, height);
+    Ok(Float32Array::from(&[x, y][..]))
+}
+
+// Canvas pixel [px, py] (top-left origin) for an NDC position.
+#[wasm_bindgen]
+pub fn ndc_to_pixel(x: f32, y: f32, width: f32, height: f32) -> Result<Float32Array, JsValue> {
+    check_canvas_size(width, height)?;
+    let (px, py) = coords::ndc_to_pixel(x, y, width, height);
+    Ok(Float32Array::from(&[px, py][..]))
+}
+
+fn check_canvas_size(width: f32, height: f32) -> Result<(), JsValue> {
+    if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+        return Err(utils::error("canvas size must be positive"));
+    }
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn interleave_mesh(
+    positions: &Float32Array,
+    colors: &Float32Array,
+) -> Result<Float32Array, JsValue> {
+    let positions = utils::array_to_vec(positions);
+    let colors = utils::array_to_vec(colors);
+    let data = batcher::interleave(&positions, &colors).map_err(utils::error)?;
+    Ok(Float32Array::from(data.as_slice()))
+}
+
+#[wasm_bindgen]
+pub fn primitive_cube(size: f32, ccw: Option<bool>) -> Result<Float32Array, JsValue> {
+    let data = primitives::cube(size, ccw.unwrap_or(true)).map_err(utils::error)?;
+    Ok(Float32Array::from(data.as_slice()))
+}
+
+#[wasm_bindgen]
+pub fn primitive_sphere(
+    radius: f32,
+    segments: u32,
+    ccw: Option<bool>,
+) -> Result<Float32Array, JsValue> {
+    let data = primitives::sphere(radius, segments, ccw.unwrap_or(true)).map_err(utils::error)?;
+    Ok(Float32Array::from(data.as_slice()))
+}
+
+#[wasm_bindgen]
+pub fn primitive_quad(width: f32, height: f32, ccw: Option<bool>) -> Result<Float32Array, JsValue> {
+    let data = primitives::quad(width, height, ccw.unwrap_or(true)).map_err(utils::error)?;
+    Ok(Float32Array::from(data.as_slice()))
+}
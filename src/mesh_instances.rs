@@ -1,55 +1,184 @@
 use js_sys::Float32Array;
-use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use wasm_bindgen::JsValue;
 use web_sys::WebGl2RenderingContext as Gl;
 
-use crate::batcher::MATRIX_FLOATS;
 use crate::gpu::GlBuffer;
 use crate::utils::error;
 
+pub(crate) const DEFAULT_GROWTH_FACTOR: f32 = 2.0;
+pub(crate) const GROWTH_FACTOR_RANGE: [f32; 2] = [1.1, 2.0];
+
 pub(crate) struct MeshInstances {
     buffer: GlBuffer,
-    transforms: Vec<[f32; MATRIX_FLOATS]>,
+    stride: usize, // floats per instance
+    data: Vec<f32>,
     handles: Vec<u32>,
     capacity: usize,
-    pending: BTreeMap<usize, [f32; MATRIX_FLOATS]>,
+    pending: BTreeSet<usize>,
     scratch: Vec<f32>,
+    visible: Vec<bool>,
+    hidden: usize,
+    // While any slot is hidden the GPU buffer holds only the visible slots,
+    // packed; this marks that the packing (or its undoing) is stale.
+    layout_dirty: bool,
+    // Visible slots as (depth, slot), sorted for the next upload; the buffer
+    // stays in that order until a later flush without a sort undoes it.
+    draw_order: Vec<(f32, usize)>,
+    sort_requested: bool,
+    reordered: bool,
+    // Visible slots outside the view frustum, packed out like hidden ones.
+    // Rebuilt before every draw, so slot changes just drop it.
+    culled: Vec<bool>,
+    culled_count: usize,
+    growth_factor: f32,
 }
 
 impl MeshInstances {
-    pub(crate) fn new(gl: &Gl, initial_capacity: usize) -> Result<Self, JsValue> {
+    pub(crate) fn new(
+        gl: &Gl,
+        stride: usize,
+        initial_capacity: usize,
+        growth_factor: f32,
+    ) -> Result<Self, JsValue> {
         let buffer = GlBuffer::new(gl)?;
         buffer.bind_array_buffer();
         let capacity = initial_capacity.max(1);
+        let stride = stride.max(1);
         gl.buffer_data_with_i32(
             Gl::ARRAY_BUFFER,
-            (capacity * MATRIX_FLOATS * std::mem::size_of::<f32>()) as i32,
+            (capacity * stride * std::mem::size_of::<f32>()) as i32,
             Gl::DYNAMIC_DRAW,
         );
         Ok(Self {
             buffer,
-            transforms: Vec::new(),
+            stride,
+            data: Vec::new(),
             handles: Vec::new(),
             capacity,
-            pending: BTreeMap::new(),
+            pending: BTreeSet::new(),
             scratch: Vec::new(),
+            visible: Vec::new(),
+            hidden: 0,
+            layout_dirty: false,
+            draw_order: Vec::new(),
+            sort_requested: false,
+            reordered: false,
+            culled: Vec::new(),
+            culled_count: 0,
+            growth_factor,
         })
     }
 
     pub(crate) fn len(&self) -> usize {
-        self.transforms.len()
+        self.handles.len()
+    }
+
+    pub(crate) fn visible_count(&self) -> usize {
+        self.handles.len() - self.hidden
+    }
+
+    // Instances the draw call covers; hidden and culled slots are packed out.
+    pub(crate) fn draw_count(&self) -> usize {
+        self.handles.len() - self.hidden - self.culled_count
+    }
+
+    fn packed(&self) -> bool {
+        self.hidden + self.culled_count > 0
+    }
+
+    // Culls the visible slots `inside` rejects and returns how many were.
+    pub(crate) fn cull(&mut self, inside: impl Fn(&[f32]) -> bool) -> usize {
+        let culled: Vec<bool> = (0..self.handles.len())
+            .map(|slot| self.visible[slot] && !inside(self.slot_values(slot)))
+            .collect();
+        if culled != self.culled {
+            self.culled = culled;
+            self.culled_count = self.culled.iter().filter(|&&culled| culled).count();
+            self.layout_dirty = true;
+            self.sort_requested = false;
+        }
+        self.culled_count
+    }
+
+    pub(crate) fn clear_culling(&mut self) {
+        if self.culled_count > 0 {
+            self.layout_dirty = true;
+        }
+        self.culled.clear();
+        self.culled_count = 0;
+    }
+
+    pub(crate) fn set_visible(&mut self, slot: usize, visible: bool) -> Result<(), JsValue> {
+        let current = self
+            .visible
+            .get_mut(slot)
+            .ok_or_else(|| error("invalid instance slot"))?;
+        if *current != visible {
+            *current = visible;
+            if visible {
+                self.hidden -= 1;
+            } else {
+                self.hidden += 1;
+            }
+            self.layout_dirty = true;
+            self.sort_requested = false;
+            self.clear_culling();
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_visible(&self, slot: usize) -> bool {
+        self.visible.get(slot).copied().unwrap_or(false)
+    }
+
+    // Visible and not culled, so draws cover the slot.
+    pub(crate) fn is_drawn(&self, slot: usize) -> bool {
+        self.is_visible(slot) && !self.culled.get(slot).copied().unwrap_or(false)
+    }
+
+    pub(crate) fn set_growth_factor(&mut self, factor: f32) {
+        self.growth_factor = factor;
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn handle_at(&self, slot: usize) -> u32 {
+        self.handles[slot]
+    }
+
+    pub(crate) fn slot_values(&self, slot: usize) -> &[f32] {
+        &self.data[slot * self.stride..(slot + 1) * self.stride]
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.data.clear();
+        self.handles.clear();
+        self.pending.clear();
+        self.visible.clear();
+        self.hidden = 0;
+        self.layout_dirty = false;
+        self.sort_requested = false;
+        self.culled.clear();
+        self.culled_count = 0;
     }
 
     pub(crate) fn buffer_handle(&self) -> &GlBuffer {
         &self.buffer
     }
 
-    pub(crate) fn allocate(&mut self, gl: &Gl, matrix: &[f32; MATRIX_FLOATS]) -> Result<usize, JsValue> {
-        let slot = self.transforms.len();
-        self.transforms.push(*matrix);
+    pub(crate) fn allocate(&mut self, gl: &Gl, values: &[f32]) -> Result<usize, JsValue> {
+        self.check_stride(values)?;
+        let slot = self.handles.len();
+        self.data.extend_from_slice(values);
         self.handles.push(0);
+        self.visible.push(true);
+        self.sort_requested = false; // the sorted order no longer covers every slot
+        self.clear_culling();
         self.ensure_capacity(gl, slot + 1)?;
-        self.pending.insert(slot, *matrix);
+        self.pending.insert(slot);
         Ok(slot)
     }
 
@@ -59,31 +188,54 @@ impl MeshInstances {
         }
     }
 
-    pub(crate) fn update_slot(&mut self, slot: usize, matrix: &[f32; MATRIX_FLOATS]) -> Result<(), JsValue> {
-        let target = self
-            .transforms
-            .get_mut(slot)
-            .ok_or_else(|| error("invalid instance slot"))?;
-        *target = *matrix;
-        self.pending.insert(slot, *matrix);
+    pub(crate) fn update_slot(
+        &mut self,
+        slot: usize,
+        offset: usize,
+        values: &[f32],
+    ) -> Result<(), JsValue> {
+        if offset + values.len() > self.stride {
+            return Err(error(
+                "instance data does not match the mesh instance layout",
+            ));
+        }
+        if slot >= self.handles.len() {
+            return Err(error("invalid instance slot"));
+        }
+        let start = slot * self.stride + offset;
+        self.data[start..start + values.len()].copy_from_slice(values);
+        self.pending.insert(slot);
         Ok(())
     }
 
     pub(crate) fn remove_slot(&mut self, slot: usize) -> Result<Option<u32>, JsValue> {
-        if slot >= self.transforms.len() {
+        if slot >= self.handles.len() {
             return Err(error("invalid instance slot"));
         }
-        let last_index = self.transforms.len() - 1;
-        self.transforms.swap(slot, last_index);
+        let last_index = self.handles.len() - 1;
+        if slot != last_index {
+            let (head, tail) = self.data.split_at_mut(last_index * self.stride);
+            head[slot * self.stride..(slot + 1) * self.stride]
+                .copy_from_slice(&tail[..self.stride]);
+        }
+        self.sort_requested = false;
+        self.clear_culling();
         self.handles.swap(slot, last_index);
-        self.transforms.pop();
+        self.visible.swap(slot, last_index);
+        self.data.truncate(last_index * self.stride);
         let _removed_handle = self.handles.pop();
+        if self.visible.pop() == Some(false) {
+            self.hidden -= 1;
+            self.layout_dirty = true;
+        }
+        if self.packed() {
+            self.layout_dirty = true;
+        }
+        self.pending.remove(&last_index);
 
-        let moved_handle = if slot < self.transforms.len() {
-            let handle = self.handles[slot];
-            let matrix = self.transforms[slot];
-            self.pending.insert(slot, matrix);
-            Some(handle)
+        let moved_handle = if slot < self.handles.len() {
+            self.pending.insert(slot);
+            Some(self.handles[slot])
         } else {
             None
         };
@@ -95,68 +247,131 @@ impl MeshInstances {
         if self.capacity >= min_capacity.max(1) {
             return Ok(());
         }
-        let mut new_capacity = self.capacity.max(1);
-        while new_capacity < min_capacity {
-            new_capacity *= 2;
-        }
-        self.capacity = new_capacity;
+        self.capacity = grown_capacity(self.capacity, min_capacity, self.growth_factor);
         self.buffer.bind_array_buffer();
         gl.buffer_data_with_i32(
             Gl::ARRAY_BUFFER,
-            (self.capacity * MATRIX_FLOATS * std::mem::size_of::<f32>()) as i32,
+            (self.capacity * self.stride * std::mem::size_of::<f32>()) as i32,
             Gl::DYNAMIC_DRAW,
         );
         self.upload_all(gl);
         Ok(())
     }
 
-    pub(crate) fn upload_all(&self, gl: &Gl) {
-        if self.transforms.is_empty() {
-            return;
+    fn upload_packed(&mut self, gl: &Gl) {
+        self.scratch.clear();
+        for slot in 0..self.handles.len() {
+            if self.is_drawn(slot) {
+                self.scratch
+                    .extend_from_slice(&self.data[slot * self.stride..(slot + 1) * self.stride]);
+            }
         }
-        let mut flat = Vec::with_capacity(self.transforms.len() * MATRIX_FLOATS);
-        for matrix in &self.transforms {
-            flat.extend_from_slice(matrix);
+        let packed = std::mem::take(&mut self.scratch);
+        self.write_chunk(gl, 0, &packed);
+        self.scratch = packed;
+        self.scratch.clear();
+    }
+
+    pub(crate) fn upload_all(&mut self, gl: &Gl) {
+        // a full upload restores the unpacked layout
+        self.layout_dirty = self.packed();
+        if self.data.is_empty() {
+            return;
         }
         self.buffer.bind_array_buffer();
-        let view = unsafe { Float32Array::view(&flat) };
+        let view = unsafe { Float32Array::view(&self.data) };
         gl.buffer_sub_data_with_f64_and_array_buffer_view(Gl::ARRAY_BUFFER, 0.0, &view);
     }
 
     pub(crate) fn defragment(&mut self, gl: &Gl) {
-        self.capacity = self.transforms.len().max(1);
+        self.capacity = self.handles.len().max(1);
         self.buffer.bind_array_buffer();
         gl.buffer_data_with_i32(
             Gl::ARRAY_BUFFER,
-            (self.capacity * MATRIX_FLOATS * std::mem::size_of::<f32>()) as i32,
+            (self.capacity * self.stride * std::mem::size_of::<f32>()) as i32,
             Gl::DYNAMIC_DRAW,
         );
         self.upload_all(gl);
         self.pending.clear();
     }
 
+    // Orders the next upload by ascending `depth` (view-space z, so farthest
+    // first) and returns the mean depth; None when nothing is visible.
+    pub(crate) fn sort_for_draw(&mut self, depth: impl Fn(&[f32]) -> f32) -> Option<f32> {
+        self.draw_order.clear();
+        for slot in 0..self.handles.len() {
+            if self.is_drawn(slot) {
+                self.draw_order.push((depth(self.slot_values(slot)), slot));
+            }
+        }
+        if self.draw_order.is_empty() {
+            return None;
+        }
+        self.draw_order.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.sort_requested = true;
+        let total: f32 = self.draw_order.iter().map(|(depth, _)| depth).sum();
+        Some(total / self.draw_order.len() as f32)
+    }
+
+    fn upload_sorted(&mut self, gl: &Gl) {
+        self.scratch.clear();
+        for &(_, slot) in &self.draw_order {
+            self.scratch
+                .extend_from_slice(&self.data[slot * self.stride..(slot + 1) * self.stride]);
+        }
+        let sorted = std::mem::take(&mut self.scratch);
+        self.write_chunk(gl, 0, &sorted);
+        self.scratch = sorted;
+        self.scratch.clear();
+    }
+
     pub(crate) fn flush_pending(&mut self, gl: &Gl) {
+        if std::mem::take(&mut self.sort_requested) {
+            self.upload_sorted(gl);
+            self.pending.clear();
+            self.layout_dirty = false;
+            self.reordered = true;
+            return;
+        }
+        if std::mem::take(&mut self.reordered) {
+            self.layout_dirty = true; // back to slot order
+        }
+        if self.packed() {
+            if self.layout_dirty || !self.pending.is_empty() {
+                self.upload_packed(gl);
+            }
+            self.pending.clear();
+            self.layout_dirty = false;
+            return;
+        }
+        if self.layout_dirty {
+            // the last hidden or culled slot came back: undo the packing
+            self.upload_all(gl);
+            self.pending.clear();
+            return;
+        }
         if self.pending.is_empty() {
             return;
         }
         self.scratch.clear();
         let mut current_start: Option<usize> = None;
         let mut last_slot = 0usize;
-        for (slot, matrix) in self.pending.iter() {
+        for &slot in self.pending.iter() {
+            let values = &self.data[slot * self.stride..(slot + 1) * self.stride];
             if let Some(start) = current_start {
-                if *slot == last_slot + 1 {
-                    self.scratch.extend_from_slice(matrix);
+                if slot == last_slot + 1 {
+                    self.scratch.extend_from_slice(values);
                 } else {
                     self.write_chunk(gl, start, &self.scratch);
                     self.scratch.clear();
-                    self.scratch.extend_from_slice(matrix);
-                    current_start = Some(*slot);
+                    self.scratch.extend_from_slice(values);
+                    current_start = Some(slot);
                 }
             } else {
-                current_start = Some(*slot);
-                self.scratch.extend_from_slice(matrix);
+                current_start = Some(slot);
+                self.scratch.extend_from_slice(values);
             }
-            last_slot = *slot;
+            last_slot = slot;
         }
         if let Some(start) = current_start {
             self.write_chunk(gl, start, &self.scratch);
@@ -170,8 +385,40 @@ impl MeshInstances {
             return;
         }
         self.buffer.bind_array_buffer();
-        let offset = (start_slot * MATRIX_FLOATS * std::mem::size_of::<f32>()) as f64;
+        let offset = (start_slot * self.stride * std::mem::size_of::<f32>()) as f64;
         let view = unsafe { Float32Array::view(data) };
         gl.buffer_sub_data_with_f64_and_array_buffer_view(Gl::ARRAY_BUFFER, offset, &view);
     }
+
+    fn check_stride(&self, values: &[f32]) -> Result<(), JsValue> {
+        if values.len() != self.stride {
+            return Err(error(
+                "instance data does not match the mesh instance layout",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn grown_capacity(current: usize, min_capacity: usize, factor: f32) -> usize {
+    let mut capacity = current.max(1);
+    while capacity < min_capacity {
+        // always grow by at least one slot so small buffers make progress
+        capacity = ((capacity as f32 * factor).ceil() as usize).max(capacity + 1);
+    }
+    capacity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capacity_grows_by_factor() {
+        assert_eq!(grown_capacity(256, 257, 2.0), 512);
+        assert_eq!(grown_capacity(256, 257, 1.5), 384);
+        assert_eq!(grown_capacity(256, 400, 1.5), 576);
+        assert_eq!(grown_capacity(1, 2, 1.1), 2);
+        assert_eq!(grown_capacity(0, 1, 2.0), 1);
+    }
 }
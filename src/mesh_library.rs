@@ -0,0 +1,46 @@
+use js_sys::Float32Array;
+use wasm_bindgen::prelude::*;
+
+use crate::batcher::{MESH_VERTEX_STRIDE, Mesh};
+use crate::utils::{array_to_vec, error};
+
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct MeshLibrary {
+    meshes: Vec<Mesh>,
+}
+
+#[wasm_bindgen]
+impl MeshLibrary {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> MeshLibrary {
+        MeshLibrary::default()
+    }
+
+    pub fn add(&mut self, vertices: &Float32Array) -> Result<u32, JsValue> {
+        let mesh = Mesh::new(array_to_vec(vertices)).map_err(error)?;
+        self.meshes.push(mesh);
+        Ok((self.meshes.len() - 1) as u32)
+    }
+
+    pub fn len(&self) -> u32 {
+        self.meshes.len() as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.meshes.is_empty()
+    }
+
+    pub fn vertex_count(&self, id: u32) -> Result<u32, JsValue> {
+        let mesh = self.get(id)?;
+        Ok((mesh.raw().len() / MESH_VERTEX_STRIDE) as u32)
+    }
+}
+
+impl MeshLibrary {
+    pub(crate) fn get(&self, id: u32) -> Result<&Mesh, JsValue> {
+        self.meshes
+            .get(id as usize)
+            .ok_or_else(|| error("invalid mesh library id"))
+    }
+}
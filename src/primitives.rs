@@ -0,0 +1,218 @@
+#![cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+
+use std::f32::consts::PI;
+
+use crate::batcher::MESH_VERTEX_STRIDE;
+
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const MAX_SPHERE_SEGMENTS: u32 = 256;
+
+type Vec3 = [f32; 3];
+
+// Generators emit counter-clockwise triangles seen from outside, so meshes
+// survive the batched pass's back-face culling. `ccw = false` reverses every
+// triangle for pipelines that cull the other way.
+
+pub fn cube(size: f32, ccw: bool) -> Result<Vec<f32>, &'static str> {
+    check_extent(size, "cube size must be positive and finite")?;
+    let h = size * 0.5;
+    // (normal, u, v) with u x v = normal
+    let faces: [(Vec3, Vec3, Vec3); 6] = [
+        ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]),
+    ];
+    let mut out = Vec::with_capacity(36 * MESH_VERTEX_STRIDE);
+    for (normal, u, v) in faces {
+        let corner = |su: f32, sv: f32| -> Vec3 {
+            [
+                (normal[0] + u[0] * su + v[0] * sv) * h,
+                (normal[1] + u[1] * su + v[1] * sv) * h,
+                (normal[2] + u[2] * su + v[2] * sv) * h,
+            ]
+        };
+        push_quad(
+            &mut out,
+            [
+                corner(-1.0, -1.0),
+                corner(1.0, -1.0),
+                corner(1.0, 1.0),
+                corner(-1.0, 1.0),
+            ],
+        );
+    }
+    Ok(with_winding(out, ccw))
+}
+
+pub fn quad(width: f32, height: f32, ccw: bool) -> Result<Vec<f32>, &'static str> {
+    check_extent(width, "quad width must be positive and finite")?;
+    check_extent(height, "quad height must be positive and finite")?;
+    let (x, y) = (width * 0.5, height * 0.5);
+    let mut out = Vec::with_capacity(6 * MESH_VERTEX_STRIDE);
+    push_quad(
+        &mut out,
+        [[-x, -y, 0.0], [x, -y, 0.0], [x, y, 0.0], [-x, y, 0.0]],
+    );
+    Ok(with_winding(out, ccw))
+}
+
+pub fn sphere(radius: f32, segments: u32, ccw: bool) -> Result<Vec<f32>, &'static str> {
+    check_extent(radius, "sphere radius must be positive and finite")?;
+    if !(3..=MAX_SPHERE_SEGMENTS).contains(&segments) {
+        return Err("sphere segments must be between 3 and 256");
+    }
+    let stacks = segments as usize;
+    let slices = stacks * 2;
+    let point = |stack: usize, slice: usize| -> Vec3 {
+        let theta = PI * stack as f32 / stacks as f32;
+        let phi = 2.0 * PI * slice as f32 / slices as f32;
+        [
+            radius * theta.sin() * phi.cos(),
+            radius * theta.cos(),
+            -radius * theta.sin() * phi.sin(),
+        ]
+    };
+
+    let mut out = Vec::with_capacity(slices * (stacks - 1) * 6 * MESH_VERTEX_STRIDE);
+    for stack in 0..stacks {
+        for slice in 0..slices {
+            let a = point(stack, slice);
+            let b = point(stack + 1, slice);
+            let c = point(stack + 1, slice + 1);
+            let d = point(stack, slice + 1);
+            // the pole rows collapse one triangle of each quad
+            if stack != stacks - 1 {
+                push_triangle(&mut out, [a, b, c]);
+            }
+            if stack != 0 {
+                push_triangle(&mut out, [a, c, d]);
+            }
+        }
+    }
+    Ok(with_winding(out, ccw))
+}
+
+fn check_extent(value: f32, message: &'static str) -> Result<(), &'static str> {
+    if value.is_finite() && value > 0.0 {
+        Ok(())
+    } else {
+        Err(message)
+    }
+}
+
+// Swaps the last two vertices of every triangle when clockwise is wanted.
+fn with_winding(mut out: Vec<f32>, ccw: bool) -> Vec<f32> {
+    if !ccw {
+        for triangle in out.chunks_exact_mut(3 * MESH_VERTEX_STRIDE) {
+            let (_, rest) = triangle.split_at_mut(MESH_VERTEX_STRIDE);
+            let (b, c) = rest.split_at_mut(MESH_VERTEX_STRIDE);
+            b.swap_with_slice(c);
+        }
+    }
+    out
+}
+
+fn push_quad(out: &mut Vec<f32>, [a, b, c, d]: [Vec3; 4]) {
+    push_triangle(out, [a, b, c]);
+    push_triangle(out, [a, c, d]);
+}
+
+fn push_triangle(out: &mut Vec<f32>, vertices: [Vec3; 3]) {
+    for position in vertices {
+        out.extend_from_slice(&position);
+        out.extend_from_slice(&WHITE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(data: &[f32]) -> Vec<Vec3> {
+        data.chunks_exact(MESH_VERTEX_STRIDE)
+            .map(|vertex| [vertex[0], vertex[1], vertex[2]])
+            .collect()
+    }
+
+    // every triangle's normal should point away from the origin
+    fn assert_outward(data: &[f32]) {
+        for tri in positions(data).chunks_exact(3) {
+            let e1 = [
+                tri[1][0] - tri[0][0],
+                tri[1][1] - tri[0][1],
+                tri[1][2] - tri[0][2],
+            ];
+            let e2 = [
+                tri[2][0] - tri[0][0],
+                tri[2][1] - tri[0][1],
+                tri[2][2] - tri[0][2],
+            ];
+            let normal = [
+                e1[1] * e2[2] - e1[2] * e2[1],
+                e1[2] * e2[0] - e1[0] * e2[2],
+                e1[0] * e2[1] - e1[1] * e2[0],
+            ];
+            let centroid: Vec3 = std::array::from_fn(|i| tri[0][i] + tri[1][i] + tri[2][i]);
+            let facing: f32 = (0..3).map(|i| normal[i] * centroid[i]).sum();
+            assert!(facing > 0.0, "triangle {tri:?} winds inwards");
+        }
+    }
+
+    #[test]
+    fn cube_layout() {
+        let data = cube(2.0, true).unwrap();
+        assert_eq!(data.len(), 36 * MESH_VERTEX_STRIDE);
+        assert!(
+            positions(&data)
+                .iter()
+                .all(|p| p.iter().all(|c| c.abs() == 1.0))
+        );
+        assert_outward(&data);
+        assert!(cube(0.0, true).is_err());
+    }
+
+    #[test]
+    fn sphere_layout() {
+        let segments = 8;
+        let data = sphere(1.5, segments, true).unwrap();
+        let slices = segments as usize * 2;
+        let expected_vertices = slices * (segments as usize - 1) * 6;
+        assert_eq!(data.len(), expected_vertices * MESH_VERTEX_STRIDE);
+        for p in positions(&data) {
+            let length = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+            assert!((length - 1.5).abs() < 1e-4);
+        }
+        assert_outward(&data);
+        assert!(sphere(1.0, 2, true).is_err());
+        assert!(sphere(-1.0, 8, true).is_err());
+    }
+
+    #[test]
+    fn quad_layout() {
+        let data = quad(4.0, 2.0, true).unwrap();
+        assert!(data.len().is_multiple_of(MESH_VERTEX_STRIDE));
+        assert_eq!(data.len() / MESH_VERTEX_STRIDE, 6);
+        assert_eq!(&data[3..7], &WHITE);
+        for tri in positions(&data).chunks_exact(3) {
+            let z = (tri[1][0] - tri[0][0]) * (tri[2][1] - tri[0][1])
+                - (tri[1][1] - tri[0][1]) * (tri[2][0] - tri[0][0]);
+            assert!(z > 0.0, "quad should face +Z");
+        }
+        assert!(quad(1.0, f32::NAN, true).is_err());
+    }
+
+    #[test]
+    fn clockwise_reverses_triangles() {
+        let ccw = positions(&cube(2.0, true).unwrap());
+        let cw = positions(&cube(2.0, false).unwrap());
+        assert_eq!(ccw.len(), cw.len());
+        for (front, back) in ccw.chunks_exact(3).zip(cw.chunks_exact(3)) {
+            assert_eq!([front[0], front[2], front[1]], [back[0], back[1], back[2]]);
+        }
+        // colors travel with their vertices
+        assert_eq!(&quad(1.0, 1.0, false).unwrap()[3..7], &WHITE);
+    }
+}
@@ -0,0 +1,181 @@
+#![cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+
+use serde::{Deserialize, Serialize};
+
+use crate::batcher::{InstanceFormat, MATRIX_FLOATS, MAX_EXTRA_COLUMNS};
+use crate::gpu::Primitive;
+use crate::snapshot::InstanceSnapshot;
+
+pub const SCENE_VERSION: u32 = 1;
+
+// Self-describing dump of a batched renderer for bug reports and golden
+// tests. Matrices are always column-major, whatever the renderer's layout.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SceneDocument {
+    pub version: u32,
+    pub meshes: Vec<Option<SceneMesh>>, // null where remove_mesh left a gap
+    pub instances: Vec<InstanceSnapshot>, // same shape as snapshot()
+    pub view: Vec<f32>,
+    pub projection: Vec<f32>,
+    pub clear_color: [f32; 4],
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SceneMesh {
+    pub format: SceneFormat,
+    pub vertices: Vec<f32>, // packed (x, y, z, r, g, b, a)
+    pub depth_test: bool,
+    pub opaque: bool,
+    pub extra_columns: usize,
+    pub dynamic: bool,
+    pub interpolated: bool,
+    #[serde(default)]
+    pub primitive: ScenePrimitive,
+    pub layer: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SceneFormat {
+    Matrix,
+    Billboard,
+    Sprite,
+}
+
+impl From<InstanceFormat> for SceneFormat {
+    fn from(format: InstanceFormat) -> Self {
+        match format {
+            InstanceFormat::Matrix => SceneFormat::Matrix,
+            InstanceFormat::Billboard => SceneFormat::Billboard,
+            InstanceFormat::Sprite => SceneFormat::Sprite,
+        }
+    }
+}
+
+impl From<SceneFormat> for InstanceFormat {
+    fn from(format: SceneFormat) -> Self {
+        match format {
+            SceneFormat::Matrix => InstanceFormat::Matrix,
+            SceneFormat::Billboard => InstanceFormat::Billboard,
+            SceneFormat::Sprite => InstanceFormat::Sprite,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScenePrimitive {
+    #[default]
+    Triangles,
+    Lines,
+    Points,
+}
+
+impl From<Primitive> for ScenePrimitive {
+    fn from(primitive: Primitive) -> Self {
+        match primitive {
+            Primitive::Triangles => ScenePrimitive::Triangles,
+            Primitive::Lines => ScenePrimitive::Lines,
+            Primitive::Points => ScenePrimitive::Points,
+        }
+    }
+}
+
+impl From<ScenePrimitive> for Primitive {
+    fn from(primitive: ScenePrimitive) -> Self {
+        match primitive {
+            ScenePrimitive::Triangles => Primitive::Triangles,
+            ScenePrimitive::Lines => Primitive::Lines,
+            ScenePrimitive::Points => Primitive::Points,
+        }
+    }
+}
+
+impl SceneDocument {
+    // Instance data is checked later by the same validation restore() uses.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.version != SCENE_VERSION {
+            return Err("unsupported scene version");
+        }
+        if self.view.len() != MATRIX_FLOATS || self.projection.len() != MATRIX_FLOATS {
+            return Err("scene camera matrices must have 16 floats");
+        }
+        let camera = self
+            .view
+            .iter()
+            .chain(&self.projection)
+            .chain(&self.clear_color);
+        if camera.copied().any(|value| !value.is_finite()) {
+            return Err("scene camera and clear color must be finite");
+        }
+        for mesh in self.meshes.iter().flatten() {
+            if mesh.extra_columns > MAX_EXTRA_COLUMNS {
+                return Err("scene mesh extraColumns must be between 0 and 4");
+            }
+            if mesh.interpolated && mesh.format != SceneFormat::Matrix {
+                return Err("only matrix meshes can be interpolated");
+            }
+            if mesh.vertices.iter().any(|value| !value.is_finite()) {
+                return Err("scene mesh vertices must be finite");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document() -> SceneDocument {
+        SceneDocument {
+            version: SCENE_VERSION,
+            meshes: vec![Some(SceneMesh {
+                format: SceneFormat::Billboard,
+                vertices: vec![0.0; 21],
+                depth_test: true,
+                opaque: false,
+                extra_columns: 0,
+                dynamic: false,
+                interpolated: false,
+                primitive: ScenePrimitive::Triangles,
+                layer: 0,
+            })],
+            instances: Vec::new(),
+            view: vec![0.0; 16],
+            projection: vec![0.0; 16],
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+
+    #[test]
+    fn scene_validation() {
+        assert!(document().validate().is_ok());
+
+        let mut future = document();
+        future.version = SCENE_VERSION + 1;
+        assert!(future.validate().is_err());
+
+        let mut short_view = document();
+        short_view.view.pop();
+        assert!(short_view.validate().is_err());
+
+        let mut nan_color = document();
+        nan_color.clear_color[0] = f32::NAN;
+        assert!(nan_color.validate().is_err());
+
+        let mut interpolated_billboard = document();
+        interpolated_billboard.meshes[0]
+            .as_mut()
+            .unwrap()
+            .interpolated = true;
+        assert!(interpolated_billboard.validate().is_err());
+
+        // removed meshes are kept as gaps so later handles line up
+        let mut removed = document();
+        removed.meshes.insert(0, None);
+        assert!(removed.validate().is_ok());
+    }
+}
@@ -1,6 +1,43 @@
-use wasm_bindgen::JsValue;
+use std::cell::Cell;
+
+use wasm_bindgen::prelude::*;
 use web_sys::{WebGl2RenderingContext as Gl, WebGlProgram, WebGlShader};
 
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderPrecision {
+    Highp,
+    Mediump,
+}
+
+impl ShaderPrecision {
+    fn qualifier(self) -> &'static str {
+        match self {
+            ShaderPrecision::Highp => "highp",
+            ShaderPrecision::Mediump => "mediump",
+        }
+    }
+}
+
+thread_local! {
+    static SHADER_PRECISION: Cell<ShaderPrecision> = const { Cell::new(ShaderPrecision::Mediump) };
+}
+
+pub fn set_shader_precision(precision: ShaderPrecision) {
+    SHADER_PRECISION.with(|current| current.set(precision));
+}
+
+pub fn shader_precision() -> ShaderPrecision {
+    SHADER_PRECISION.with(|current| current.get())
+}
+
+pub fn with_precision(source: &str, precision: ShaderPrecision) -> String {
+    source.replace(
+        DEFAULT_PRECISION_LINE,
+        &format!("precision {} float;", precision.qualifier()),
+    )
+}
+
 pub fn compile_shader(gl: &Gl, shader_type: u32, source: &str) -> Result<WebGlShader, JsValue> {
     let shader = gl
         .create_shader(shader_type)
@@ -66,6 +103,16 @@ pub fn timeseries_fragment_shader_source() -> &'static str {
     TIMESERIES_FRAGMENT_SHADER_SOURCE
 }
 
+pub fn fullscreen_vertex_shader_source() -> &'static str {
+    FULLSCREEN_VERTEX_SHADER_SOURCE
+}
+
+pub fn fullscreen_fragment_shader_source() -> &'static str {
+    FULLSCREEN_FRAGMENT_SHADER_SOURCE
+}
+
+const DEFAULT_PRECISION_LINE: &str = "precision mediump float;";
+
 const VERTEX_SHADER_SOURCE: &str = r#"
 precision mediump float;
 attribute vec3 a_position;
@@ -74,27 +121,104 @@ attribute vec4 a_instance_col0;
 attribute vec4 a_instance_col1;
 attribute vec4 a_instance_col2;
 attribute vec4 a_instance_col3;
+attribute highp vec4 a_instance_params; // x = depth bias, y = flat flag, z = pick id
+attribute vec4 a_instance_color;
+// previous matrix of interpolated meshes, blended towards the current one
+attribute vec4 a_instance_prev0;
+attribute vec4 a_instance_prev1;
+attribute vec4 a_instance_prev2;
+attribute vec4 a_instance_prev3;
+// optional per-mesh extra columns; unused here, available to shader variants
+attribute vec4 a_instance_extra0;
+attribute vec4 a_instance_extra1;
+attribute vec4 a_instance_extra2;
+attribute vec4 a_instance_extra3;
 uniform mat4 u_view;
 uniform mat4 u_projection;
+uniform int u_instance_format;
+uniform int u_billboard_facing;
+uniform float u_point_size;
+uniform int u_interpolate;
+uniform float u_interpolation_alpha;
 varying vec4 v_color;
+varying highp float v_pick_id;
 
 void main() {
-    mat4 model = mat4(
-        a_instance_col0,
-        a_instance_col1,
-        a_instance_col2,
-        a_instance_col3
-    );
-    gl_Position = u_projection * u_view * model * vec4(a_position, 1.0);
-    v_color = a_color;
+    vec4 world;
+    if (u_instance_format == 1) {
+        // billboard: a_instance_col0 = (x, y, z, scale)
+        vec3 offset = a_position * a_instance_col0.w;
+        if (u_billboard_facing == 1) {
+            // rows of the view rotation are the camera basis; apply its inverse
+            vec3 right = vec3(u_view[0][0], u_view[1][0], u_view[2][0]);
+            vec3 up = vec3(u_view[0][1], u_view[1][1], u_view[2][1]);
+            vec3 back = vec3(u_view[0][2], u_view[1][2], u_view[2][2]);
+            offset = right * offset.x + up * offset.y + back * offset.z;
+        }
+        world = vec4(a_instance_col0.xyz + offset, 1.0);
+    } else if (u_instance_format == 2) {
+        // sprite: a_instance_col0 = (x, y, rotation, scale)
+        float s = sin(a_instance_col0.z);
+        float c = cos(a_instance_col0.z);
+        vec2 local = a_position.xy * a_instance_col0.w;
+        vec2 rotated = vec2(c * local.x - s * local.y, s * local.x + c * local.y);
+        world = vec4(a_instance_col0.xy + rotated, a_position.z, 1.0);
+    } else {
+        mat4 model = mat4(
+            a_instance_col0,
+            a_instance_col1,
+            a_instance_col2,
+            a_instance_col3
+        );
+        if (u_interpolate == 1) {
+            mat4 previous = mat4(
+                a_instance_prev0,
+                a_instance_prev1,
+                a_instance_prev2,
+                a_instance_prev3
+            );
+            model = previous + (model - previous) * u_interpolation_alpha;
+        }
+        world = model * vec4(a_position, 1.0);
+    }
+    gl_Position = u_projection * u_view * world;
+    // bias is in NDC depth units, scaled by w so it survives the perspective divide
+    gl_Position.z += a_instance_params.x * gl_Position.w;
+    gl_PointSize = u_point_size;
+    // flat instances ignore the vertex colors; the rest are tinted
+    v_color = a_instance_params.y > 0.5 ? a_instance_color : a_color * a_instance_color;
+    v_pick_id = a_instance_params.z;
 }
 "#;
 
 const FRAGMENT_SHADER_SOURCE: &str = r#"
 precision mediump float;
 varying vec4 v_color;
+varying highp float v_pick_id;
+uniform int u_depth_debug;
+uniform vec3 u_depth_params; // near, far, 1.0 when the projection is perspective
+uniform int u_pick;
 
 void main() {
+    if (u_pick == 1) {
+        // instance handle + 1 as little-endian RGB bytes, so 0 stays background
+        highp float id = floor(v_pick_id + 0.5) + 1.0;
+        vec3 bytes = vec3(mod(id, 256.0), mod(floor(id / 256.0), 256.0), floor(id / 65536.0));
+        gl_FragColor = vec4(bytes / 255.0, 1.0);
+        return;
+    }
+    if (u_depth_debug == 1) {
+        float depth = gl_FragCoord.z;
+        if (u_depth_params.z > 0.5) {
+            float n = u_depth_params.x;
+            float f = u_depth_params.y;
+            float z = depth * 2.0 - 1.0;
+            float linear = 2.0 * n * f / (f + n - z * (f - n));
+            depth = (linear - n) / (f - n);
+        }
+        gl_FragColor = vec4(vec3(depth), 1.0);
+        return;
+    }
     gl_FragColor = v_color;
 }
 "#;
@@ -102,8 +226,17 @@ void main() {
 const TIMESERIES_VERTEX_SHADER_SOURCE: &str = r#"
 precision mediump float;
 attribute vec2 a_position;
+attribute float a_edge; // signed pixel distance from the centre line
+attribute float a_alpha; // age fade, 1 when the series has none
+attribute vec4 a_color; // up/down tint, white when the series has none
+varying float v_edge;
+varying float v_alpha;
+varying vec4 v_color;
 
 void main() {
+    v_edge = a_edge;
+    v_alpha = a_alpha;
+    v_color = a_color;
     gl_Position = vec4(a_position, 0.0, 1.0);
 }
 "#;
@@ -111,8 +244,56 @@ void main() {
 const TIMESERIES_FRAGMENT_SHADER_SOURCE: &str = r#"
 precision mediump float;
 uniform vec4 u_color;
+uniform float u_half_width;
+uniform int u_antialias;
+varying float v_edge;
+varying float v_alpha;
+varying vec4 v_color;
+
+void main() {
+    float coverage = 1.0;
+    if (u_antialias == 1) {
+        // one-pixel ramp centred on the true edge
+        coverage = clamp(u_half_width + 0.5 - abs(v_edge), 0.0, 1.0);
+    }
+    vec4 color = u_color * v_color;
+    gl_FragColor = vec4(color.rgb, color.a * coverage * v_alpha);
+}
+"#;
+
+const FULLSCREEN_VERTEX_SHADER_SOURCE: &str = r#"
+precision mediump float;
+attribute vec2 a_position;
+varying vec2 v_uv;
 
 void main() {
-    gl_FragColor = u_color;
+    // v flipped so images uploaded top row first appear upright
+    v_uv = vec2(a_position.x * 0.5 + 0.5, 0.5 - a_position.y * 0.5);
+    gl_Position = vec4(a_position, 0.0, 1.0);
 }
 "#;
+
+const FULLSCREEN_FRAGMENT_SHADER_SOURCE: &str = r#"
+precision mediump float;
+uniform sampler2D u_texture;
+varying vec2 v_uv;
+
+void main() {
+    gl_FragColor = texture2D(u_texture, v_uv);
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn precision_substitution() {
+        let highp = with_precision(vertex_shader_source(), ShaderPrecision::Highp);
+        assert!(highp.contains("precision highp float;"));
+        assert!(!highp.contains(DEFAULT_PRECISION_LINE));
+
+        let mediump = with_precision(fragment_shader_source(), ShaderPrecision::Mediump);
+        assert_eq!(mediump, fragment_shader_source());
+    }
+}
@@ -0,0 +1,91 @@
+#![cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::batcher::MAX_PICK_HANDLE;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RendererSnapshot {
+    pub instances: Vec<InstanceSnapshot>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstanceSnapshot {
+    pub handle: u32,
+    pub mesh: u32,
+    pub values: Vec<f32>, // format data, per-instance parameters, then any extras
+}
+
+impl RendererSnapshot {
+    // `mesh_strides` holds the floats per instance of every registered mesh
+    pub fn validate(&self, mesh_strides: &[usize]) -> Result<(), &'static str> {
+        let mut handles = HashSet::with_capacity(self.instances.len());
+        for instance in &self.instances {
+            let stride = mesh_strides
+                .get(instance.mesh as usize)
+                .ok_or("snapshot references a mesh that is not registered")?;
+            if instance.values.len() != *stride {
+                return Err("snapshot instance data does not match the mesh instance layout");
+            }
+            if instance.values.iter().any(|value| !value.is_finite()) {
+                return Err("snapshot instance data must be finite");
+            }
+            if instance.handle > MAX_PICK_HANDLE {
+                return Err(
+                    "snapshot instance handles must be at most 16777214 so pick can tell them apart",
+                );
+            }
+            if !handles.insert(instance.handle) {
+                return Err("snapshot contains duplicate instance handles");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(handle: u32, mesh: u32, stride: usize) -> InstanceSnapshot {
+        InstanceSnapshot {
+            handle,
+            mesh,
+            values: vec![1.0; stride],
+        }
+    }
+
+    #[test]
+    fn snapshot_validation() {
+        let strides = [24, 12]; // a matrix mesh and a billboard mesh
+        let valid = RendererSnapshot {
+            instances: vec![instance(3, 0, 24), instance(0, 1, 12)],
+        };
+        assert!(valid.validate(&strides).is_ok());
+
+        let unknown_mesh = RendererSnapshot {
+            instances: vec![instance(0, 2, 24)],
+        };
+        assert!(unknown_mesh.validate(&strides).is_err());
+
+        let wrong_layout = RendererSnapshot {
+            instances: vec![instance(0, 1, 24)],
+        };
+        assert!(wrong_layout.validate(&strides).is_err());
+
+        let duplicate = RendererSnapshot {
+            instances: vec![instance(1, 0, 24), instance(1, 1, 12)],
+        };
+        assert!(duplicate.validate(&strides).is_err());
+
+        let unpickable = RendererSnapshot {
+            instances: vec![instance(MAX_PICK_HANDLE + 1, 0, 24)],
+        };
+        assert!(unpickable.validate(&strides).is_err());
+        let last_pickable = RendererSnapshot {
+            instances: vec![instance(MAX_PICK_HANDLE, 0, 24)],
+        };
+        assert!(last_pickable.validate(&strides).is_ok());
+    }
+}
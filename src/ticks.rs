@@ -0,0 +1,85 @@
+const MAX_TICKS: usize = 1000;
+
+// Tick positions inside [min, max] spaced at 1, 2 or 5 x 10^n, picking the
+// smallest such step that yields at most about `target_count` intervals.
+// Empty when no multiple of the step falls inside the range.
+pub fn nice_ticks(min: f32, max: f32, target_count: u32) -> Result<Vec<f32>, &'static str> {
+    if !min.is_finite() || !max.is_finite() {
+        return Err("tick range must be finite");
+    }
+    if max <= min {
+        return Err("tick range max must be greater than min");
+    }
+    if target_count == 0 {
+        return Err("tick count must be positive");
+    }
+    let step = nice_step((max as f64 - min as f64) / target_count as f64);
+    let first = snap_to_integer(min as f64 / step).ceil() as i64;
+    let last = snap_to_integer(max as f64 / step).floor() as i64;
+    if last < first {
+        return Ok(Vec::new());
+    }
+    if (last - first) as usize >= MAX_TICKS {
+        return Err("tick range is too large for the requested count");
+    }
+    // multiples of the step, so accumulated error never drifts the labels
+    Ok((first..=last).map(|k| (k as f64 * step) as f32).collect())
+}
+
+// Endpoints arrive as f32, so 0.1 / 0.1 comes out a hair above 1 and would
+// ceil past a tick sitting exactly on the bound.
+fn snap_to_integer(value: f64) -> f64 {
+    let nearest = value.round();
+    if (value - nearest).abs() <= 1e-6 * nearest.abs().max(1.0) {
+        nearest
+    } else {
+        value
+    }
+}
+
+fn nice_step(raw: f64) -> f64 {
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let normalized = raw / magnitude;
+    let factor = [1.0, 2.0, 5.0]
+        .into_iter()
+        .find(|&factor| normalized <= factor)
+        .unwrap_or(10.0);
+    factor * magnitude
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_land_on_round_numbers() {
+        assert_eq!(
+            nice_ticks(0.0, 100.0, 5),
+            Ok(vec![0.0, 20.0, 40.0, 60.0, 80.0, 100.0])
+        );
+        assert_eq!(nice_ticks(-3.7, 12.2, 4), Ok(vec![0.0, 5.0, 10.0]));
+        assert_eq!(
+            nice_ticks(0.0, 1.0, 5),
+            Ok(vec![0.0, 0.2, 0.4, 0.6, 0.8, 1.0])
+        );
+        assert_eq!(
+            nice_ticks(1.0, 9.0, 8),
+            Ok(vec![1., 2., 3., 4., 5., 6., 7., 8., 9.])
+        );
+        assert_eq!(nice_ticks(1993.0, 2027.0, 3), Ok(vec![2000.0, 2020.0]));
+        assert!(nice_ticks(1.0, 1.0, 5).is_err());
+        assert!(nice_ticks(0.0, f32::INFINITY, 5).is_err());
+        assert!(nice_ticks(0.0, 1.0, 0).is_err());
+    }
+
+    #[test]
+    fn endpoints_on_a_tick_are_kept() {
+        assert_eq!(nice_ticks(0.1, 0.5, 4), Ok(vec![0.1, 0.2, 0.3, 0.4, 0.5]));
+        assert_eq!(
+            nice_ticks(0.3, 0.9, 6),
+            Ok(vec![0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9])
+        );
+        // no multiple of the 0.1 step between the bounds
+        assert_eq!(nice_ticks(0.11, 0.19, 1), Ok(vec![]));
+    }
+}
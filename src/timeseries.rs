@@ -1,19 +1,41 @@
 use js_sys::{Array, Float32Array, Object, Reflect};
-use std::cell::RefCell;
+use serde::Serialize;
+use std::cell::{RefCell, RefMut};
 use std::rc::Rc;
-use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use wasm_bindgen::JsValue;
-use web_sys::{WebGl2RenderingContext as Gl, WebGlProgram, WebGlUniformLocation};
+use wasm_bindgen::prelude::*;
+use web_sys::{OffscreenCanvas, WebGl2RenderingContext as Gl, WebGlProgram, WebGlUniformLocation};
 
-use crate::context::{shared_context, SharedContext};
-use crate::gpu::GlBuffer;
+use crate::color;
+use crate::context::{SharedContext, Viewport, shared_context, shared_context_offscreen};
+use crate::gpu::{BlendMode, GlBuffer, query_size_range};
 use crate::shader::{
-    compile_shader, link_program, timeseries_fragment_shader_source,
-    timeseries_vertex_shader_source,
+    compile_shader, link_program, shader_precision, timeseries_fragment_shader_source,
+    timeseries_vertex_shader_source, with_precision,
 };
 use crate::utils::{array_to_vec, clamp_unit, error};
 
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AxisScale {
+    Linear,
+    Log10,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineJoin {
+    Miter,
+    Bevel,
+    Round,
+}
+
+const DEFAULT_MITER_LIMIT: f32 = 4.0;
+const ROUND_JOIN_STEP: f32 = std::f32::consts::PI / 8.0;
+const AA_FEATHER: f32 = 0.5;
+const DEFAULT_GHOST_OPACITY: f32 = 0.35;
+
 #[wasm_bindgen]
 pub struct TimeSeriesRenderer {
     inner: Rc<RefCell<TimeSeriesRendererInner>>,
@@ -27,19 +49,191 @@ impl TimeSeriesRenderer {
         TimeSeriesRenderer::with_shared_context(context)
     }
 
+    pub fn from_offscreen(canvas: OffscreenCanvas) -> Result<TimeSeriesRenderer, JsValue> {
+        let context = shared_context_offscreen(canvas)?;
+        TimeSeriesRenderer::with_shared_context(context)
+    }
+
     pub fn resize(&self, width: u32, height: u32) {
         let context = self.context_handle();
         context.resize(width, height);
     }
 
+    pub fn is_usable(&self) -> bool {
+        self.context_handle().is_usable()
+    }
+
+    pub fn set_max_dimension(&self, max: u32) -> Result<(), JsValue> {
+        self.context_handle().set_max_dimension(max)
+    }
+
     pub fn clear(&self, r: f32, g: f32, b: f32, a: f32) {
         let color = [clamp_unit(r), clamp_unit(g), clamp_unit(b), clamp_unit(a)];
         let context = self.context_handle();
-        context.clear(color, None);
+        context.clear(color, None, None);
+    }
+
+    pub fn clear_hex(&self, hex: &str) -> Result<(), JsValue> {
+        let color = color::parse_hex(hex).map_err(error)?;
+        self.context_handle().clear(color, None, None);
+        Ok(())
     }
 
     pub fn set_series(&self, timestamps: &Float32Array, series: &Array) -> Result<(), JsValue> {
-        self.inner.borrow_mut().set_series(timestamps, series)
+        self.inner_mut().set_series(timestamps, series)
+    }
+
+    // Streams new samples onto the end of the current data: one Float32Array
+    // of values per existing series, each as long as `timestamps`, which must
+    // keep increasing.
+    pub fn append_samples(&self, timestamps: &Float32Array, values: &Array) -> Result<(), JsValue> {
+        self.inner_mut().append_samples(timestamps, values)
+    }
+
+    // One new column: a value per existing series at a timestamp later than
+    // the last one.
+    pub fn append_sample(&self, timestamp: f32, values: &Float32Array) -> Result<(), JsValue> {
+        self.inner_mut().append_sample(timestamp, values)
+    }
+
+    // Keeps only the newest `max` samples, dropping the first ones in order
+    // as new ones arrive; `undefined` keeps everything.
+    pub fn set_max_samples(&self, max: Option<u32>) -> Result<(), JsValue> {
+        if max == Some(0) {
+            return Err(error("max samples must be at least 1"));
+        }
+        let mut inner = self.inner_mut();
+        inner.max_samples = max.map(|max| max as usize);
+        inner.trim_to_max_samples()?;
+        inner.reproject()
+    }
+
+    pub fn set_ghost_series(
+        &self,
+        timestamps: &Float32Array,
+        series: &Array,
+    ) -> Result<(), JsValue> {
+        self.inner_mut().set_ghost_series(timestamps, series)
+    }
+
+    pub fn clear_ghost(&self) {
+        self.inner_mut().ghost = None;
+    }
+
+    pub fn set_ghost_opacity(&self, opacity: f32) -> Result<(), JsValue> {
+        if !(0.0..=1.0).contains(&opacity) {
+            return Err(error("ghost opacity must be between 0 and 1"));
+        }
+        let mut inner = self.inner_mut();
+        inner.ghost_opacity = opacity;
+        inner.reproject()
+    }
+
+    // When enabled the ghost is normalised to its own data extents instead of
+    // the primary chart's (e.g. last week's timestamps drawn over this week's).
+    pub fn set_ghost_own_range(&self, enabled: bool) -> Result<(), JsValue> {
+        let mut inner = self.inner_mut();
+        inner.ghost_own_range = enabled;
+        inner.reproject()
+    }
+
+    // Reference time for series with a `fade` option; undefined goes back to
+    // fading from the newest sample.
+    pub fn set_now(&self, timestamp: Option<f32>) -> Result<(), JsValue> {
+        if timestamp.is_some_and(|t| !t.is_finite()) {
+            return Err(error("now must be a finite timestamp"));
+        }
+        let mut inner = self.inner_mut();
+        inner.now = timestamp;
+        inner.reproject()
+    }
+
+    pub fn set_plot_margins(
+        &self,
+        left: f32,
+        right: f32,
+        top: f32,
+        bottom: f32,
+    ) -> Result<(), JsValue> {
+        let area = PlotArea::from_margins(left, right, top, bottom).map_err(error)?;
+        let mut inner = self.inner_mut();
+        inner.plot_area = area;
+        inner.reproject()
+    }
+
+    pub fn set_time_scale(&self, scale: AxisScale) -> Result<(), JsValue> {
+        self.inner_mut().set_time_scale(scale)
+    }
+
+    pub fn set_blend_mode(&self, mode: BlendMode) {
+        self.inner_mut().blend_mode = mode;
+    }
+
+    pub fn set_line_join(&self, join: LineJoin) -> Result<(), JsValue> {
+        let mut inner = self.inner_mut();
+        inner.line_join = join;
+        inner.reproject()
+    }
+
+    pub fn set_miter_limit(&self, limit: f32) -> Result<(), JsValue> {
+        if !limit.is_finite() || limit < 1.0 {
+            return Err(error("miter limit must be at least 1"));
+        }
+        let mut inner = self.inner_mut();
+        inner.miter_limit = limit;
+        inner.reproject()
+    }
+
+    // Breaks every line wherever consecutive timestamps are more than
+    // `max_delta` apart (in timestamp units); undefined joins them again.
+    pub fn set_gap_threshold(&self, max_delta: Option<f32>) -> Result<(), JsValue> {
+        if max_delta.is_some_and(|delta| !delta.is_finite() || delta <= 0.0) {
+            return Err(error("gap threshold must be a positive number"));
+        }
+        let mut inner = self.inner_mut();
+        inner.gap_threshold = max_delta;
+        inner.reproject()
+    }
+
+    pub fn set_viewport(&self, x: u32, y: u32, width: u32, height: u32) -> Result<(), JsValue> {
+        let viewport = Viewport::new(x, y, width, height).map_err(error)?;
+        self.inner_mut().viewport = Some(viewport);
+        Ok(())
+    }
+
+    pub fn clear_viewport(&self) {
+        self.inner_mut().viewport = None;
+    }
+
+    pub fn set_series_antialias(&self, index: u32, enabled: bool) -> Result<(), JsValue> {
+        self.inner_mut()
+            .set_series_antialias(index as usize, enabled)
+    }
+
+    pub fn pan_by_pixels(&self, dx: f32, dy: f32) -> Result<(), JsValue> {
+        self.inner_mut().pan_by_pixels(dx, dy)
+    }
+
+    pub fn zoom_at_pixel(&self, x: f32, y: f32, factor: f32) -> Result<(), JsValue> {
+        self.inner_mut().zoom_at_pixel(x, y, factor)
+    }
+
+    pub fn reset_view(&self) -> Result<(), JsValue> {
+        self.inner_mut().reset_view()
+    }
+
+    // Pins an axis instead of autoscaling it to the data. Time is in axis
+    // units, so log10 of the timestamps on a log scale.
+    pub fn set_time_domain(&self, min: f32, max: f32) -> Result<(), JsValue> {
+        self.inner_mut().set_time_domain(min, max)
+    }
+
+    pub fn set_value_domain(&self, min: f32, max: f32) -> Result<(), JsValue> {
+        self.inner_mut().set_value_domain(min, max)
+    }
+
+    pub fn clear_fixed_domains(&self) -> Result<(), JsValue> {
+        self.inner_mut().clear_fixed_domains()
     }
 
     pub fn draw(&self) -> Result<(), JsValue> {
@@ -50,10 +244,28 @@ impl TimeSeriesRenderer {
         self.inner.borrow().series_count()
     }
 
+    pub fn sample_at(&self, timestamp: f32) -> Float32Array {
+        Float32Array::from(self.inner.borrow().sample_at(timestamp).as_slice())
+    }
+
+    pub fn clear_series(&self, index: u32) -> Result<(), JsValue> {
+        self.inner_mut().clear_series(index as usize)
+    }
+
+    pub fn clear_series_pool(&self) {
+        self.inner_mut().clear_series_pool();
+    }
+
     pub fn sample_count(&self) -> u32 {
         self.inner.borrow().sample_count()
     }
 
+    // { points, capacity } of one series' vertex buffer, both in vertices.
+    pub fn series_stats(&self, index: u32) -> Result<JsValue, JsValue> {
+        let stats = self.inner.borrow().series_stats(index as usize)?;
+        serde_wasm_bindgen::to_value(&stats).map_err(JsValue::from)
+    }
+
     pub fn time_domain(&self) -> Float32Array {
         Float32Array::from(self.inner.borrow().time_range.as_slice())
     }
@@ -75,22 +287,53 @@ impl TimeSeriesRenderer {
         self.inner.clone()
     }
 
-    fn context_handle(&self) -> SharedContext {
+    pub(crate) fn context_handle(&self) -> SharedContext {
         self.inner.borrow().context.clone()
     }
+
+    // Borrow for JS calls that change what the next frame shows.
+    fn inner_mut(&self) -> RefMut<'_, TimeSeriesRendererInner> {
+        let mut inner = self.inner.borrow_mut();
+        inner.dirty = true;
+        inner
+    }
 }
 
 pub(crate) struct TimeSeriesRendererInner {
     pub(crate) context: SharedContext,
     gl: Gl,
     program: WebGlProgram,
-    position_location: u32,
-    color_location: WebGlUniformLocation,
+    locations: LineLocations,
     lines: Vec<LineSeries>,
+    line_pool: Vec<LineSeries>,
+    raw_samples: Vec<f32>, // timestamps as given, before the time scale
+    samples: Vec<f32>,
+    sources: Vec<SeriesStage>,
+    data_time_range: [f32; 2],
+    data_value_range: [f32; 2],
+    time_window: Option<[f32; 2]>, // pan/zoom, over the fixed domain
+    value_window: Option<[f32; 2]>,
+    fixed_time_domain: Option<[f32; 2]>, // over the data extents
+    fixed_value_domain: Option<[f32; 2]>,
     time_range: [f32; 2],
     value_range: [f32; 2],
     sample_count: u32,
     line_width_limits: [f32; 2],
+    time_scale: AxisScale,
+    plot_area: PlotArea,
+    line_join: LineJoin,
+    blend_mode: BlendMode,
+    miter_limit: f32,
+    projected_size: (u32, u32),
+    viewport: Option<Viewport>,
+    antialias: Vec<bool>, // per series index, defaults to smooth
+    ghost: Option<GhostLayer>,
+    ghost_opacity: f32,
+    ghost_own_range: bool,
+    now: Option<f32>, // fade reference; None follows the newest sample
+    gap_threshold: Option<f32>,
+    max_samples: Option<usize>, // oldest samples past this are dropped
+    dirty: bool,                // changed since the last render pass
 }
 
 impl TimeSeriesRendererInner {
@@ -101,180 +344,947 @@ impl TimeSeriesRendererInner {
         gl.enable(Gl::BLEND);
         gl.blend_func(Gl::SRC_ALPHA, Gl::ONE_MINUS_SRC_ALPHA);
 
-        let vert_shader =
-            compile_shader(&gl, Gl::VERTEX_SHADER, timeseries_vertex_shader_source())?;
-        let frag_shader =
-            compile_shader(&gl, Gl::FRAGMENT_SHADER, timeseries_fragment_shader_source())?;
+        let precision = shader_precision();
+        let vert_shader = compile_shader(
+            &gl,
+            Gl::VERTEX_SHADER,
+            &with_precision(timeseries_vertex_shader_source(), precision),
+        )?;
+        let frag_shader = compile_shader(
+            &gl,
+            Gl::FRAGMENT_SHADER,
+            &with_precision(timeseries_fragment_shader_source(), precision),
+        )?;
         let program = link_program(&gl, &vert_shader, &frag_shader)?;
 
-        let position_location = gl
-            .get_attrib_location(&program, "a_position")
-            .try_into()
-            .map_err(|_| error("a_position attribute missing"))?;
-        let color_location = gl
-            .get_uniform_location(&program, "u_color")
-            .ok_or_else(|| error("u_color uniform missing"))?;
-        let line_width_limits = query_line_width_limits(&gl);
+        let locations = LineLocations {
+            position: gl
+                .get_attrib_location(&program, "a_position")
+                .try_into()
+                .map_err(|_| error("a_position attribute missing"))?,
+            edge: gl
+                .get_attrib_location(&program, "a_edge")
+                .try_into()
+                .map_err(|_| error("a_edge attribute missing"))?,
+            alpha: gl
+                .get_attrib_location(&program, "a_alpha")
+                .try_into()
+                .map_err(|_| error("a_alpha attribute missing"))?,
+            tint: gl
+                .get_attrib_location(&program, "a_color")
+                .try_into()
+                .map_err(|_| error("a_color attribute missing"))?,
+            color: gl
+                .get_uniform_location(&program, "u_color")
+                .ok_or_else(|| error("u_color uniform missing"))?,
+            half_width: gl
+                .get_uniform_location(&program, "u_half_width")
+                .ok_or_else(|| error("u_half_width uniform missing"))?,
+            antialias: gl
+                .get_uniform_location(&program, "u_antialias")
+                .ok_or_else(|| error("u_antialias uniform missing"))?,
+        };
+        let line_width_limits = query_size_range(&gl, Gl::ALIASED_LINE_WIDTH_RANGE);
 
         Ok(TimeSeriesRendererInner {
             context,
             gl,
             program,
-            position_location,
-            color_location,
+            locations,
             lines: Vec::new(),
+            line_pool: Vec::new(),
+            raw_samples: Vec::new(),
+            samples: Vec::new(),
+            sources: Vec::new(),
+            data_time_range: [0.0, 0.0],
+            data_value_range: [0.0, 0.0],
+            time_window: None,
+            value_window: None,
+            fixed_time_domain: None,
+            fixed_value_domain: None,
             time_range: [0.0, 0.0],
             value_range: [0.0, 0.0],
             sample_count: 0,
             line_width_limits,
+            time_scale: AxisScale::Linear,
+            plot_area: PlotArea::FULL,
+            line_join: LineJoin::Miter,
+            blend_mode: BlendMode::AlphaOver,
+            miter_limit: DEFAULT_MITER_LIMIT,
+            projected_size: (0, 0),
+            viewport: None,
+            antialias: Vec::new(),
+            ghost: None,
+            ghost_opacity: DEFAULT_GHOST_OPACITY,
+            ghost_own_range: false,
+            now: None,
+            gap_threshold: None,
+            max_samples: None,
+            dirty: true,
         })
     }
 
+    pub(crate) fn viewport(&self) -> Option<Viewport> {
+        self.viewport
+    }
+
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
     pub(crate) fn render_pass(&mut self) -> Result<(), JsValue> {
+        if !self.context.check_usable() {
+            return Ok(());
+        }
+        self.dirty = false;
+        // thick lines are expanded in pixel space, so a resize invalidates them
+        if self.target().size() != self.projected_size {
+            self.reproject()?;
+        }
+        if let Some(viewport) = self.viewport {
+            self.context.apply_viewport(viewport);
+        }
         self.gl.use_program(Some(&self.program));
         self.gl.disable(Gl::DEPTH_TEST);
         self.gl.disable(Gl::CULL_FACE);
-        self.gl.enable(Gl::BLEND);
-        self.gl
-            .blend_func(Gl::SRC_ALPHA, Gl::ONE_MINUS_SRC_ALPHA);
+        self.blend_mode.apply(&self.gl);
 
-        self.gl.enable_vertex_attrib_array(self.position_location);
-        for line in &self.lines {
-            line.draw(&self.gl, self.position_location, &self.color_location);
+        // Lines draw from the default vertex array, whose divisors another
+        // pass on this context may have set; none of these are instanced.
+        self.gl.bind_vertex_array(None);
+        for location in self.locations.attributes() {
+            self.gl.vertex_attrib_divisor(location, 0);
+        }
+        self.gl.enable_vertex_attrib_array(self.locations.position);
+        let ghost_lines = self.ghost.iter().flat_map(|ghost| &ghost.lines);
+        for line in ghost_lines.chain(&self.lines) {
+            line.draw(&self.gl, &self.locations);
+        }
+        for location in self.locations.attributes() {
+            self.gl.disable_vertex_attrib_array(location);
+        }
+        if self.viewport.is_some() {
+            self.context.reset_viewport();
         }
-        self.gl
-            .disable_vertex_attrib_array(self.position_location);
         Ok(())
     }
 
+    fn target(&self) -> Viewport {
+        self.viewport
+            .unwrap_or_else(|| Viewport::full(self.context.size()))
+    }
+
     fn set_series(&mut self, timestamps: &Float32Array, series: &Array) -> Result<(), JsValue> {
-        let samples = array_to_vec(timestamps);
+        let raw_samples = array_to_vec(timestamps);
+        let samples = apply_scale("timestamp", raw_samples.clone(), self.time_scale)?;
         let sample_count = samples.len();
         if sample_count == 0 {
             if series.length() != 0 {
                 return Err(error("series cannot be provided without timestamps"));
             }
-            self.lines.clear();
-            self.sample_count = 0;
-            self.time_range = [0.0, 0.0];
-            self.value_range = [0.0, 0.0];
+            self.raw_samples.clear();
+            self.samples.clear();
+            self.sources.clear();
+            self.data_time_range = [0.0, 0.0];
+            self.data_value_range = [0.0, 0.0];
+            return self.reproject();
+        }
+
+        let (time_min, time_max) = compute_range("timestamp", &samples)?;
+        let (staged_lines, value_min, value_max) = stage_series(series, sample_count)?;
+
+        self.raw_samples = raw_samples;
+        self.samples = samples;
+        self.sources = staged_lines;
+        self.data_time_range = [time_min, time_max];
+        self.data_value_range = [value_min, value_max];
+        self.trim_to_max_samples()?;
+        self.reproject()
+    }
+
+    fn append_samples(&mut self, timestamps: &Float32Array, values: &Array) -> Result<(), JsValue> {
+        let raw_samples = array_to_vec(timestamps);
+        let appended = values
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                entry
+                    .dyn_into::<Float32Array>()
+                    .map(|array| array_to_vec(&array))
+                    .map_err(|_| error(&format!("appended series[{index}] must be Float32Array")))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let counts: Vec<usize> = appended.iter().map(Vec::len).collect();
+        check_append(raw_samples.len(), &counts, &self.cleared_series())
+            .map_err(|message| error(&message))?;
+        check_append_order(self.raw_samples.last().copied(), &raw_samples).map_err(error)?;
+        self.extend_samples(raw_samples, appended)
+    }
+
+    // Series whose values were dropped by clear_series while the timestamps
+    // stayed.
+    fn cleared_series(&self) -> Vec<bool> {
+        self.sources
+            .iter()
+            .map(|staged| staged.values.len() != self.samples.len())
+            .collect()
+    }
+
+    fn append_sample(&mut self, timestamp: f32, values: &Float32Array) -> Result<(), JsValue> {
+        if values.length() as usize != self.sources.len() {
+            return Err(error(&format!(
+                "append_sample expects one value per series ({}), got {}",
+                self.sources.len(),
+                values.length()
+            )));
+        }
+        check_append_order(self.raw_samples.last().copied(), &[timestamp]).map_err(error)?;
+        // a cleared series takes a NaN placeholder so the columns still line up
+        let mut appended = Vec::with_capacity(self.sources.len());
+        for (index, (value, cleared)) in array_to_vec(values)
+            .into_iter()
+            .zip(self.cleared_series())
+            .enumerate()
+        {
+            match (cleared, value.is_nan()) {
+                (false, _) => appended.push(vec![value]),
+                (true, true) => appended.push(Vec::new()),
+                (true, false) => {
+                    return Err(error(&format!(
+                        "series[{index}] is cleared; append_sample expects NaN for it"
+                    )));
+                }
+            }
+        }
+        self.extend_samples(vec![timestamp], appended)
+    }
+
+    // Expects one value per timestamp for each existing series, and none for
+    // cleared ones.
+    fn extend_samples(
+        &mut self,
+        raw_samples: Vec<f32>,
+        appended: Vec<Vec<f32>>,
+    ) -> Result<(), JsValue> {
+        if raw_samples.is_empty() {
             return Ok(());
         }
 
+        let samples = apply_scale("timestamp", raw_samples.clone(), self.time_scale)?;
         let (time_min, time_max) = compute_range("timestamp", &samples)?;
-        let (staged_lines, value_min, value_max) =
-            stage_series(series, sample_count, self.line_width_limits)?;
+        let mut value_min = self.data_value_range[0];
+        let mut value_max = self.data_value_range[1];
+        for (index, (staged, values)) in self.sources.iter().zip(&appended).enumerate() {
+            for value in values {
+                if staged.break_on_nan && value.is_nan() {
+                    continue;
+                }
+                if !value.is_finite() {
+                    return Err(error(&format!(
+                        "appended series[{index}] values must be finite floats"
+                    )));
+                }
+                value_min = value_min.min(*value);
+                value_max = value_max.max(*value);
+            }
+        }
+
+        let had_samples = !self.samples.is_empty();
+        for (staged, values) in self.sources.iter_mut().zip(appended) {
+            staged.values.extend(values);
+        }
+        self.raw_samples.extend(raw_samples);
+        self.samples.extend(samples);
+        self.data_time_range = if had_samples {
+            [
+                self.data_time_range[0].min(time_min),
+                self.data_time_range[1].max(time_max),
+            ]
+        } else {
+            [time_min, time_max]
+        };
+        self.data_value_range = [value_min, value_max];
+        self.trim_to_max_samples()?;
+        self.reproject()
+    }
 
+    fn trim_to_max_samples(&mut self) -> Result<(), JsValue> {
+        let trimmed = trim_samples(
+            self.max_samples,
+            &mut self.raw_samples,
+            &mut self.samples,
+            &mut self.sources,
+        )?;
+        if let Some((time_range, value_range)) = trimmed {
+            self.data_time_range = time_range;
+            self.data_value_range = value_range;
+        }
+        Ok(())
+    }
+
+    fn set_ghost_series(
+        &mut self,
+        timestamps: &Float32Array,
+        series: &Array,
+    ) -> Result<(), JsValue> {
+        let raw_samples = array_to_vec(timestamps);
+        if raw_samples.is_empty() {
+            return Err(error("ghost series require timestamps"));
+        }
+        let samples = apply_scale("timestamp", raw_samples.clone(), self.time_scale)?;
+        let (time_min, time_max) = compute_range("timestamp", &samples)?;
+        let (sources, value_min, value_max) = stage_series(series, samples.len())?;
+        let lines = self
+            .ghost
+            .take()
+            .map(|ghost| ghost.lines)
+            .unwrap_or_default();
+        self.ghost = Some(GhostLayer {
+            raw_samples,
+            samples,
+            sources,
+            data_time_range: [time_min, time_max],
+            data_value_range: [value_min, value_max],
+            lines,
+        });
+        self.reproject()
+    }
+
+    // Re-derives the axis-space timestamps from the retained raw ones; the
+    // old scale stays in place if the data cannot be shown on the new one.
+    fn set_time_scale(&mut self, scale: AxisScale) -> Result<(), JsValue> {
+        if scale == self.time_scale {
+            return Ok(());
+        }
+        type Rescaled = Option<(Vec<f32>, [f32; 2])>;
+        let rescale = |raw: &[f32]| -> Result<Rescaled, JsValue> {
+            if raw.is_empty() {
+                return Ok(None);
+            }
+            let samples = apply_scale("timestamp", raw.to_vec(), scale)?;
+            let (time_min, time_max) = compute_range("timestamp", &samples)?;
+            Ok(Some((samples, [time_min, time_max])))
+        };
+        let primary = rescale(&self.raw_samples)?;
+        let ghost = match &self.ghost {
+            Some(ghost) => rescale(&ghost.raw_samples)?,
+            None => None,
+        };
+        if let Some((samples, range)) = primary {
+            self.samples = samples;
+            self.data_time_range = range;
+        }
+        if let (Some(layer), Some((samples, range))) = (self.ghost.as_mut(), ghost) {
+            layer.samples = samples;
+            layer.data_time_range = range;
+        }
+        self.time_scale = scale;
+        // a window or domain in the old axis units is meaningless
+        self.time_window = None;
+        self.fixed_time_domain = None;
+        self.reproject()
+    }
+
+    fn reproject(&mut self) -> Result<(), JsValue> {
+        let time_range = shown_range(
+            self.time_window,
+            self.fixed_time_domain,
+            self.data_time_range,
+        );
+        let value_range = shown_range(
+            self.value_window,
+            self.fixed_value_domain,
+            self.data_value_range,
+        );
+
+        let size = self.target().size();
+        let pixels_per_ndc = [size.0 as f32 * 0.5, size.1 as f32 * 0.5];
+
+        let projection = Projection {
+            samples: &self.samples,
+            raw_samples: &self.raw_samples,
+            time_range,
+            value_range,
+            pixels_per_ndc,
+            now: self.fade_now(self.data_time_range),
+        };
         let mut active = 0usize;
-        for staged in staged_lines {
-            let positions = build_positions(
-                &samples,
-                &staged.values,
-                time_min,
-                time_max,
-                value_min,
-                value_max,
-            );
+        for staged in &self.sources {
+            let antialias = self.antialias.get(active).copied().unwrap_or(true);
+            let (geometry, style) = self.series_geometry(staged, &projection, antialias);
             if let Some(existing) = self.lines.get_mut(active) {
-                existing.update(&self.gl, &positions, staged.color, staged.line_width)?;
+                existing.update(&self.gl, &geometry, style)?;
+            } else if let Some(mut pooled) = self.line_pool.pop() {
+                pooled.update(&self.gl, &geometry, style)?;
+                self.lines.push(pooled);
             } else {
-                self.lines.push(LineSeries::from_positions(
-                    &self.gl,
-                    &positions,
-                    staged.color,
-                    staged.line_width,
-                )?);
+                self.lines
+                    .push(LineSeries::from_geometry(&self.gl, &geometry, style)?);
             }
             active += 1;
         }
-        self.lines.truncate(active);
+        self.recycle_lines(active);
+        self.reproject_ghost(time_range, value_range, pixels_per_ndc)?;
+        self.projected_size = size;
+
+        self.sample_count = self.samples.len() as u32;
+        self.time_range = time_range;
+        self.value_range = value_range;
+        Ok(())
+    }
 
-        self.sample_count = sample_count as u32;
-        self.time_range = [time_min, time_max];
-        self.value_range = [value_min, value_max];
+    fn reproject_ghost(
+        &mut self,
+        time_range: [f32; 2],
+        value_range: [f32; 2],
+        pixels_per_ndc: [f32; 2],
+    ) -> Result<(), JsValue> {
+        let Some(ghost) = self.ghost.take() else {
+            return Ok(());
+        };
+        let (time_range, value_range) = if self.ghost_own_range {
+            (ghost.data_time_range, ghost.data_value_range)
+        } else {
+            (time_range, value_range)
+        };
+        let projection = Projection {
+            samples: &ghost.samples,
+            raw_samples: &ghost.raw_samples,
+            time_range,
+            value_range,
+            pixels_per_ndc,
+            now: self.fade_now(ghost.data_time_range),
+        };
+        let mut geometries = Vec::with_capacity(ghost.sources.len());
+        for (index, staged) in ghost.sources.iter().enumerate() {
+            let antialias = self.antialias.get(index).copied().unwrap_or(true);
+            let (geometry, mut style) = self.series_geometry(staged, &projection, antialias);
+            style.color[3] *= self.ghost_opacity;
+            geometries.push((geometry, style));
+        }
+        let mut ghost = ghost;
+        ghost.lines.truncate(geometries.len());
+        for (index, (geometry, style)) in geometries.iter().enumerate() {
+            match ghost.lines.get_mut(index) {
+                Some(existing) => existing.update(&self.gl, geometry, *style)?,
+                None => ghost
+                    .lines
+                    .push(LineSeries::from_geometry(&self.gl, geometry, *style)?),
+            }
+        }
+        self.ghost = Some(ghost);
         Ok(())
     }
 
+    // Fades are measured from `set_now`, or from the newest sample.
+    fn fade_now(&self, data_time_range: [f32; 2]) -> f32 {
+        self.now
+            .unwrap_or_else(|| self.axis_to_time(data_time_range[1]))
+    }
+
+    fn series_geometry(
+        &self,
+        staged: &SeriesStage,
+        projection: &Projection,
+        antialias: bool,
+    ) -> (LineGeometry, LineStyle) {
+        let (mut geometry, style) = self.stroke_geometry(staged, projection, antialias);
+        if let Some(directional) = &staged.directional {
+            // runs before the fade so the duplicated strip vertices fade too
+            let tints = directional_colors(&staged.values, directional);
+            if geometry.components == 2 {
+                let (positions, ranges, colors) =
+                    tint_strip(&geometry.positions, &geometry.ranges, &tints);
+                geometry.positions = positions;
+                geometry.ranges = ranges;
+                geometry.colors = colors;
+            } else {
+                // each expanded triangle belongs to one segment, found from
+                // its centroid's timestamp like the fade reads its vertices
+                let mut colors = Vec::with_capacity(geometry.positions.len() / 3 * 4);
+                for triangle in geometry.positions.chunks_exact(9) {
+                    let x = (triangle[0] + triangle[3] + triangle[6]) / 3.0;
+                    let axis = ndc_to_range(x, self.plot_area.x, projection.time_range);
+                    let end = segment_end(projection.samples, axis);
+                    for _ in 0..3 {
+                        colors.extend_from_slice(&tints[end * 4..end * 4 + 4]);
+                    }
+                }
+                geometry.colors = colors;
+            }
+        }
+        if let Some(tail) = staged.fade_tail {
+            // each vertex's timestamp is read back off its x so the expanded
+            // stroke fades exactly like the strip it came from
+            let alphas = geometry
+                .positions
+                .chunks_exact(geometry.components)
+                .map(|vertex| {
+                    let axis = ndc_to_range(vertex[0], self.plot_area.x, projection.time_range);
+                    fade_alpha(projection.now - self.axis_to_time(axis), tail)
+                })
+                .collect();
+            geometry.alphas = alphas;
+        }
+        (geometry, style)
+    }
+
+    fn axis_to_time(&self, axis: f32) -> f32 {
+        match self.time_scale {
+            AxisScale::Linear => axis,
+            AxisScale::Log10 => 10f32.powf(axis),
+        }
+    }
+
+    fn stroke_geometry(
+        &self,
+        staged: &SeriesStage,
+        projection: &Projection,
+        antialias: bool,
+    ) -> (LineGeometry, LineStyle) {
+        let positions = build_positions(
+            projection.samples,
+            &staged.values,
+            projection.time_range,
+            projection.value_range,
+            &self.plot_area,
+        );
+        let mut ranges = strip_ranges(&staged.values);
+        if let Some(threshold) = self.gap_threshold {
+            ranges = split_gaps(&ranges, projection.raw_samples, threshold);
+        }
+        if staged.line_width > 1.0 {
+            let feather = if antialias { AA_FEATHER } else { 0.0 };
+            let stroke = StrokeStyle {
+                width: staged.line_width,
+                join: self.line_join,
+                miter_limit: self.miter_limit,
+                feather,
+            };
+            let triangles =
+                expand_polyline(&positions, &ranges, projection.pixels_per_ndc, &stroke);
+            let vertex_count = (triangles.len() / 3) as i32;
+            let geometry = LineGeometry {
+                positions: triangles,
+                ranges: vec![(0, vertex_count)],
+                mode: Gl::TRIANGLES,
+                components: 3,
+                alphas: Vec::new(),
+                colors: Vec::new(),
+            };
+            let style = LineStyle {
+                color: staged.base_color(),
+                line_width: 1.0,
+                half_width: staged.line_width * 0.5,
+                antialias,
+            };
+            (geometry, style)
+        } else {
+            let geometry = LineGeometry {
+                positions,
+                ranges,
+                mode: Gl::LINE_STRIP,
+                components: 2,
+                alphas: Vec::new(),
+                colors: Vec::new(),
+            };
+            let [min, max] = self.line_width_limits;
+            let style = LineStyle {
+                color: staged.base_color(),
+                line_width: staged.line_width.clamp(min, max.max(min)),
+                half_width: 0.0,
+                antialias: false,
+            };
+            (geometry, style)
+        }
+    }
+
+    fn sample_at(&self, timestamp: f32) -> Vec<f32> {
+        // interpolate in axis space so the value matches the drawn line
+        let scaled = match self.time_scale {
+            AxisScale::Linear => Some(timestamp),
+            AxisScale::Log10 => (timestamp > 0.0).then(|| timestamp.log10()),
+        };
+        let bracket = scaled.and_then(|t| bracket_sample(&self.samples, t));
+        self.sources
+            .iter()
+            .map(|staged| match bracket {
+                Some((index, weight)) if staged.values.len() == self.samples.len() => {
+                    let a = staged.values[index];
+                    let b = staged.values.get(index + 1).copied().unwrap_or(a);
+                    a + (b - a) * weight
+                }
+                _ => f32::NAN,
+            })
+            .collect()
+    }
+
+    fn clear_series(&mut self, index: usize) -> Result<(), JsValue> {
+        // keeps the slot, color and width; only the samples go
+        let staged = self
+            .sources
+            .get_mut(index)
+            .ok_or_else(|| error("invalid series index"))?;
+        staged.values.clear();
+        self.reproject()
+    }
+
+    fn set_series_antialias(&mut self, index: usize, enabled: bool) -> Result<(), JsValue> {
+        if index >= self.sources.len() {
+            return Err(error("invalid series index"));
+        }
+        if self.antialias.len() <= index {
+            self.antialias.resize(index + 1, true);
+        }
+        self.antialias[index] = enabled;
+        self.reproject()
+    }
+
+    fn pan_by_pixels(&mut self, dx: f32, dy: f32) -> Result<(), JsValue> {
+        if !dx.is_finite() || !dy.is_finite() {
+            return Err(error("pan deltas must be finite"));
+        }
+        if self.samples.is_empty() {
+            return Ok(());
+        }
+        let (width, height) = self.target().size();
+        let plot_width = (self.plot_area.x[1] - self.plot_area.x[0]) * 0.5 * width as f32;
+        let plot_height = (self.plot_area.y[1] - self.plot_area.y[0]) * 0.5 * height as f32;
+        // dragging right reveals earlier samples; DOM y grows downwards
+        let time_shift = -dx * span(self.time_range) / plot_width;
+        let value_shift = dy * span(self.value_range) / plot_height;
+        // only a moved axis is pinned; the other keeps autoscaling to new data
+        if dx != 0.0 {
+            self.time_window = Some(shift_range(self.time_range, time_shift));
+        }
+        if dy != 0.0 {
+            self.value_window = Some(shift_range(self.value_range, value_shift));
+        }
+        self.reproject()
+    }
+
+    fn zoom_at_pixel(&mut self, x: f32, y: f32, factor: f32) -> Result<(), JsValue> {
+        if !x.is_finite() || !y.is_finite() {
+            return Err(error("zoom anchor must be finite"));
+        }
+        if !factor.is_finite() || factor <= 0.0 {
+            return Err(error("zoom factor must be positive"));
+        }
+        // a unit factor changes neither axis, so it must not pin them either
+        if self.samples.is_empty() || factor == 1.0 {
+            return Ok(());
+        }
+        let (ndc_x, ndc_y) = self.target().pixel_to_ndc(x, y, self.context.size().1);
+        let anchor_time = ndc_to_range(ndc_x, self.plot_area.x, self.time_range);
+        let anchor_value = ndc_to_range(ndc_y, self.plot_area.y, self.value_range);
+        self.time_window = Some(zoom_range(self.time_range, anchor_time, factor));
+        self.value_window = Some(zoom_range(self.value_range, anchor_value, factor));
+        self.reproject()
+    }
+
+    fn reset_view(&mut self) -> Result<(), JsValue> {
+        self.time_window = None;
+        self.value_window = None;
+        self.reproject()
+    }
+
+    // A new domain replaces that axis's pan/zoom window.
+    fn set_time_domain(&mut self, min: f32, max: f32) -> Result<(), JsValue> {
+        self.fixed_time_domain = Some(fixed_domain(min, max).map_err(error)?);
+        self.time_window = None;
+        self.reproject()
+    }
+
+    fn set_value_domain(&mut self, min: f32, max: f32) -> Result<(), JsValue> {
+        self.fixed_value_domain = Some(fixed_domain(min, max).map_err(error)?);
+        self.value_window = None;
+        self.reproject()
+    }
+
+    fn clear_fixed_domains(&mut self) -> Result<(), JsValue> {
+        self.fixed_time_domain = None;
+        self.fixed_value_domain = None;
+        self.reproject()
+    }
+
     fn series_count(&self) -> u32 {
         self.lines.len() as u32
     }
 
+    fn recycle_lines(&mut self, keep: usize) {
+        if keep >= self.lines.len() {
+            return;
+        }
+        self.line_pool.extend(self.lines.drain(keep..));
+    }
+
+    fn clear_series_pool(&mut self) {
+        self.line_pool.clear();
+    }
+
     fn sample_count(&self) -> u32 {
         self.sample_count
     }
+
+    fn series_stats(&self, index: usize) -> Result<SeriesStats, JsValue> {
+        let line = self
+            .lines
+            .get(index)
+            .ok_or_else(|| error("invalid series index"))?;
+        Ok(SeriesStats {
+            points: line.point_count.max(0) as u32,
+            capacity: (line.capacity / line.components.max(1)) as u32,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct SeriesStats {
+    points: u32,
+    capacity: u32,
+}
+
+struct LineGeometry {
+    positions: Vec<f32>,
+    ranges: Vec<(i32, i32)>, // (first, count) per contiguous strip
+    mode: u32,
+    components: usize, // 2 for (x, y) strips, 3 for expanded (x, y, edge)
+    alphas: Vec<f32>,  // one fade alpha per vertex; empty when the series has no fade
+    colors: Vec<f32>,  // rgba per vertex; empty unless the series colors by direction
+}
+
+#[derive(Clone, Copy)]
+struct LineStyle {
+    color: [f32; 4],
+    line_width: f32, // GL line width for the strip path
+    half_width: f32, // pixels, for the expanded path's falloff
+    antialias: bool,
+}
+
+struct LineLocations {
+    position: u32,
+    edge: u32,
+    alpha: u32,
+    tint: u32,
+    color: WebGlUniformLocation,
+    half_width: WebGlUniformLocation,
+    antialias: WebGlUniformLocation,
+}
+
+impl LineLocations {
+    fn attributes(&self) -> [u32; 4] {
+        [self.position, self.edge, self.alpha, self.tint]
+    }
 }
 
 struct LineSeries {
     buffer: GlBuffer,
     point_count: i32,
+    ranges: Vec<(i32, i32)>,
+    mode: u32,
+    components: usize,
     capacity: usize,
-    color: [f32; 4],
-    line_width: f32,
+    style: LineStyle,
+    fade_buffer: Option<GlBuffer>, // created the first time the series fades
+    fade_capacity: usize,
+    faded: bool,
+    color_buffer: Option<GlBuffer>, // created the first time the series colors by direction
+    color_capacity: usize,
+    tinted: bool,
 }
 
 impl LineSeries {
-    fn from_positions(
-        gl: &Gl,
-        positions: &[f32],
-        color: [f32; 4],
-        line_width: f32,
-    ) -> Result<Self, JsValue> {
+    fn from_geometry(gl: &Gl, geometry: &LineGeometry, style: LineStyle) -> Result<Self, JsValue> {
+        let positions = &geometry.positions;
         let buffer = GlBuffer::new(gl)?;
         buffer.bind_array_buffer();
         let view = unsafe { Float32Array::view(positions) };
         gl.buffer_data_with_array_buffer_view(Gl::ARRAY_BUFFER, &view, Gl::STATIC_DRAW);
-        Ok(Self {
+        let mut series = Self {
             buffer,
-            point_count: (positions.len() / 2) as i32,
+            point_count: (positions.len() / geometry.components) as i32,
+            ranges: geometry.ranges.clone(),
+            mode: geometry.mode,
+            components: geometry.components,
             capacity: positions.len(),
-            color,
-            line_width,
-        })
+            style,
+            fade_buffer: None,
+            fade_capacity: 0,
+            faded: false,
+            color_buffer: None,
+            color_capacity: 0,
+            tinted: false,
+        };
+        series.upload_alphas(gl, &geometry.alphas)?;
+        series.upload_colors(gl, &geometry.colors)?;
+        Ok(series)
     }
 
     fn update(
         &mut self,
         gl: &Gl,
-        positions: &[f32],
-        color: [f32; 4],
-        line_width: f32,
+        geometry: &LineGeometry,
+        style: LineStyle,
     ) -> Result<(), JsValue> {
-        self.point_count = (positions.len() / 2) as i32;
+        let positions = &geometry.positions;
+        self.point_count = (positions.len() / geometry.components) as i32;
+        self.ranges.clone_from(&geometry.ranges);
+        self.mode = geometry.mode;
+        self.components = geometry.components;
         self.buffer.bind_array_buffer();
-        let view = unsafe { Float32Array::view(positions) };
-        if positions.len() > self.capacity {
-            gl.buffer_data_with_array_buffer_view(Gl::ARRAY_BUFFER, &view, Gl::STATIC_DRAW);
-            self.capacity = positions.len();
-        } else {
-            gl.buffer_sub_data_with_f64_and_array_buffer_view(Gl::ARRAY_BUFFER, 0.0, &view);
+        write_floats(gl, positions, &mut self.capacity);
+        self.style = style;
+        self.upload_alphas(gl, &geometry.alphas)?;
+        self.upload_colors(gl, &geometry.colors)
+    }
+
+    fn upload_alphas(&mut self, gl: &Gl, alphas: &[f32]) -> Result<(), JsValue> {
+        self.faded = !alphas.is_empty();
+        if !self.faded {
+            return Ok(());
+        }
+        if self.fade_buffer.is_none() {
+            self.fade_buffer = Some(GlBuffer::new(gl)?);
+        }
+        if let Some(buffer) = &self.fade_buffer {
+            buffer.bind_array_buffer();
+            write_floats(gl, alphas, &mut self.fade_capacity);
         }
-        self.color = color;
-        self.line_width = line_width;
         Ok(())
     }
 
-    fn draw(&self, gl: &Gl, position_location: u32, color_location: &WebGlUniformLocation) {
+    fn upload_colors(&mut self, gl: &Gl, colors: &[f32]) -> Result<(), JsValue> {
+        self.tinted = !colors.is_empty();
+        if !self.tinted {
+            return Ok(());
+        }
+        if self.color_buffer.is_none() {
+            self.color_buffer = Some(GlBuffer::new(gl)?);
+        }
+        if let Some(buffer) = &self.color_buffer {
+            buffer.bind_array_buffer();
+            write_floats(gl, colors, &mut self.color_capacity);
+        }
+        Ok(())
+    }
+
+    fn draw(&self, gl: &Gl, locations: &LineLocations) {
         if self.point_count <= 0 {
             return;
         }
         gl.bind_buffer(Gl::ARRAY_BUFFER, Some(self.buffer.handle()));
-        gl.vertex_attrib_pointer_with_i32(position_location, 2, Gl::FLOAT, false, 0, 0);
-        gl.uniform4fv_with_f32_array(Some(color_location), &self.color);
-        gl.line_width(self.line_width);
-        gl.draw_arrays(Gl::LINE_STRIP, 0, self.point_count);
+        let stride = (self.components * std::mem::size_of::<f32>()) as i32;
+        gl.vertex_attrib_pointer_with_i32(locations.position, 2, Gl::FLOAT, false, stride, 0);
+        if self.components == 3 {
+            gl.enable_vertex_attrib_array(locations.edge);
+            let offset = (2 * std::mem::size_of::<f32>()) as i32;
+            gl.vertex_attrib_pointer_with_i32(locations.edge, 1, Gl::FLOAT, false, stride, offset);
+        } else {
+            gl.disable_vertex_attrib_array(locations.edge);
+            gl.vertex_attrib1f(locations.edge, 0.0);
+        }
+        match (&self.fade_buffer, self.faded) {
+            (Some(buffer), true) => {
+                gl.bind_buffer(Gl::ARRAY_BUFFER, Some(buffer.handle()));
+                gl.enable_vertex_attrib_array(locations.alpha);
+                gl.vertex_attrib_pointer_with_i32(locations.alpha, 1, Gl::FLOAT, false, 0, 0);
+            }
+            _ => {
+                gl.disable_vertex_attrib_array(locations.alpha);
+                gl.vertex_attrib1f(locations.alpha, 1.0);
+            }
+        }
+        match (&self.color_buffer, self.tinted) {
+            (Some(buffer), true) => {
+                gl.bind_buffer(Gl::ARRAY_BUFFER, Some(buffer.handle()));
+                gl.enable_vertex_attrib_array(locations.tint);
+                gl.vertex_attrib_pointer_with_i32(locations.tint, 4, Gl::FLOAT, false, 0, 0);
+            }
+            _ => {
+                gl.disable_vertex_attrib_array(locations.tint);
+                gl.vertex_attrib4f(locations.tint, 1.0, 1.0, 1.0, 1.0);
+            }
+        }
+        gl.uniform4fv_with_f32_array(Some(&locations.color), &self.style.color);
+        gl.uniform1f(Some(&locations.half_width), self.style.half_width);
+        gl.uniform1i(Some(&locations.antialias), self.style.antialias as i32);
+        gl.line_width(self.style.line_width);
+        for &(first, count) in &self.ranges {
+            gl.draw_arrays(self.mode, first, count);
+        }
+    }
+}
+
+// Grows the bound ARRAY_BUFFER only when `data` no longer fits.
+fn write_floats(gl: &Gl, data: &[f32], capacity: &mut usize) {
+    let view = unsafe { Float32Array::view(data) };
+    if data.len() > *capacity {
+        *capacity = grown_capacity(*capacity, data.len());
+        let bytes = (*capacity * std::mem::size_of::<f32>()) as i32;
+        gl.buffer_data_with_i32(Gl::ARRAY_BUFFER, bytes, Gl::STATIC_DRAW);
+    }
+    gl.buffer_sub_data_with_f64_and_array_buffer_view(Gl::ARRAY_BUFFER, 0.0, &view);
+}
+
+// At least doubles, so streamed appends rarely reallocate.
+fn grown_capacity(capacity: usize, needed: usize) -> usize {
+    if needed <= capacity {
+        capacity
+    } else {
+        needed.max(capacity * 2)
     }
 }
 
+// Where a set of staged series lands on screen.
+struct Projection<'a> {
+    samples: &'a [f32],
+    raw_samples: &'a [f32], // as given, for gap detection
+    time_range: [f32; 2],
+    value_range: [f32; 2],
+    pixels_per_ndc: [f32; 2],
+    now: f32, // raw timestamp that fading series measure age against
+}
+
+// A faded comparison copy of the chart, drawn underneath the primary series.
+struct GhostLayer {
+    raw_samples: Vec<f32>,
+    samples: Vec<f32>,
+    sources: Vec<SeriesStage>,
+    data_time_range: [f32; 2],
+    data_value_range: [f32; 2],
+    lines: Vec<LineSeries>,
+}
+
 struct SeriesStage {
     values: Vec<f32>,
     color: [f32; 4],
     line_width: f32,
+    break_on_nan: bool,
+    fade_tail: Option<f32>, // timestamp units after which a sample is invisible
+    directional: Option<DirectionalColor>,
+}
+
+impl SeriesStage {
+    // Directional series are tinted per vertex, so the uniform color only
+    // carries the alpha the ghost layer scales.
+    fn base_color(&self) -> [f32; 4] {
+        if self.directional.is_some() {
+            [1.0, 1.0, 1.0, 1.0]
+        } else {
+            self.color
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct DirectionalColor {
+    up: [f32; 4],
+    down: [f32; 4],
 }
 
 fn stage_series(
     series: &Array,
     sample_count: usize,
-    width_limits: [f32; 2],
 ) -> Result<(Vec<SeriesStage>, f32, f32), JsValue> {
     if series.length() == 0 {
         return Ok((Vec::new(), 0.0, 0.0));
@@ -302,7 +1312,14 @@ fn stage_series(
         }
         let mut values = vec![0.0; sample_count];
         values_array.copy_to(&mut values);
+        let break_on_nan = Reflect::get(&object, &JsValue::from_str("breakOnNaN"))
+            .ok()
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
         for value in &values {
+            if break_on_nan && value.is_nan() {
+                continue;
+            }
             if !value.is_finite() {
                 return Err(error("series values must be finite floats"));
             }
@@ -311,12 +1328,17 @@ fn stage_series(
         }
 
         let color = extract_color(&object, index)?;
-        let line_width = extract_line_width(&object, width_limits);
+        let line_width = extract_line_width(&object);
+        let fade_tail = extract_fade(&object, index)?;
+        let directional = extract_directional(&object, index)?;
 
         staged.push(SeriesStage {
             values,
             color,
             line_width,
+            break_on_nan,
+            fade_tail,
+            directional,
         });
     }
 
@@ -335,15 +1357,137 @@ fn stage_series(
     Ok((staged, value_min, value_max))
 }
 
+// Extent of every finite staged value, widened like `stage_series` when flat;
+// [0, 0] when nothing is left, as for an empty `set_series`.
+fn value_extent(sources: &[SeriesStage]) -> [f32; 2] {
+    let (min, max) = sources
+        .iter()
+        .flat_map(|staged| &staged.values)
+        .filter(|value| value.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &value| {
+            (min.min(value), max.max(value))
+        });
+    if !min.is_finite() {
+        [0.0, 0.0]
+    } else if max - min <= f32::EPSILON {
+        [min - 0.5, min + 0.5]
+    } else {
+        [min, max]
+    }
+}
+
+// The strictness of `stage_series`, applied to a streamed append: every
+// existing series gets exactly one value per appended timestamp, except
+// cleared ones, which stay blank and must get an empty array.
+fn check_append(
+    timestamp_count: usize,
+    value_counts: &[usize],
+    cleared: &[bool],
+) -> Result<(), String> {
+    let series_count = cleared.len();
+    if value_counts.len() != series_count {
+        return Err(format!(
+            "append_samples expects values for {series_count} series, got {}",
+            value_counts.len()
+        ));
+    }
+    for (index, (count, cleared)) in value_counts.iter().zip(cleared).enumerate() {
+        if *cleared {
+            if *count != 0 {
+                return Err(format!(
+                    "appended series[{index}] is cleared and takes no values until set_series"
+                ));
+            }
+        } else if *count != timestamp_count {
+            return Err(format!(
+                "appended series[{index}] has {count} values for {timestamp_count} timestamps"
+            ));
+        }
+    }
+    Ok(())
+}
+
+type DataExtents = ([f32; 2], [f32; 2]); // (time, value)
+
+// A pan/zoom window wins over a fixed domain, which wins over the data.
+fn shown_range(window: Option<[f32; 2]>, fixed: Option<[f32; 2]>, data: [f32; 2]) -> [f32; 2] {
+    window.or(fixed).unwrap_or(data)
+}
+
+// Drops the oldest samples past `max`, in order, from the timestamps and
+// every series still lined up with them, and returns the data extents of
+// what is left; None when nothing was dropped. Fixed domains and windows sit
+// on top of these extents, so they are not touched here.
+fn trim_samples(
+    max: Option<usize>,
+    raw_samples: &mut Vec<f32>,
+    samples: &mut Vec<f32>,
+    sources: &mut [SeriesStage],
+) -> Result<Option<DataExtents>, JsValue> {
+    let count = samples.len();
+    let excess = max.map_or(0, |max| count.saturating_sub(max));
+    if excess == 0 {
+        return Ok(None);
+    }
+    for staged in sources.iter_mut() {
+        // cleared series have no samples to drop
+        if staged.values.len() == count {
+            staged.values.drain(..excess);
+        }
+    }
+    raw_samples.drain(..excess);
+    samples.drain(..excess);
+    let (time_min, time_max) = compute_range("timestamp", samples)?;
+    Ok(Some(([time_min, time_max], value_extent(sources))))
+}
+
+// Appends only ever extend the end of the data, so the new timestamps must
+// increase strictly, both among themselves and from the last stored one.
+fn check_append_order(last: Option<f32>, timestamps: &[f32]) -> Result<(), &'static str> {
+    let mut previous = last;
+    for &timestamp in timestamps {
+        if previous.is_some_and(|previous| timestamp <= previous) {
+            return Err("appended timestamps must be later than the samples before them");
+        }
+        previous = Some(timestamp);
+    }
+    Ok(())
+}
+
 fn extract_color(object: &Object, index: usize) -> Result<[f32; 4], JsValue> {
     let color_value = Reflect::get(object, &JsValue::from_str("color"))
         .map_err(|_| error(&format!("series[{index}] missing color property")))?;
-    let color_array = color_value
+    if color_value.is_undefined() {
+        return Ok(color::palette_color(index));
+    }
+    parse_color(color_value, &format!("series[{index}].color"))
+}
+
+// `directionalColor: { up, down }`, each a Float32Array like `color`.
+fn extract_directional(object: &Object, index: usize) -> Result<Option<DirectionalColor>, JsValue> {
+    let directional =
+        Reflect::get(object, &JsValue::from_str("directionalColor")).unwrap_or(JsValue::UNDEFINED);
+    if directional.is_undefined() || directional.is_null() {
+        return Ok(None);
+    }
+    let side = |name: &str| {
+        let value =
+            Reflect::get(&directional, &JsValue::from_str(name)).unwrap_or(JsValue::UNDEFINED);
+        parse_color(value, &format!("series[{index}].directionalColor.{name}"))
+    };
+    Ok(Some(DirectionalColor {
+        up: side("up")?,
+        down: side("down")?,
+    }))
+}
+
+fn parse_color(value: JsValue, label: &str) -> Result<[f32; 4], JsValue> {
+    let color_array = value
         .dyn_into::<Float32Array>()
-        .map_err(|_| error(&format!("series[{index}].color must be Float32Array")))?;
+        .map_err(|_| error(&format!("{label} must be Float32Array")))?;
     if color_array.length() < 3 {
         return Err(error(&format!(
-            "series[{index}].color requires at least three components"
+            "{label} requires at least three components"
         )));
     }
     let mut color = [0.0; 4];
@@ -358,40 +1502,434 @@ fn extract_color(object: &Object, index: usize) -> Result<[f32; 4], JsValue> {
     Ok(color)
 }
 
-fn extract_line_width(object: &Object, limits: [f32; 2]) -> f32 {
+// `fade: { tailSeconds }`, where the tail is in the same units as the timestamps.
+fn extract_fade(object: &Object, index: usize) -> Result<Option<f32>, JsValue> {
+    let fade = Reflect::get(object, &JsValue::from_str("fade")).unwrap_or(JsValue::UNDEFINED);
+    if fade.is_undefined() || fade.is_null() {
+        return Ok(None);
+    }
+    Reflect::get(&fade, &JsValue::from_str("tailSeconds"))
+        .ok()
+        .and_then(|tail| tail.as_f64())
+        .map(|tail| tail as f32)
+        .filter(|tail| tail.is_finite() && *tail > 0.0)
+        .map(Some)
+        .ok_or_else(|| {
+            error(&format!(
+                "series[{index}].fade.tailSeconds must be a positive number"
+            ))
+        })
+}
+
+// One rgba per sample: `up` where the value rose from (or held at) the
+// previous sample, `down` where it fell. The first sample of a strip takes
+// the direction of the segment leaving it.
+fn directional_colors(values: &[f32], colors: &DirectionalColor) -> Vec<f32> {
+    let mut out = Vec::with_capacity(values.len() * 4);
+    for (index, value) in values.iter().enumerate() {
+        let previous = index
+            .checked_sub(1)
+            .map(|i| values[i])
+            .filter(|v| !v.is_nan());
+        let next = values.get(index + 1).copied().filter(|v| !v.is_nan());
+        let rising = match (previous, next) {
+            (Some(previous), _) => *value >= previous,
+            (None, Some(next)) => next >= *value,
+            (None, None) => true,
+        };
+        out.extend_from_slice(if rising { &colors.up } else { &colors.down });
+    }
+    out
+}
+
+// A segment takes the color of the sample it ends on, so each one is a
+// single color. Where consecutive segments differ, the shared strip vertex
+// is emitted twice, once per color, so the switch is sharp instead of a
+// gradient across the segment. Returns (positions, ranges, colors).
+fn tint_strip(
+    positions: &[f32],
+    ranges: &[(i32, i32)],
+    tints: &[f32],
+) -> (Vec<f32>, Vec<(i32, i32)>, Vec<f32>) {
+    let tint = |sample: usize| &tints[sample * 4..sample * 4 + 4];
+    let mut out_positions = Vec::with_capacity(positions.len());
+    let mut out_ranges = Vec::with_capacity(ranges.len());
+    let mut colors = Vec::with_capacity(positions.len() * 2);
+    for &(first, count) in ranges {
+        let (first, end) = (first as usize, (first + count) as usize);
+        let start = (out_positions.len() / 2) as i32;
+        for sample in first..end {
+            let incoming = (sample > first).then(|| tint(sample));
+            let outgoing = (sample + 1 < end).then(|| tint(sample + 1));
+            let vertex = &positions[sample * 2..sample * 2 + 2];
+            let colors_here: &[Option<&[f32]>] = match (incoming, outgoing) {
+                (Some(a), Some(b)) if a != b => &[incoming, outgoing],
+                (Some(_), _) => &[incoming],
+                (None, _) => &[outgoing],
+            };
+            for color in colors_here {
+                out_positions.extend_from_slice(vertex);
+                // a lone sample has no segment; its own tint is as good as any
+                colors.extend_from_slice(color.unwrap_or_else(|| tint(sample)));
+            }
+        }
+        let count = (out_positions.len() / 2) as i32 - start;
+        out_ranges.push((start, count));
+    }
+    (out_positions, out_ranges, colors)
+}
+
+// The sample that ends the segment containing `t`; outside the data, the
+// nearest segment's end.
+fn segment_end(samples: &[f32], t: f32) -> usize {
+    let (index, _) = bracket_sample(samples, t).unwrap_or_else(|| nearest_end(samples, t));
+    (index + 1).min(samples.len().saturating_sub(1))
+}
+
+// Fallback for a timestamp outside every sample pair: the closest end.
+fn nearest_end(samples: &[f32], t: f32) -> (usize, f32) {
+    let last = samples.len().saturating_sub(1);
+    if samples.len() > 1 && (t - samples[last]).abs() < (t - samples[0]).abs() {
+        (last, 0.0)
+    } else {
+        (0, 0.0)
+    }
+}
+
+// Linear decay from fully opaque at age 0 to invisible at `tail`; samples
+// from the future stay opaque.
+fn fade_alpha(age: f32, tail: f32) -> f32 {
+    (1.0 - age / tail).clamp(0.0, 1.0)
+}
+
+fn extract_line_width(object: &Object) -> f32 {
     let width_value =
         Reflect::get(object, &JsValue::from_str("lineWidth")).unwrap_or(JsValue::UNDEFINED);
-    let requested = width_value
+    width_value
         .as_f64()
         .map(|v| v as f32)
         .filter(|v| v.is_finite() && *v > 0.0)
-        .unwrap_or(1.0);
-    let min = limits[0];
-    let max = limits[1].max(min);
-    requested.clamp(min, max)
+        .unwrap_or(1.0)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PlotArea {
+    x: [f32; 2],
+    y: [f32; 2],
+}
+
+impl PlotArea {
+    const FULL: PlotArea = PlotArea {
+        x: [-1.0, 1.0],
+        y: [-1.0, 1.0],
+    };
+
+    fn from_margins(left: f32, right: f32, top: f32, bottom: f32) -> Result<Self, &'static str> {
+        let margins = [left, right, top, bottom];
+        if margins.iter().any(|m| !m.is_finite() || *m < 0.0) {
+            return Err("plot margins must be finite and non-negative");
+        }
+        if left + right >= 1.0 || top + bottom >= 1.0 {
+            return Err("plot margins must leave a non-empty plot area");
+        }
+        Ok(PlotArea {
+            x: [-1.0 + 2.0 * left, 1.0 - 2.0 * right],
+            y: [-1.0 + 2.0 * bottom, 1.0 - 2.0 * top],
+        })
+    }
 }
 
 fn build_positions(
     timestamps: &[f32],
     values: &[f32],
-    time_min: f32,
-    time_max: f32,
-    value_min: f32,
-    value_max: f32,
+    time_range: [f32; 2],
+    value_range: [f32; 2],
+    plot: &PlotArea,
 ) -> Vec<f32> {
     let mut out = Vec::with_capacity(values.len() * 2);
-    let time_span = (time_max - time_min).abs().max(f32::EPSILON);
-    let value_span = (value_max - value_min).abs().max(f32::EPSILON);
+    let time_span = (time_range[1] - time_range[0]).abs().max(f32::EPSILON);
+    let value_span = (value_range[1] - value_range[0]).abs().max(f32::EPSILON);
+    let plot_width = plot.x[1] - plot.x[0];
+    let plot_height = plot.y[1] - plot.y[0];
     for (index, value) in values.iter().enumerate() {
         let t = timestamps[index];
-        let x = ((t - time_min) / time_span) * 2.0 - 1.0;
-        let y = ((value - value_min) / value_span) * 2.0 - 1.0;
+        let x = plot.x[0] + ((t - time_range[0]) / time_span) * plot_width;
+        let y = plot.y[0] + ((value - value_range[0]) / value_span) * plot_height;
         out.push(x);
         out.push(y);
     }
     out
 }
 
+struct StrokeStyle {
+    width: f32, // pixels
+    join: LineJoin,
+    miter_limit: f32,
+    feather: f32, // extra pixels per side for the antialiasing ramp
+}
+
+type Point = [f32; 2];
+
+fn expand_polyline(
+    positions: &[f32],
+    ranges: &[(i32, i32)],
+    pixels_per_ndc: [f32; 2],
+    stroke: &StrokeStyle,
+) -> Vec<f32> {
+    let half = stroke.width * 0.5 + stroke.feather;
+    let mut out = Vec::new();
+    for &(first, count) in ranges {
+        let mut points: Vec<Point> = (first as usize..(first + count) as usize)
+            .map(|i| {
+                [
+                    positions[i * 2] * pixels_per_ndc[0],
+                    positions[i * 2 + 1] * pixels_per_ndc[1],
+                ]
+            })
+            .collect();
+        points.dedup_by(|a, b| (a[0] - b[0]).abs() < 1e-4 && (a[1] - b[1]).abs() < 1e-4);
+
+        let mut sink = TriangleSink {
+            out: &mut out,
+            pixels_per_ndc,
+        };
+        for pair in points.windows(2) {
+            let n = scaled(normal(pair[0], pair[1]), half);
+            let (a, b) = (pair[0], pair[1]);
+            sink.push([add(a, n), sub(a, n), add(b, n)], [half, -half, half]);
+            sink.push([add(b, n), sub(a, n), sub(b, n)], [half, -half, -half]);
+        }
+        for triple in points.windows(3) {
+            push_join(&mut sink, triple[0], triple[1], triple[2], half, stroke);
+        }
+    }
+    out
+}
+
+fn push_join(
+    sink: &mut TriangleSink,
+    p0: Point,
+    p: Point,
+    p2: Point,
+    half: f32,
+    stroke: &StrokeStyle,
+) {
+    let d0 = sub(p, p0);
+    let d1 = sub(p2, p);
+    let turn = cross(d0, d1);
+    if turn.abs() <= 1e-6 * length(d0) * length(d1) && dot(d0, d1) > 0.0 {
+        return; // collinear, the segment quads already meet
+    }
+    // the gap opens on the outside of the turn
+    let side = if turn > 0.0 { -1.0 } else { 1.0 };
+    let u0 = scaled(normal(p0, p), side);
+    let u1 = scaled(normal(p, p2), side);
+    let outer0 = add(p, scaled(u0, half));
+    let outer1 = add(p, scaled(u1, half));
+    let fan = [0.0, half, half]; // edge distance from the joint outwards
+
+    match stroke.join {
+        LineJoin::Bevel => sink.push([p, outer0, outer1], fan),
+        LineJoin::Miter => {
+            let bisector = add(u0, u1);
+            let bisector_length = length(bisector);
+            let ratio = if bisector_length > 1e-6 {
+                bisector_length / dot(bisector, u0).max(1e-6)
+            } else {
+                f32::INFINITY
+            };
+            if ratio > stroke.miter_limit {
+                sink.push([p, outer0, outer1], fan);
+                return;
+            }
+            // ratio = 1 / cos(half the angle between normals)
+            let tip = add(p, scaled(bisector, half * ratio / bisector_length));
+            sink.push([p, outer0, tip], fan);
+            sink.push([p, tip, outer1], fan);
+        }
+        LineJoin::Round => {
+            let angle = dot(u0, u1).clamp(-1.0, 1.0).acos();
+            let direction = if cross(u0, u1) < 0.0 { -1.0 } else { 1.0 };
+            let steps = (angle / ROUND_JOIN_STEP).ceil().max(1.0) as usize;
+            let mut previous = outer0;
+            for step in 1..=steps {
+                let theta = direction * angle * step as f32 / steps as f32;
+                let next = add(p, scaled(rotate(u0, theta), half));
+                sink.push([p, previous, next], fan);
+                previous = next;
+            }
+        }
+    }
+}
+
+// Emits (x, y, edge) per vertex: NDC position plus the signed pixel
+// distance from the centre line, used for the antialiasing falloff.
+struct TriangleSink<'a> {
+    out: &'a mut Vec<f32>,
+    pixels_per_ndc: [f32; 2],
+}
+
+impl TriangleSink<'_> {
+    fn push(&mut self, points: [Point; 3], edges: [f32; 3]) {
+        for (point, edge) in points.into_iter().zip(edges) {
+            self.out.push(point[0] / self.pixels_per_ndc[0]);
+            self.out.push(point[1] / self.pixels_per_ndc[1]);
+            self.out.push(edge);
+        }
+    }
+}
+
+fn add(a: Point, b: Point) -> Point {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn scaled(point: Point, factor: f32) -> Point {
+    [point[0] * factor, point[1] * factor]
+}
+
+fn dot(a: Point, b: Point) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn cross(a: Point, b: Point) -> f32 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+fn length(point: Point) -> f32 {
+    dot(point, point).sqrt()
+}
+
+fn normal(from: Point, to: Point) -> Point {
+    let d = sub(to, from);
+    let len = length(d).max(f32::EPSILON);
+    [-d[1] / len, d[0] / len]
+}
+
+fn rotate(point: Point, theta: f32) -> Point {
+    let (sin, cos) = theta.sin_cos();
+    [
+        cos * point[0] - sin * point[1],
+        sin * point[0] + cos * point[1],
+    ]
+}
+
+fn fixed_domain(min: f32, max: f32) -> Result<[f32; 2], &'static str> {
+    if !min.is_finite() || !max.is_finite() {
+        return Err("domain bounds must be finite");
+    }
+    if min >= max {
+        return Err("domain min must be below max");
+    }
+    Ok([min, max])
+}
+
+fn span(range: [f32; 2]) -> f32 {
+    range[1] - range[0]
+}
+
+fn shift_range(range: [f32; 2], delta: f32) -> [f32; 2] {
+    [range[0] + delta, range[1] + delta]
+}
+
+fn ndc_to_range(ndc: f32, plot: [f32; 2], range: [f32; 2]) -> f32 {
+    range[0] + (ndc - plot[0]) / (plot[1] - plot[0]) * span(range)
+}
+
+fn zoom_range(range: [f32; 2], anchor: f32, factor: f32) -> [f32; 2] {
+    [
+        anchor - (anchor - range[0]) / factor,
+        anchor + (range[1] - anchor) / factor,
+    ]
+}
+
+fn strip_ranges(values: &[f32]) -> Vec<(i32, i32)> {
+    let mut ranges = Vec::new();
+    let mut start: Option<usize> = None;
+    for (index, value) in values.iter().enumerate() {
+        match (value.is_nan(), start) {
+            (false, None) => start = Some(index),
+            (true, Some(first)) => {
+                push_strip(&mut ranges, first, index);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(first) = start {
+        push_strip(&mut ranges, first, values.len());
+    }
+    ranges
+}
+
+// Splits strips between consecutive samples more than `threshold` apart.
+fn split_gaps(ranges: &[(i32, i32)], timestamps: &[f32], threshold: f32) -> Vec<(i32, i32)> {
+    let mut out = Vec::with_capacity(ranges.len());
+    for &(first, count) in ranges {
+        let (first, end) = (first as usize, (first + count) as usize);
+        let mut start = first;
+        for index in first + 1..end {
+            if (timestamps[index] - timestamps[index - 1]).abs() > threshold {
+                push_strip(&mut out, start, index);
+                start = index;
+            }
+        }
+        push_strip(&mut out, start, end);
+    }
+    out
+}
+
+fn push_strip(ranges: &mut Vec<(i32, i32)>, first: usize, end: usize) {
+    // a single isolated sample cannot form a line segment
+    if end - first >= 2 {
+        ranges.push((first as i32, (end - first) as i32));
+    }
+}
+
+// First sample pair bracketing `t`, as (index, weight towards index + 1).
+fn bracket_sample(samples: &[f32], t: f32) -> Option<(usize, f32)> {
+    if !t.is_finite() {
+        return None;
+    }
+    if samples.len() == 1 {
+        return (samples[0] == t).then_some((0, 0.0));
+    }
+    samples.windows(2).enumerate().find_map(|(index, pair)| {
+        let (lo, hi) = (pair[0].min(pair[1]), pair[0].max(pair[1]));
+        if t < lo || t > hi {
+            return None;
+        }
+        let span = pair[1] - pair[0];
+        let weight = if span == 0.0 {
+            0.0
+        } else {
+            (t - pair[0]) / span
+        };
+        Some((index, weight))
+    })
+}
+
+fn apply_scale(label: &str, samples: Vec<f32>, scale: AxisScale) -> Result<Vec<f32>, JsValue> {
+    match scale {
+        AxisScale::Linear => Ok(samples),
+        AxisScale::Log10 => samples
+            .into_iter()
+            .map(|value| {
+                if value.is_finite() && value > 0.0 {
+                    Ok(value.log10())
+                } else {
+                    Err(error(&format!(
+                        "{label}s must be strictly positive on a log10 axis"
+                    )))
+                }
+            })
+            .collect(),
+    }
+}
+
 fn compute_range(label: &str, samples: &[f32]) -> Result<(f32, f32), JsValue> {
     let mut min_value = f32::INFINITY;
     let mut max_value = f32::NEG_INFINITY;
@@ -417,23 +1955,290 @@ fn compute_range(label: &str, samples: &[f32]) -> Result<(f32, f32), JsValue> {
     Ok((min_value, max_value))
 }
 
-fn query_line_width_limits(gl: &Gl) -> [f32; 2] {
-    let raw = gl.get_parameter(Gl::ALIASED_LINE_WIDTH_RANGE);
-    if let Ok(value) = raw {
-        let array = Array::from(&value);
-        let min = array
-            .get(0)
-            .as_f64()
-            .map(|v| v as f32)
-            .filter(|v| v.is_finite() && *v > 0.0)
-            .unwrap_or(1.0);
-        let max = array
-            .get(1)
-            .as_f64()
-            .map(|v| v as f32)
-            .filter(|v| v.is_finite() && *v >= min)
-            .unwrap_or(min);
-        return [min, max.max(min)];
-    }
-    [1.0, 1.0]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_respect_plot_margins() {
+        let timestamps = [0.0, 10.0];
+        let values = [5.0, 15.0];
+        let full = build_positions(
+            &timestamps,
+            &values,
+            [0.0, 10.0],
+            [5.0, 15.0],
+            &PlotArea::FULL,
+        );
+        assert_eq!(full, vec![-1.0, -1.0, 1.0, 1.0]);
+
+        let inset = PlotArea::from_margins(0.1, 0.0, 0.25, 0.0).unwrap();
+        let positions = build_positions(&timestamps, &values, [0.0, 10.0], [5.0, 15.0], &inset);
+        let expected = [-0.8, -1.0, 1.0, 0.5];
+        for (actual, expected) in positions.iter().zip(expected) {
+            assert!((actual - expected).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn line_buffers_grow_by_doubling() {
+        assert_eq!(grown_capacity(0, 40), 40);
+        assert_eq!(grown_capacity(40, 42), 80);
+        assert_eq!(grown_capacity(40, 120), 120);
+        assert_eq!(grown_capacity(80, 42), 80);
+    }
+
+    #[test]
+    fn append_counts_must_line_up() {
+        assert!(check_append(3, &[3, 3], &[false, false]).is_ok());
+        assert!(check_append(0, &[], &[]).is_ok());
+        let mismatched = check_append(3, &[3, 2, 3], &[false; 3]).unwrap_err();
+        assert!(mismatched.contains("series[1]"));
+        let missing = check_append(3, &[3], &[false, false]).unwrap_err();
+        assert!(missing.contains("2 series"));
+    }
+
+    fn stage(values: Vec<f32>) -> SeriesStage {
+        SeriesStage {
+            values,
+            color: [1.0; 4],
+            line_width: 1.0,
+            break_on_nan: false,
+            fade_tail: None,
+            directional: None,
+        }
+    }
+
+    #[test]
+    fn trim_drops_the_oldest_samples_in_step() {
+        let mut raw = vec![10.0, 11.0, 12.0, 13.0, 14.0];
+        let mut samples = raw.clone();
+        let mut sources = vec![
+            stage(vec![9.0, 1.0, 2.0, 3.0, 4.0]),
+            stage(Vec::new()), // cleared
+            stage(vec![0.0, 5.0, 6.0, 7.0, 8.0]),
+        ];
+        let extents = trim_samples(Some(3), &mut raw, &mut samples, &mut sources).unwrap();
+        assert_eq!(raw, [12.0, 13.0, 14.0]);
+        assert_eq!(samples, raw);
+        assert_eq!(sources[0].values, [2.0, 3.0, 4.0]);
+        assert!(sources[1].values.is_empty());
+        assert_eq!(sources[2].values, [6.0, 7.0, 8.0]);
+        // both extents shrink to what is left: the 9.0 and 0.0 went with the trim
+        assert_eq!(extents, Some(([12.0, 14.0], [2.0, 8.0])));
+
+        // under the limit nothing moves and the extents stay as they were
+        let extents = trim_samples(Some(3), &mut raw, &mut samples, &mut sources).unwrap();
+        assert_eq!(extents, None);
+        assert_eq!(
+            trim_samples(None, &mut raw, &mut samples, &mut sources).unwrap(),
+            None
+        );
+        assert_eq!(raw.len(), 3);
+    }
+
+    #[test]
+    fn fixed_domains_survive_a_trim() {
+        let mut raw = vec![0.0, 1.0, 2.0];
+        let mut samples = raw.clone();
+        let mut sources = vec![stage(vec![-50.0, 1.0, 2.0])];
+        let (_, value_range) = trim_samples(Some(2), &mut raw, &mut samples, &mut sources)
+            .unwrap()
+            .unwrap();
+        assert_eq!(value_range, [1.0, 2.0]);
+        // a fixed value domain still wins over the shrunken data extent...
+        let fixed = Some([-100.0, 100.0]);
+        assert_eq!(shown_range(None, fixed, value_range), [-100.0, 100.0]);
+        // ...a pan/zoom window wins over both, and with neither the trimmed data shows
+        assert_eq!(
+            shown_range(Some([0.0, 1.0]), fixed, value_range),
+            [0.0, 1.0]
+        );
+        assert_eq!(shown_range(None, None, value_range), [1.0, 2.0]);
+    }
+
+    #[test]
+    fn appended_timestamps_never_go_backwards() {
+        assert!(check_append_order(None, &[1.0, 2.0, 3.0]).is_ok());
+        assert!(check_append_order(Some(3.0), &[3.5, 4.0]).is_ok());
+        assert!(check_append_order(Some(3.0), &[]).is_ok());
+        // repeats are rejected too, the sequence must keep increasing
+        assert!(check_append_order(None, &[1.0, 2.0, 2.0]).is_err());
+        assert!(check_append_order(Some(3.0), &[3.0]).is_err());
+        assert!(check_append_order(Some(3.0), &[2.0]).is_err());
+        assert!(check_append_order(None, &[1.0, 3.0, 2.0]).is_err());
+    }
+
+    #[test]
+    fn cleared_series_take_no_appended_values() {
+        assert!(check_append(3, &[3, 0], &[false, true]).is_ok());
+        let dropped = check_append(3, &[3, 3], &[false, true]).unwrap_err();
+        assert!(dropped.contains("series[1] is cleared"));
+    }
+
+    #[test]
+    fn fade_decays_with_age() {
+        assert_eq!(fade_alpha(0.0, 10.0), 1.0);
+        assert_eq!(fade_alpha(2.5, 10.0), 0.75);
+        assert_eq!(fade_alpha(10.0, 10.0), 0.0);
+        assert_eq!(fade_alpha(25.0, 10.0), 0.0);
+        assert_eq!(fade_alpha(-3.0, 10.0), 1.0);
+    }
+
+    #[test]
+    fn zoom_keeps_anchor_fixed() {
+        let zoomed = zoom_range([0.0, 10.0], 2.5, 2.0);
+        assert_eq!(zoomed, [1.25, 6.25]);
+        let anchor_before = ndc_to_range(-0.5, [-1.0, 1.0], [0.0, 10.0]);
+        let anchor_after = ndc_to_range(-0.5, [-1.0, 1.0], zoomed);
+        assert_eq!(anchor_before, anchor_after);
+        assert_eq!(shift_range([1.0, 2.0], -0.5), [0.5, 1.5]);
+    }
+
+    #[test]
+    fn fixed_domains_need_ordered_bounds() {
+        assert_eq!(fixed_domain(-1.0, 4.0), Ok([-1.0, 4.0]));
+        assert!(fixed_domain(2.0, 2.0).is_err());
+        assert!(fixed_domain(3.0, 1.0).is_err());
+        assert!(fixed_domain(f32::NAN, 1.0).is_err());
+        assert!(fixed_domain(0.0, f32::INFINITY).is_err());
+    }
+
+    #[test]
+    fn sample_brackets_interpolate() {
+        let samples = [0.0, 1.0, 3.0];
+        assert_eq!(bracket_sample(&samples, 0.5), Some((0, 0.5)));
+        assert_eq!(bracket_sample(&samples, 2.0), Some((1, 0.5)));
+        assert_eq!(bracket_sample(&samples, 3.0), Some((1, 1.0)));
+        assert_eq!(bracket_sample(&samples, -0.1), None);
+        assert_eq!(bracket_sample(&samples, 3.5), None);
+        assert_eq!(bracket_sample(&samples, f32::NAN), None);
+        assert_eq!(bracket_sample(&[2.0], 2.0), Some((0, 0.0)));
+    }
+
+    #[test]
+    fn directional_colors_follow_each_step() {
+        let colors = DirectionalColor {
+            up: [0.0, 1.0, 0.0, 1.0],
+            down: [1.0, 0.0, 0.0, 1.0],
+        };
+        let tints = directional_colors(&[3.0, 2.0, 2.0, 5.0, f32::NAN, 4.0, 1.0], &colors);
+        let rising: Vec<bool> = tints.chunks_exact(4).map(|c| c[1] == 1.0).collect();
+        assert_eq!(rising, [false, false, true, true, false, false, false]);
+        assert_eq!(nearest_end(&[0.0, 1.0, 2.0], 5.0), (2, 0.0));
+        assert_eq!(nearest_end(&[0.0, 1.0, 2.0], -1.0), (0, 0.0));
+        assert_eq!(segment_end(&[0.0, 1.0, 2.0], 0.5), 1);
+        assert_eq!(segment_end(&[0.0, 1.0, 2.0], 1.5), 2);
+        assert_eq!(segment_end(&[0.0, 1.0, 2.0], 5.0), 2);
+        assert_eq!(segment_end(&[0.0, 1.0, 2.0], -1.0), 1);
+    }
+
+    #[test]
+    fn strip_segments_take_one_color_each() {
+        let palette = DirectionalColor {
+            up: [0.0, 1.0, 0.0, 1.0],
+            down: [1.0, 0.0, 0.0, 1.0],
+        };
+        // fall, rise, rise
+        let values = [3.0, 2.0, 4.0, 5.0];
+        let tints = directional_colors(&values, &palette);
+        let positions: Vec<f32> = (0..4).flat_map(|i| [i as f32, values[i]]).collect();
+        let (positions, ranges, colors) = tint_strip(&positions, &[(0, 4)], &tints);
+        // the turn at sample 1 is doubled: once ending the fall, once starting the rise
+        assert_eq!(ranges, vec![(0, 5)]);
+        let xs: Vec<f32> = positions.chunks_exact(2).map(|v| v[0]).collect();
+        assert_eq!(xs, [0.0, 1.0, 1.0, 2.0, 3.0]);
+        let rising: Vec<bool> = colors.chunks_exact(4).map(|c| c[1] == 1.0).collect();
+        assert_eq!(rising, [false, false, true, true, true]);
+
+        // a later strip starts after the duplicated vertex
+        let values = [3.0, 2.0, 4.0, 5.0, f32::NAN, 1.0, 0.0];
+        let tints = directional_colors(&values, &palette);
+        let (_, ranges, colors) = tint_strip(&[0.0; 14], &[(0, 4), (5, 2)], &tints);
+        assert_eq!(ranges, vec![(0, 5), (5, 2)]);
+        let rising: Vec<bool> = colors.chunks_exact(4).map(|c| c[1] == 1.0).collect();
+        assert_eq!(rising, [false, false, true, true, true, false, false]);
+    }
+
+    #[test]
+    fn nan_values_split_strips() {
+        assert_eq!(strip_ranges(&[1.0, 2.0, 3.0]), vec![(0, 3)]);
+        assert_eq!(
+            strip_ranges(&[1.0, 2.0, f32::NAN, 3.0, 4.0, 5.0]),
+            vec![(0, 2), (3, 3)]
+        );
+        assert_eq!(
+            strip_ranges(&[f32::NAN, 1.0, f32::NAN, 2.0, 3.0, f32::NAN]),
+            vec![(3, 2)]
+        );
+        assert!(strip_ranges(&[f32::NAN, f32::NAN]).is_empty());
+    }
+
+    #[test]
+    fn gaps_split_strips() {
+        let timestamps = [0.0, 1.0, 2.0, 10.0, 11.0, 30.0, 31.0, 32.0];
+        assert_eq!(
+            split_gaps(&[(0, 8)], &timestamps, 2.0),
+            vec![(0, 3), (3, 2), (5, 3)]
+        );
+        assert_eq!(split_gaps(&[(0, 8)], &timestamps, 50.0), vec![(0, 8)]);
+        // the lone sample after a gap draws nothing, like one between NaNs
+        assert_eq!(split_gaps(&[(1, 3)], &timestamps, 2.0), vec![(1, 2)]);
+    }
+
+    fn stroke(join: LineJoin) -> StrokeStyle {
+        StrokeStyle {
+            width: 2.0,
+            join,
+            miter_limit: DEFAULT_MITER_LIMIT,
+            feather: 0.0,
+        }
+    }
+
+    fn expanded_vertices(positions: &[f32], join: LineJoin) -> Vec<f32> {
+        let ranges = [(0, (positions.len() / 2) as i32)];
+        expand_polyline(positions, &ranges, [1.0, 1.0], &stroke(join))
+    }
+
+    #[test]
+    fn line_joins_fill_corners() {
+        let straight = expanded_vertices(&[0.0, 0.0, 10.0, 0.0], LineJoin::Miter);
+        assert_eq!(straight.len(), 6 * 3);
+        // edge distances span the full width across the quad
+        assert_eq!([straight[2], straight[5]], [1.0, -1.0]);
+
+        // right-angle left turn: two segment quads plus the join
+        let corner = [0.0, 0.0, 10.0, 0.0, 10.0, 10.0];
+        assert_eq!(expanded_vertices(&corner, LineJoin::Bevel).len(), 15 * 3);
+        assert_eq!(expanded_vertices(&corner, LineJoin::Round).len(), 24 * 3);
+        let miter = expanded_vertices(&corner, LineJoin::Miter);
+        assert_eq!(miter.len(), 18 * 3);
+        let tip = [miter[miter.len() - 6], miter[miter.len() - 5]];
+        assert!((tip[0] - 11.0).abs() < 1e-4 && (tip[1] + 1.0).abs() < 1e-4);
+
+        // a near reversal exceeds the miter limit and falls back to a bevel
+        let spike = [0.0, 0.0, 10.0, 0.0, 0.0, 0.5];
+        assert_eq!(expanded_vertices(&spike, LineJoin::Miter).len(), 15 * 3);
+    }
+
+    #[test]
+    fn antialias_feather_widens_quads() {
+        let mut style = stroke(LineJoin::Miter);
+        style.feather = AA_FEATHER;
+        let ranges = [(0, 2)];
+        let vertices = expand_polyline(&[0.0, 0.0, 10.0, 0.0], &ranges, [1.0, 1.0], &style);
+        assert_eq!(vertices[1], 1.0 + AA_FEATHER);
+        assert_eq!(vertices[2], 1.0 + AA_FEATHER);
+    }
+
+    #[test]
+    fn plot_margins_validation() {
+        assert!(PlotArea::from_margins(-0.1, 0.0, 0.0, 0.0).is_err());
+        assert!(PlotArea::from_margins(0.5, 0.5, 0.0, 0.0).is_err());
+        assert!(PlotArea::from_margins(0.0, 0.0, f32::NAN, 0.0).is_err());
+        assert_eq!(
+            PlotArea::from_margins(0.0, 0.0, 0.0, 0.0),
+            Ok(PlotArea::FULL)
+        );
+    }
 }
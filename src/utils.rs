@@ -14,7 +14,7 @@ pub(crate) fn log(message: &str) {
 }
 
 pub(crate) fn clamp_unit(value: f32) -> f32 {
-    value.max(0.0).min(1.0)
+    value.clamp(0.0, 1.0)
 }
 
 pub(crate) fn error(message: &str) -> JsValue {
@@ -30,19 +30,32 @@ pub(crate) fn identity_matrix() -> [f32; MATRIX_FLOATS] {
     out
 }
 
-pub(crate) fn matrix_from_array(source: &Float32Array) -> Result<[f32; MATRIX_FLOATS], JsValue> {
-    read_fixed(source, "matrix")
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatrixLayout {
+    ColumnMajor,
+    RowMajor,
 }
 
-pub(crate) fn copy_into_matrix(
-    target: &mut [f32; MATRIX_FLOATS],
+pub(crate) fn matrix_from_array(
     source: &Float32Array,
-) -> Result<(), JsValue> {
-    if source.length() as usize != MATRIX_FLOATS {
-        return Err(error("matrices must contain 16 floats"));
+    layout: MatrixLayout,
+) -> Result<[f32; MATRIX_FLOATS], JsValue> {
+    let matrix = read_fixed(source, "matrix")?;
+    Ok(match layout {
+        MatrixLayout::ColumnMajor => matrix,
+        MatrixLayout::RowMajor => transpose_matrix(&matrix),
+    })
+}
+
+pub(crate) fn transpose_matrix(matrix: &[f32; MATRIX_FLOATS]) -> [f32; MATRIX_FLOATS] {
+    let mut out = [0.0; MATRIX_FLOATS];
+    for column in 0..4 {
+        for row in 0..4 {
+            out[row * 4 + column] = matrix[column * 4 + row];
+        }
     }
-    source.copy_to(target);
-    Ok(())
+    out
 }
 
 pub(crate) fn vec3_from_array(array: &Float32Array) -> Result<[f32; 3], JsValue> {
@@ -69,3 +82,21 @@ pub(crate) fn array_to_vec(array: &Float32Array) -> Vec<f32> {
     }
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transpose_round_trip() {
+        let mut matrix = [0.0; MATRIX_FLOATS];
+        for (index, value) in matrix.iter_mut().enumerate() {
+            *value = index as f32;
+        }
+        let transposed = transpose_matrix(&matrix);
+        assert_eq!(transposed[1], 4.0);
+        assert_eq!(transposed[12], 3.0);
+        assert_eq!(transpose_matrix(&transposed), matrix);
+        assert_eq!(transpose_matrix(&identity_matrix()), identity_matrix());
+    }
+}
@@ -77,6 +77,9 @@ const tests = [
             await withBatchedRenderer("Instance Handles", async ({ renderer, composer }) => {
                 const mesh = buildSingleTriangle();
                 const meshHandle = renderer.register_mesh(mesh);
+                if (renderer.mesh_triangle_count(meshHandle) !== 1) {
+                    throw new Error("a single-triangle mesh should report one triangle");
+                }
                 const handleA = renderer.create_instance(meshHandle, identityMatrix());
                 const handleB = renderer.create_instance(
                     meshHandle,
@@ -87,6 +90,10 @@ const tests = [
                 renderer.set_instance_transform(handleA, moved);
                 composer.render();
 
+                if (renderer.instance_mesh(handleA) !== meshHandle) {
+                    throw new Error("instance_mesh should report the owning mesh handle");
+                }
+
                 renderer.remove_instance(handleB);
                 const remaining = renderer.instance_count();
                 if (remaining !== 1) {
@@ -97,6 +104,246 @@ const tests = [
             });
         },
     },
+    {
+        label: "Snapshot Restore",
+        slug: slugify("Snapshot Restore"),
+        async run() {
+            await withBatchedRenderer("Snapshot Restore", async ({ renderer, composer }) => {
+                const meshHandle = renderer.register_mesh(buildSingleTriangle());
+                const kept = renderer.create_instance(meshHandle, identityMatrix());
+                const snapshot = renderer.snapshot();
+
+                const moved = rotationTranslationMatrix([0.5, 0, 0], 0);
+                renderer.set_instance_transform(kept, moved);
+                renderer.create_instance(meshHandle, moved);
+                renderer.restore(snapshot);
+                composer.render();
+
+                if (renderer.instance_count() !== 1) {
+                    throw new Error("restore should drop instances created after the snapshot");
+                }
+                const restored = renderer.snapshot().instances[0];
+                if (restored.handle !== kept || restored.values[12] !== 0) {
+                    throw new Error("restore should bring back the original handle and transform");
+                }
+            });
+        },
+    },
+    {
+        label: "Mesh Reset",
+        slug: slugify("Mesh Reset"),
+        async run() {
+            await withBatchedRenderer("Mesh Reset", async ({ renderer, composer }) => {
+                const first = renderer.register_mesh(buildSingleTriangle());
+                renderer.create_instance(first, identityMatrix());
+                const second = renderer.register_mesh(buildSingleTriangle());
+                renderer.create_instance(second, identityMatrix());
+                composer.render();
+
+                const proto = WebGL2RenderingContext.prototype;
+                const originalDeleteBuffer = proto.deleteBuffer;
+                const originalDeleteVertexArray = proto.deleteVertexArray;
+                let bufferDeletes = 0;
+                let vaoDeletes = 0;
+                proto.deleteBuffer = function (buffer) {
+                    bufferDeletes += 1;
+                    return originalDeleteBuffer.call(this, buffer);
+                };
+                proto.deleteVertexArray = function (vao) {
+                    vaoDeletes += 1;
+                    return originalDeleteVertexArray.call(this, vao);
+                };
+                try {
+                    renderer.reset_meshes();
+                } finally {
+                    proto.deleteBuffer = originalDeleteBuffer;
+                    proto.deleteVertexArray = originalDeleteVertexArray;
+                }
+
+                // each mesh owns a vertex buffer, an instance buffer, and a VAO
+                if (bufferDeletes !== 4 || vaoDeletes !== 2) {
+                    throw new Error(
+                        `expected 4 buffer and 2 VAO deletes, saw ${bufferDeletes} and ${vaoDeletes}`,
+                    );
+                }
+                if (renderer.instance_count() !== 0) {
+                    throw new Error("reset_meshes should drop every instance");
+                }
+
+                const fresh = renderer.register_mesh(buildSingleTriangle());
+                if (fresh !== first) {
+                    throw new Error("mesh handles should restart after a reset");
+                }
+                renderer.create_instance(fresh, identityMatrix());
+                composer.render();
+            });
+        },
+    },
+    {
+        label: "Mesh Removal",
+        slug: slugify("Mesh Removal"),
+        async run() {
+            await withBatchedRenderer("Mesh Removal", async ({ renderer, composer, canvas }) => {
+                renderer.set_retain_mesh_data(true);
+                const removed = renderer.register_mesh(buildSingleTriangle());
+                const removedInstance = renderer.create_instance(removed, identityMatrix());
+                const kept = renderer.register_mesh(buildSingleTriangle());
+                const keptInstance = renderer.create_instance(kept, identityMatrix());
+                composer.render();
+
+                renderer.remove_mesh(removed);
+                const proto = WebGL2RenderingContext.prototype;
+                const originalDraw = proto.drawArraysInstanced;
+                const counts = [];
+                proto.drawArraysInstanced = function (mode, first, count, instances) {
+                    counts.push(instances);
+                    return originalDraw.call(this, mode, first, count, instances);
+                };
+                try {
+                    renderer.set_instance_transform(
+                        keptInstance,
+                        rotationTranslationMatrix([0.25, 0, 0], 0),
+                    );
+                    composer.render();
+                } finally {
+                    proto.drawArraysInstanced = originalDraw;
+                }
+                if (counts.join(",") !== "1") {
+                    throw new Error(`expected one draw of the kept mesh, saw ${counts.join(",")}`);
+                }
+                const moved = renderer.snapshot().instances;
+                if (moved.length !== 1 || moved[0].handle !== keptInstance || moved[0].values[12] !== 0.25) {
+                    throw new Error("the kept instance should survive remove_mesh and move");
+                }
+
+                expectThrows(() => renderer.create_instance(removed, identityMatrix()), "removed mesh handle");
+                expectThrows(
+                    () => renderer.set_instance_transform(removedInstance, identityMatrix()),
+                    "instance of a removed mesh",
+                );
+                expectThrows(() => renderer.remove_mesh(removed), "second remove_mesh");
+
+                const json = renderer.to_json();
+                if (JSON.parse(json).meshes[0] !== null) {
+                    throw new Error("to_json should write the removed mesh as null");
+                }
+                const { BatchedRenderer } = await loadRendererModule();
+                const copy = BatchedRenderer.from_json(canvas.id, json);
+                try {
+                    if (copy.instance_count() !== 1) {
+                        throw new Error("from_json should restore only the kept instance");
+                    }
+                    expectThrows(() => copy.create_instance(removed, identityMatrix()), "reloaded removed handle");
+                    copy.create_instance(kept, identityMatrix());
+                } finally {
+                    copy.free();
+                }
+            });
+        },
+    },
+    {
+        label: "Instance Visibility",
+        slug: slugify("Instance Visibility"),
+        async run() {
+            await withBatchedRenderer("Instance Visibility", async ({ renderer, composer }) => {
+                const mesh = renderer.register_mesh(buildSingleTriangle());
+                const handles = [0, 1, 2].map((i) =>
+                    renderer.create_instance(mesh, rotationTranslationMatrix([i * 0.2, 0, 0], 0)),
+                );
+
+                const proto = WebGL2RenderingContext.prototype;
+                const originalDraw = proto.drawArraysInstanced;
+                const counts = [];
+                proto.drawArraysInstanced = function (mode, first, count, instances) {
+                    counts.push(instances);
+                    return originalDraw.call(this, mode, first, count, instances);
+                };
+                try {
+                    renderer.set_instance_visible(handles[1], false);
+                    composer.render();
+                    renderer.set_instance_visible(handles[1], true);
+                    composer.render();
+                } finally {
+                    proto.drawArraysInstanced = originalDraw;
+                }
+
+                if (counts.join(",") !== "2,3") {
+                    throw new Error(`expected instanced draws of 2 then 3, saw ${counts.join(",")}`);
+                }
+                if (renderer.instance_count() !== 3) {
+                    throw new Error("hidden instances should keep their handles");
+                }
+            });
+        },
+    },
+    {
+        label: "Animation Loop",
+        slug: slugify("Animation Loop"),
+        async run() {
+            await withBatchedRenderer("Animation Loop", async ({ renderer, composer }) => {
+                const mesh = renderer.register_mesh(buildSingleTriangle());
+                renderer.create_instance(mesh, rotationTranslationMatrix([0, 0, 0], 0));
+
+                const timestamps = [];
+                await new Promise((resolve) => {
+                    const handle = composer.start_loop((timestamp) => {
+                        timestamps.push(timestamp);
+                        if (timestamps.length === 3) {
+                            composer.stop_loop(handle);
+                            resolve();
+                        }
+                    });
+                });
+                // give a stray frame the chance to fire after stopping
+                await new Promise((resolve) => requestAnimationFrame(() => requestAnimationFrame(resolve)));
+
+                if (timestamps.length !== 3) {
+                    throw new Error(`expected 3 frames before stop_loop, saw ${timestamps.length}`);
+                }
+                if (timestamps.some((t, i) => i > 0 && t < timestamps[i - 1])) {
+                    throw new Error("frame timestamps should not go backwards");
+                }
+            });
+        },
+    },
+    {
+        label: "Animation Loop Self Stop",
+        slug: slugify("Animation Loop Self Stop"),
+        async run() {
+            await withBatchedRenderer("Animation Loop Self Stop", async ({ renderer, composer }) => {
+                const mesh = renderer.register_mesh(buildSingleTriangle());
+                renderer.create_instance(mesh, identityMatrix());
+
+                const proto = WebGL2RenderingContext.prototype;
+                const originalDraw = proto.drawArraysInstanced;
+                let draws = 0;
+                let frames = 0;
+                proto.drawArraysInstanced = function (...args) {
+                    draws += 1;
+                    return originalDraw.apply(this, args);
+                };
+                try {
+                    await new Promise((resolve) => {
+                        const handle = composer.start_loop(() => {
+                            frames += 1;
+                            composer.stop_loop(handle);
+                            resolve();
+                        });
+                    });
+                    await new Promise((resolve) => requestAnimationFrame(() => requestAnimationFrame(resolve)));
+                } finally {
+                    proto.drawArraysInstanced = originalDraw;
+                }
+
+                if (frames !== 1) {
+                    throw new Error(`expected the callback to run once, saw ${frames}`);
+                }
+                if (draws !== 0) {
+                    throw new Error(`stopping inside the callback should skip that frame's render, saw ${draws} draws`);
+                }
+            });
+        },
+    },
     {
         label: "Dynamic Batches",
         slug: slugify("Dynamic Batches"),
@@ -105,7 +352,7 @@ const tests = [
                 const mesh = buildSingleTriangle();
                 const meshHandle = renderer.register_mesh(mesh);
                 const maxPerBatch = renderer.max_instances();
-                const target = maxPerBatch + 3;
+                const target = maxPerBatch;
                 const handles = [];
 
                 for (let i = 0; i < target; i += 1) {
@@ -117,6 +364,16 @@ const tests = [
                     throw new Error(`expected ${target} instances to be active`);
                 }
 
+                let overBudget = false;
+                try {
+                    renderer.create_instance(meshHandle, identityMatrix());
+                } catch (err) {
+                    overBudget = true;
+                }
+                if (!overBudget) {
+                    throw new Error("create_instance should reject instances beyond max_instances");
+                }
+
                 composer.render();
                 composer.render();
 
@@ -129,6 +386,80 @@ const tests = [
             });
         },
     },
+    {
+        label: "Billboard Instances",
+        slug: slugify("Billboard Instances"),
+        async run() {
+            await withBatchedRenderer("Billboard Instances", async ({ renderer, composer }) => {
+                const meshHandle = renderer.register_mesh_billboard(buildSingleTriangle());
+                const handle = renderer.create_billboard(meshHandle, 0.2, -0.1, 0, 0.5);
+                renderer.set_billboard(handle, -0.2, 0.1, 0, 0.75);
+                composer.render();
+
+                let threw = false;
+                try {
+                    renderer.create_instance(meshHandle, identityMatrix());
+                } catch (err) {
+                    threw = true;
+                }
+                if (!threw) {
+                    throw new Error("create_instance should reject billboard meshes");
+                }
+                if (renderer.instance_count() !== 1) {
+                    throw new Error("expected exactly one billboard instance");
+                }
+            });
+        },
+    },
+    {
+        label: "Shared Mesh Library",
+        slug: slugify("Shared Mesh Library"),
+        async run() {
+            const { MeshLibrary } = await loadRendererModule();
+            const library = new MeshLibrary();
+            const meshId = library.add(buildSingleTriangle());
+            await withBatchedRenderer("Shared Mesh Library", async ({ renderer, composer }) => {
+                const meshHandle = renderer.register_from_library(library, meshId);
+                renderer.create_instance(meshHandle, identityMatrix());
+                composer.render();
+                if (renderer.mesh_triangle_count(meshHandle) !== 1) {
+                    throw new Error("library mesh should upload a single triangle");
+                }
+            });
+            library.free();
+        },
+    },
+    {
+        label: "Offscreen Canvas",
+        slug: slugify("Offscreen Canvas"),
+        async run() {
+            if (typeof OffscreenCanvas === "undefined") {
+                return;
+            }
+            const { CanvasComposer } = await loadRendererModule();
+            const canvas = new OffscreenCanvas(64, 64);
+            const composer = CanvasComposer.from_offscreen(canvas);
+            try {
+                const renderer = composer.add_batched_pass();
+                const mesh = renderer.register_mesh(buildSingleTriangle());
+                renderer.create_instance(mesh, identityMatrix());
+                composer.set_clear_color(1, 0, 0, 1);
+                composer.render();
+                if (!composer.is_usable()) {
+                    throw new Error("an offscreen canvas should always be usable");
+                }
+                const pixels = new Uint8Array(4);
+                const gl = canvas.getContext("webgl2");
+                gl.readPixels(0, 0, 1, 1, gl.RGBA, gl.UNSIGNED_BYTE, pixels);
+                if (pixels[0] !== 255) {
+                    throw new Error(`expected the clear color in the corner, got ${pixels.join(",")}`);
+                }
+                renderer.free();
+            } finally {
+                composer.free();
+            }
+        },
+    },
     {
         label: "Orbit Camera Controls",
         slug: slugify("Orbit Camera Controls"),
@@ -210,6 +541,161 @@ const tests = [
             );
         },
     },
+    {
+        label: "Instance Picking",
+        slug: slugify("Instance Picking"),
+        async run() {
+            await withBatchedRenderer("Instance Picking", async ({ renderer, composer, canvas }) => {
+                const mesh = renderer.register_mesh(buildSingleTriangle());
+                const near = renderer.create_instance(mesh, identityMatrix());
+                const far = renderer.create_instance(mesh, rotationTranslationMatrix([-0.6, -0.6, 0], 0));
+                composer.render();
+
+                // drawing-buffer pixel of an NDC point, top-left origin
+                const pixel = (x, y) => [
+                    Math.floor(((x + 1) / 2) * canvas.width),
+                    Math.floor(((1 - y) / 2) * canvas.height),
+                ];
+                const picked = renderer.pick(...pixel(0.1, 0.1));
+                if (picked !== near) {
+                    throw new Error(`expected pick to find instance ${near}, saw ${picked}`);
+                }
+                const pickedFar = renderer.pick(...pixel(-0.5, -0.5));
+                if (pickedFar !== far) {
+                    throw new Error(`expected pick to find instance ${far}, saw ${pickedFar}`);
+                }
+                const background = renderer.pick(...pixel(-0.8, 0.8));
+                if (background !== undefined) {
+                    throw new Error(`an empty pixel should pick nothing, saw ${background}`);
+                }
+            });
+        },
+    },
+    {
+        label: "Interpolated Instances",
+        slug: slugify("Interpolated Instances"),
+        async run() {
+            await withBatchedRenderer("Interpolated Instances", async ({ renderer, composer, canvas }) => {
+                const mesh = renderer.register_mesh_with_options(buildSingleTriangle(), {
+                    interpolated: true,
+                });
+                const body = renderer.create_instance(mesh, identityMatrix());
+                renderer.set_instance_transform_pair(
+                    body,
+                    rotationTranslationMatrix([-0.6, -0.6, 0], 0),
+                    identityMatrix(),
+                );
+
+                // pick runs the same vertex shader, so it shows where the blend put the instance
+                const pixel = (x, y) => [
+                    Math.floor(((x + 1) / 2) * canvas.width),
+                    Math.floor(((1 - y) / 2) * canvas.height),
+                ];
+                const expectAt = (alpha, hit, miss) => {
+                    renderer.set_interpolation_alpha(alpha);
+                    composer.render();
+                    if (renderer.pick(...pixel(...hit)) !== body) {
+                        throw new Error(`alpha ${alpha} should draw the instance at ${hit}`);
+                    }
+                    if (renderer.pick(...pixel(...miss)) !== undefined) {
+                        throw new Error(`alpha ${alpha} should leave ${miss} empty`);
+                    }
+                };
+                expectAt(0, [-0.5, -0.5], [0.1, 0.1]);
+                expectAt(1, [0.1, 0.1], [-0.5, -0.5]);
+                // halfway sits between the two transforms
+                expectAt(0.5, [-0.2, -0.2], [0.3, 0.05]);
+
+                renderer.set_instance_transform(body, identityMatrix());
+                renderer.set_interpolation_alpha(0);
+                composer.render();
+                if (renderer.pick(...pixel(0.1, 0.1)) !== body) {
+                    throw new Error("a plain set_instance_transform should snap both matrices");
+                }
+
+                const plain = renderer.register_mesh(buildSingleTriangle());
+                const still = renderer.create_instance(plain, identityMatrix());
+                expectThrows(
+                    () => renderer.set_instance_transform_pair(still, identityMatrix(), identityMatrix()),
+                    "set_instance_transform_pair on a plain mesh",
+                );
+            });
+        },
+    },
+    {
+        label: "Per-Pass Clears",
+        slug: slugify("Per-Pass Clears"),
+        async run() {
+            await withBatchedRenderer("Per-Pass Clears", async ({ renderer, composer, canvas }) => {
+                const mesh = renderer.register_mesh(buildSingleTriangle());
+                renderer.create_instance(mesh, identityMatrix());
+                const overlay = composer.add_batched_pass();
+                try {
+                    const gl = canvas.getContext("webgl2");
+                    // a pixel inside the triangle, read straight after the frame
+                    const readCenter = () => {
+                        composer.render();
+                        composer.finish();
+                        const rgba = new Uint8Array(4);
+                        const x = Math.floor(0.55 * canvas.width);
+                        const y = Math.floor(0.55 * canvas.height); // GL rows start at the bottom
+                        gl.readPixels(x, y, 1, 1, gl.RGBA, gl.UNSIGNED_BYTE, rgba);
+                        return Array.from(rgba).join(",");
+                    };
+                    const red = "255,0,0,255";
+
+                    const drawn = readCenter();
+                    if (drawn === red) {
+                        throw new Error("the triangle should not start out red");
+                    }
+                    composer.set_pass_clear(1, undefined, 1.0);
+                    if (readCenter() !== drawn) {
+                        throw new Error("a depth-only pass clear should keep the color drawn before it");
+                    }
+                    composer.set_pass_clear(1, new Float32Array([1, 0, 0, 1]), undefined);
+                    if (readCenter() !== red) {
+                        throw new Error("a color pass clear should wipe what earlier passes drew");
+                    }
+                    composer.set_pass_enabled(1, false);
+                    if (readCenter() !== drawn) {
+                        throw new Error("a disabled pass should not clear");
+                    }
+                    composer.set_pass_enabled(1, true);
+                    composer.set_pass_clear(1, undefined, undefined);
+                    if (readCenter() !== drawn) {
+                        throw new Error("clearing the pass clear should stop it clearing");
+                    }
+                    expectThrows(() => composer.set_pass_clear(5, undefined, 1.0), "set_pass_clear past the last pass");
+                } finally {
+                    overlay.free();
+                }
+            });
+        },
+    },
+    {
+        label: "Dirty Rendering",
+        slug: slugify("Dirty Rendering"),
+        async run() {
+            await withBatchedRenderer("Dirty Rendering", async ({ renderer, composer }) => {
+                const mesh = renderer.register_mesh(buildSingleTriangle());
+                composer.render();
+                if (composer.render_if_dirty()) {
+                    throw new Error("an unchanged scene should not re-render");
+                }
+                renderer.create_instance(mesh, identityMatrix());
+                if (!composer.render_if_dirty()) {
+                    throw new Error("creating an instance should mark the pass dirty");
+                }
+                if (composer.render_if_dirty()) {
+                    throw new Error("render_if_dirty should clear the dirty flags");
+                }
+                composer.set_clear_color(0.1, 0.1, 0.1, 1);
+                if (!composer.render_if_dirty()) {
+                    throw new Error("composer settings should mark the frame dirty");
+                }
+            });
+        },
+    },
 ];
 
 runAllTests().catch((err) => {
@@ -345,6 +831,15 @@ async function runSharedCanvasTest() {
     }
 }
 
+function expectThrows(fn, what) {
+    try {
+        fn();
+    } catch (err) {
+        return err;
+    }
+    throw new Error(`expected ${what} to throw`);
+}
+
 function getInitialFilterKey() {
     const params = new URLSearchParams(window.location.search);
     const raw = params.get("test");